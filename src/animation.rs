@@ -0,0 +1,144 @@
+use crate::{sprite::Sprite, Context};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    // Stops on the last frame
+    Once,
+}
+
+// Cycles through atlas frames, each shown for frame_duration seconds
+#[derive(Clone, Debug)]
+pub struct Animation {
+    frames: Vec<[f32; 4]>,
+    frame_duration: f32,
+    mode: PlayMode,
+    // f64 so tiny dts still add up over a long animation
+    elapsed: f64,
+}
+
+impl Animation {
+    // frames are uv rects as taken by Sprite::set_uv_rect, there has to be at least one
+    pub fn new(frames: Vec<[f32; 4]>, frame_duration: f32, mode: PlayMode) -> Self {
+        assert!(!frames.is_empty(), "an animation needs at least one frame");
+        Self {
+            frames,
+            frame_duration: frame_duration.max(f32::EPSILON),
+            mode,
+            elapsed: 0.0,
+        }
+    }
+
+    // Returns whether the current frame changed
+    pub fn update(&mut self, dt: f32) -> bool {
+        let before = self.current_frame();
+        self.elapsed += f64::from(dt);
+        if self.mode == PlayMode::Loop {
+            // Wrapped so the float doesn't lose precision over a long session
+            self.elapsed %= self.total_duration();
+        }
+        self.current_frame() != before
+    }
+
+    pub fn current_frame(&self) -> usize {
+        let frame = (self.elapsed / f64::from(self.frame_duration)) as usize;
+        match self.mode {
+            PlayMode::Loop => frame % self.frames.len(),
+            PlayMode::Once => frame.min(self.frames.len() - 1),
+        }
+    }
+
+    pub fn uv_rect(&self) -> [f32; 4] {
+        self.frames[self.current_frame()]
+    }
+
+    // Looping animations never finish
+    pub fn is_finished(&self) -> bool {
+        self.mode == PlayMode::Once && self.elapsed >= self.total_duration()
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    fn total_duration(&self) -> f64 {
+        f64::from(self.frame_duration) * self.frames.len() as f64
+    }
+}
+
+pub struct AnimatedSprite {
+    sprite: Sprite,
+    animation: Animation,
+}
+
+impl AnimatedSprite {
+    pub fn new(mut sprite: Sprite, animation: Animation, context: &Context) -> Self {
+        sprite.set_uv_rect(animation.uv_rect(), context);
+        Self { sprite, animation }
+    }
+
+    // The uv buffer is only rewritten when the frame actually changes, a finished
+    // one-shot animation costs nothing
+    pub fn update(&mut self, context: &Context, dt: f32) {
+        if self.animation.is_finished() {
+            return;
+        }
+        if self.animation.update(dt) {
+            self.sprite.set_uv_rect(self.animation.uv_rect(), context);
+        }
+    }
+
+    // Back to the first frame, replays a one-shot animation
+    pub fn restart(&mut self, context: &Context) {
+        self.animation.restart();
+        self.sprite.set_uv_rect(self.animation.uv_rect(), context);
+    }
+
+    pub fn get_sprite(&self) -> &Sprite {
+        &self.sprite
+    }
+
+    pub fn get_sprite_mut(&mut self) -> &mut Sprite {
+        &mut self.sprite
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(count: usize) -> Vec<[f32; 4]> {
+        (0..count)
+            .map(|i| [i as f32 / count as f32, 0.0, 1.0 / count as f32, 1.0])
+            .collect()
+    }
+
+    #[test]
+    fn looping_wraps_around_to_the_first_frame() {
+        let mut animation = Animation::new(frames(3), 0.25, PlayMode::Loop);
+        let mut seen = vec![animation.current_frame()];
+        // 0.1s steps land at 0.1, 0.2, ... 1.0 seconds
+        for _ in 0..10 {
+            animation.update(0.1);
+            seen.push(animation.current_frame());
+        }
+        assert_eq!(seen, [0, 0, 0, 1, 1, 2, 2, 2, 0, 0, 1]);
+        assert!(!animation.is_finished());
+    }
+
+    #[test]
+    fn one_shots_stop_on_the_last_frame() {
+        let mut animation = Animation::new(frames(2), 0.5, PlayMode::Once);
+        assert!(!animation.update(0.25));
+        assert!(animation.update(0.5));
+        assert_eq!(animation.current_frame(), 1);
+
+        animation.update(10.0);
+        assert_eq!(animation.current_frame(), 1);
+        assert!(animation.is_finished());
+
+        animation.restart();
+        assert_eq!(animation.uv_rect(), frames(2)[0]);
+        assert!(!animation.is_finished());
+    }
+}
@@ -0,0 +1,60 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const ASSETS_ENV: &str = "VADERS_ASSETS";
+const ASSETS_DIR: &str = "assets";
+
+// Compiled in so the game starts regardless of the working directory
+pub const PLAYER_PNG: &[u8] = include_bytes!("../player.png");
+#[cfg(feature = "audio")]
+pub const SHOOT_WAV: &[u8] = include_bytes!("../shoot.wav");
+#[cfg(feature = "audio")]
+pub const EXPLOSION_WAV: &[u8] = include_bytes!("../explosion.wav");
+
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn resolve(name: impl AsRef<Path>) -> PathBuf {
+    root().join(name)
+}
+
+fn root() -> &'static Path {
+    ROOT.get_or_init(|| {
+        let candidates = [
+            env::var_os(ASSETS_ENV).map(PathBuf::from),
+            env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(|dir| dir.join(ASSETS_DIR))),
+            env::current_dir().ok(),
+        ];
+        let root =
+            first_existing(candidates.into_iter().flatten()).unwrap_or_else(|| PathBuf::from("."));
+        eprintln!("Loading assets from {}", root.display());
+        root
+    })
+}
+
+// Candidates are checked in priority order: env override, next to the executable, CWD
+fn first_existing(candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    candidates.into_iter().find(|dir| dir.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_existing_override_wins() {
+        let override_dir = env::temp_dir();
+        let found = first_existing([override_dir.clone(), env::current_dir().unwrap()]);
+        assert_eq!(found, Some(override_dir));
+    }
+
+    #[test]
+    fn a_missing_override_falls_through() {
+        let missing = env::temp_dir().join("vaders-no-such-assets");
+        let cwd = env::current_dir().unwrap();
+        assert_eq!(first_existing([missing.clone(), cwd.clone()]), Some(cwd));
+        assert_eq!(first_existing([missing]), None);
+    }
+}
@@ -0,0 +1,59 @@
+// Where each sprite sits in its spritesheet, in pixels from the image's top
+// left as an image editor shows it. Sprites take their world size from the
+// entry, one unit per pixel, so resized art doesn't need code changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasEntry {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// All of player.png
+pub const PLAYER_SHIP: AtlasEntry = AtlasEntry {
+    x: 0,
+    y: 0,
+    width: 13,
+    height: 8,
+};
+
+impl AtlasEntry {
+    pub fn size(&self) -> nalgebra_glm::Vec2 {
+        nalgebra_glm::vec2(self.width as f32, self.height as f32)
+    }
+
+    // As taken by Sprite::set_uv_rect. Textures are flipped on load, so v
+    // counts from the image's bottom row.
+    pub fn uv_rect(&self, image_width: u32, image_height: u32) -> [f32; 4] {
+        let (image_width, image_height) = (image_width as f32, image_height as f32);
+        let bottom = image_height - (self.y + self.height) as f32;
+        [
+            self.x as f32 / image_width,
+            bottom / image_height,
+            self.width as f32 / image_width,
+            self.height as f32 / image_height,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_whole_image_is_the_full_uv_rect() {
+        assert_eq!(PLAYER_SHIP.uv_rect(13, 8), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(PLAYER_SHIP.size(), nalgebra_glm::vec2(13.0, 8.0));
+    }
+
+    #[test]
+    fn the_top_right_quarter_is_at_the_top_of_the_uv_rect() {
+        let entry = AtlasEntry {
+            x: 8,
+            y: 0,
+            width: 8,
+            height: 4,
+        };
+        assert_eq!(entry.uv_rect(16, 8), [0.5, 0.5, 0.5, 0.5]);
+    }
+}
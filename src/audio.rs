@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use rodio::source::Buffered;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+
+use crate::assets;
+use crate::event::{Events, GameEvent};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundId {
+    Shoot,
+    Explosion,
+}
+
+impl SoundId {
+    const ALL: [SoundId; 2] = [SoundId::Shoot, SoundId::Explosion];
+
+    // Loud enough to duck the music
+    fn is_loud(self) -> bool {
+        match self {
+            SoundId::Shoot => false,
+            SoundId::Explosion => true,
+        }
+    }
+
+    // Bundled with the binary, like the player sprite
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            SoundId::Shoot => assets::SHOOT_WAV,
+            SoundId::Explosion => assets::EXPLOSION_WAV,
+        }
+    }
+}
+
+// Each sound at most once per update, ten kills in a frame are one explosion
+pub fn sounds_for(events: &Events) -> Vec<SoundId> {
+    let mut sounds = Vec::new();
+    for event in events.iter() {
+        let sound = match event {
+            GameEvent::InvaderKilled { .. } | GameEvent::PlayerHit { .. } => SoundId::Explosion,
+            GameEvent::WaveCleared
+            | GameEvent::BonusAwarded { .. }
+            | GameEvent::PowerUpCollected { .. } => continue,
+        };
+        if !sounds.contains(&sound) {
+            sounds.push(sound);
+        }
+    }
+    sounds
+}
+
+// Looped under the effects when the file exists, optional
+const MUSIC_FILE: &str = "music.ogg";
+const MUSIC_VOLUME: f32 = 0.5;
+
+// Lowers the music while a loud effect plays
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ducking {
+    // Fraction of the music volume taken away, 0 to 1
+    pub amount: f32,
+    // Seconds to fade back to full volume
+    pub recovery: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AudioOptions {
+    // None by default, the music never ducks
+    pub ducking: Option<Ducking>,
+}
+
+// Gain on the music, dropped by a duck and ramped linearly back to 1
+#[derive(Debug)]
+struct DuckEnvelope {
+    ducking: Option<Ducking>,
+    gain: f32,
+}
+
+impl DuckEnvelope {
+    fn new(ducking: Option<Ducking>) -> Self {
+        Self { ducking, gain: 1.0 }
+    }
+
+    fn duck(&mut self) {
+        if let Some(ducking) = self.ducking {
+            self.gain = self.gain.min(1.0 - ducking.amount.clamp(0.0, 1.0));
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        let Some(ducking) = self.ducking else {
+            return;
+        };
+        if ducking.recovery <= 0.0 {
+            self.gain = 1.0;
+            return;
+        }
+        let rate = ducking.amount.clamp(0.0, 1.0) / ducking.recovery;
+        self.gain = (self.gain + rate * dt).min(1.0);
+    }
+}
+
+type Sound = Buffered<Decoder<Cursor<Vec<u8>>>>;
+
+// Short effects decoded into memory at startup. Every play gets its own voice
+// in rodio's mixer, so rapid repeats overlap rather than cut each other off.
+pub struct Audio {
+    // None without an output device, playing is then a no-op. The stream has to
+    // outlive the handle or playback stops.
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    sounds: HashMap<SoundId, Sound>,
+    // None without an output device or music file
+    music: Option<Sink>,
+    envelope: DuckEnvelope,
+    // From Settings, master already applied
+    sfx_volume: f32,
+    music_volume: f32,
+}
+
+impl Audio {
+    pub fn new(options: AudioOptions) -> Self {
+        let output = match OutputStream::try_default() {
+            Ok(output) => Some(output),
+            Err(err) => {
+                eprintln!("No audio output, sound is disabled: {err}");
+                None
+            }
+        };
+        Self::with_output(output, options)
+    }
+
+    fn with_output(
+        output: Option<(OutputStream, OutputStreamHandle)>,
+        options: AudioOptions,
+    ) -> Self {
+        let mut sounds = HashMap::new();
+        if output.is_some() {
+            for id in SoundId::ALL {
+                if let Some(sound) = load_sound(id) {
+                    sounds.insert(id, sound);
+                }
+            }
+        }
+
+        let music = output.as_ref().and_then(|(_, handle)| start_music(handle));
+
+        Self {
+            output,
+            sounds,
+            music,
+            envelope: DuckEnvelope::new(options.ducking),
+            sfx_volume: 1.0,
+            music_volume: 1.0,
+        }
+    }
+
+    // Effects already playing finish at the old volume, the music changes at
+    // the next update
+    pub fn set_volumes(&mut self, sfx: f32, music: f32) {
+        self.sfx_volume = sfx;
+        self.music_volume = music;
+    }
+
+    // Once per fixed step, fades the music back after a duck
+    pub fn update(&mut self, dt: f32) {
+        self.envelope.update(dt);
+        if let Some(music) = self.music.as_ref() {
+            music.set_volume(self.music_gain());
+        }
+    }
+
+    fn music_gain(&self) -> f32 {
+        MUSIC_VOLUME * self.music_volume * self.envelope.gain
+    }
+
+    pub fn observe(&mut self, events: &Events) {
+        for id in sounds_for(events) {
+            self.play(id);
+        }
+    }
+
+    pub fn play(&mut self, id: SoundId) {
+        let (Some((_, handle)), Some(sound)) = (self.output.as_ref(), self.sounds.get(&id)) else {
+            return;
+        };
+        if id.is_loud() {
+            self.envelope.duck();
+        }
+        // Cloning a buffered source shares the decoded samples
+        let sound = sound.clone().amplify(self.sfx_volume);
+        if let Err(err) = handle.play_raw(sound.convert_samples()) {
+            eprintln!("Could not play {id:?}: {err}");
+        }
+    }
+}
+
+// A broken clip only silences that sound
+fn load_sound(id: SoundId) -> Option<Sound> {
+    decode(&format!("{id:?}"), id.bytes().to_vec()).map(Source::buffered)
+}
+
+// Plays the music on its own sink, looped for as long as the game runs
+fn start_music(handle: &OutputStreamHandle) -> Option<Sink> {
+    if !assets::resolve(MUSIC_FILE).exists() {
+        return None;
+    }
+    let path = assets::resolve(MUSIC_FILE);
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("Could not read music {}: {err}", path.display());
+            return None;
+        }
+    };
+    let music = decode(&path.display().to_string(), bytes)?;
+    let sink = match Sink::try_new(handle) {
+        Ok(sink) => sink,
+        Err(err) => {
+            eprintln!("Could not play music: {err}");
+            return None;
+        }
+    };
+    sink.set_volume(MUSIC_VOLUME);
+    sink.append(music.repeat_infinite());
+    Some(sink)
+}
+
+// WAV or OGG, the format is detected from the contents
+fn decode(name: &str, bytes: Vec<u8>) -> Option<Decoder<Cursor<Vec<u8>>>> {
+    match Decoder::new(Cursor::new(bytes)) {
+        Ok(decoder) => Some(decoder),
+        Err(err) => {
+            eprintln!("Could not decode sound {name}: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_loud_effect_ducks_the_music_until_it_recovers() {
+        let mut envelope = DuckEnvelope::new(Some(Ducking {
+            amount: 0.6,
+            recovery: 0.5,
+        }));
+        envelope.duck();
+        assert!((envelope.gain - 0.4).abs() < 1e-6);
+
+        envelope.update(0.25);
+        assert!((envelope.gain - 0.7).abs() < 1e-6);
+        envelope.update(0.25);
+        assert!((envelope.gain - 1.0).abs() < 1e-6);
+        envelope.update(0.25);
+        assert_eq!(envelope.gain, 1.0);
+    }
+
+    #[test]
+    fn ducking_is_off_by_default() {
+        let mut envelope = DuckEnvelope::new(AudioOptions::default().ducking);
+        envelope.duck();
+        assert_eq!(envelope.gain, 1.0);
+    }
+
+    #[test]
+    fn bundled_clips_decode() {
+        for id in SoundId::ALL {
+            let sound = load_sound(id).unwrap();
+            assert!(sound.sample_rate() > 0);
+            assert!(sound.count() > 0, "{id:?} has no samples");
+        }
+    }
+
+    #[test]
+    fn playing_without_an_output_device_is_a_no_op() {
+        let mut audio = Audio::with_output(
+            None,
+            AudioOptions {
+                ducking: Some(Ducking {
+                    amount: 0.5,
+                    recovery: 1.0,
+                }),
+            },
+        );
+        assert!(audio.sounds.is_empty());
+        assert!(audio.music.is_none());
+        audio.play(SoundId::Explosion);
+        audio.update(0.1);
+        // Nothing played, so nothing ducked
+        assert_eq!(audio.envelope.gain, 1.0);
+    }
+
+    #[test]
+    fn the_music_plays_at_its_channel_volume() {
+        let mut audio = Audio::with_output(None, AudioOptions::default());
+        assert_eq!(audio.music_gain(), MUSIC_VOLUME);
+        audio.set_volumes(1.0, 0.5);
+        assert_eq!(audio.music_gain(), MUSIC_VOLUME * 0.5);
+    }
+}
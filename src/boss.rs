@@ -0,0 +1,239 @@
+use crate::bullet::Bullet;
+use crate::collision;
+use crate::enemy::{FireCooldown, Health};
+use crate::rect::Rect;
+use crate::renderer::Renderer;
+use crate::texture::SolidTexture;
+use crate::{sprite::Sprite, Context};
+
+// Every third wave is a boss instead of a formation
+const BOSS_WAVE_EVERY: u32 = 3;
+// On top of the points for each part, for finishing the last one
+pub const BOSS_BONUS: u32 = 1000;
+
+// Offset from the boss's origin, size and health of each part. The core sits
+// between two wings, all of them solid shapes until there's boss art.
+const PARTS: [([f32; 2], [f32; 2], u32); 3] = [
+    ([-12.0, 0.0], [24.0, 12.0], 8),
+    ([-24.0, 2.0], [10.0, 8.0], 4),
+    ([14.0, 2.0], [10.0, 8.0], 4),
+];
+const TINT: [f32; 4] = [0.9, 0.3, 0.9, 1.0];
+// Gap between the top of the boss and the top of the play area
+const TOP_MARGIN: f32 = 20.0;
+// A figure of eight, SWAY across and BOB up and down, in world units
+const SWAY: f32 = 70.0;
+const BOB: f32 = 6.0;
+// In radians per second of the sideways sway, the bob runs twice as fast
+const SWAY_SPEED: f32 = 0.8;
+
+// Seconds between volleys, which alternate between a fan from the core and
+// one straight shot from each wing that's left
+const FIRE_INTERVAL: f64 = 1.0;
+const FAN_ANGLES: [f32; 5] = [-0.4, -0.2, 0.0, 0.2, 0.4];
+const BULLET_SIZE: (f32, f32) = (1.0, 4.0);
+// In world units per second
+const BULLET_SPEED: f32 = 60.0;
+const BULLET_TINT: [f32; 4] = [1.0, 0.4, 1.0, 1.0];
+
+pub fn is_boss_wave(wave: u32) -> bool {
+    wave.is_multiple_of(BOSS_WAVE_EVERY)
+}
+
+struct Part {
+    sprite: Sprite,
+    offset: nalgebra_glm::Vec2,
+    health: Health,
+    // Only the core fires the fan
+    core: bool,
+}
+
+// A large enemy of several parts, each hit and killed on its own. It's beaten
+// once no part is left.
+pub struct Boss {
+    parts: Vec<Part>,
+    // Center of the core's bottom edge at rest
+    home: nalgebra_glm::Vec2,
+    time: f32,
+    fire_cooldown: FireCooldown,
+    volleys: u32,
+    texture: SolidTexture,
+}
+
+impl Boss {
+    pub fn new(texture: SolidTexture, area: Rect, context: &Context, renderer: &Renderer) -> Self {
+        let top = area.pos.y + area.size.y - TOP_MARGIN;
+        let height = PARTS[0].1[1];
+        let home = nalgebra_glm::vec2(area.pos.x + area.size.x / 2.0, top - height - BOB);
+        let parts = PARTS
+            .iter()
+            .enumerate()
+            .map(|(i, &(offset, size, health))| {
+                let offset = nalgebra_glm::vec2(offset[0], offset[1]);
+                let mut sprite = Sprite::new(
+                    home + offset,
+                    nalgebra_glm::vec2(size[0], size[1]),
+                    &texture.texture,
+                    renderer.sprite_layouts(),
+                    &texture.sampler,
+                    context,
+                );
+                sprite.set_tint(TINT, context);
+                Part {
+                    sprite,
+                    offset,
+                    health: Health::new(health),
+                    core: i == 0,
+                }
+            })
+            .collect();
+        Self {
+            parts,
+            home,
+            time: 0.0,
+            fire_cooldown: FireCooldown::new(FIRE_INTERVAL),
+            volleys: 0,
+            texture,
+        }
+    }
+
+    pub fn update(&mut self, context: &Context, dt: f32) {
+        self.time += dt;
+        let origin = self.home + sway(self.time);
+        for part in &mut self.parts {
+            part.sprite.set_position(origin + part.offset, context);
+            if part.health.update(dt) {
+                part.sprite
+                    .set_tint_effect(part.health.tint_effect(), context);
+            }
+        }
+    }
+
+    pub fn try_fire(&mut self, context: &Context, renderer: &Renderer, dt: f32) -> Vec<Bullet> {
+        if !self.fire_cooldown.update(dt) || self.parts.is_empty() {
+            return Vec::new();
+        }
+        let fan = self.volleys.is_multiple_of(2);
+        self.volleys += 1;
+        let shots: Vec<(nalgebra_glm::Vec2, f32)> = self
+            .parts
+            .iter()
+            .filter(|part| part.core == fan)
+            .flat_map(|part| {
+                let rect = part.sprite.rect();
+                let muzzle = rect.pos + nalgebra_glm::vec2(rect.size.x / 2.0, 0.0);
+                let angles: &[f32] = if fan { &FAN_ANGLES } else { &[0.0] };
+                angles.iter().map(move |&angle| (muzzle, angle))
+            })
+            .collect();
+        shots
+            .into_iter()
+            .map(|(muzzle, angle)| self.bullet(muzzle, angle, context, renderer))
+            .collect()
+    }
+
+    // angle is from straight down
+    fn bullet(
+        &self,
+        muzzle: nalgebra_glm::Vec2,
+        angle: f32,
+        context: &Context,
+        renderer: &Renderer,
+    ) -> Bullet {
+        let size = nalgebra_glm::vec2(BULLET_SIZE.0, BULLET_SIZE.1);
+        let velocity = nalgebra_glm::vec2(angle.sin(), -angle.cos()) * BULLET_SPEED;
+        let mut sprite = Sprite::new(
+            muzzle - nalgebra_glm::vec2(size.x / 2.0, size.y),
+            size,
+            &self.texture.texture,
+            renderer.sprite_layouts(),
+            &self.texture.sampler,
+            context,
+        );
+        sprite.set_tint(BULLET_TINT, context);
+        sprite.set_rotation(angle, context);
+        Bullet::new(sprite, velocity, context)
+    }
+
+    // Indices are positions in sprites(), returns the parts out of health
+    pub fn damage(&mut self, indices: &[usize], context: &Context) -> Vec<usize> {
+        let killed = hit_parts(self.parts.iter_mut().map(|part| &mut part.health), indices);
+        for (i, part) in self.parts.iter_mut().enumerate() {
+            if !killed.contains(&i) {
+                part.sprite
+                    .set_tint_effect(part.health.tint_effect(), context);
+            }
+        }
+        killed
+    }
+
+    pub fn destroy(&mut self, indices: &[usize]) {
+        collision::remove_indices(&mut self.parts, indices);
+    }
+
+    pub fn is_beaten(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn sprites(&self) -> Vec<&Sprite> {
+        self.parts.iter().map(|part| &part.sprite).collect()
+    }
+}
+
+// Where the figure of eight has the boss time seconds in, relative to home
+fn sway(time: f32) -> nalgebra_glm::Vec2 {
+    let angle = time * SWAY_SPEED;
+    nalgebra_glm::vec2(SWAY / 2.0 * angle.sin(), BOB * (2.0 * angle).sin())
+}
+
+// Hits the parts at indices once each, returns the ones this finished
+fn hit_parts<'a>(health: impl Iterator<Item = &'a mut Health>, indices: &[usize]) -> Vec<usize> {
+    let mut health: Vec<&mut Health> = health.collect();
+    let mut killed = Vec::new();
+    for &index in indices {
+        if health[index].hit() {
+            killed.push(index);
+        }
+    }
+    killed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part_health() -> Vec<Health> {
+        PARTS
+            .iter()
+            .map(|&(_, _, health)| Health::new(health))
+            .collect()
+    }
+
+    #[test]
+    fn a_part_dies_on_its_own_health() {
+        let mut health = part_health();
+        let wing = PARTS[1].2 as usize;
+        for _ in 1..wing {
+            assert!(hit_parts(health.iter_mut(), &[1]).is_empty());
+        }
+        assert_eq!(hit_parts(health.iter_mut(), &[1]), vec![1]);
+        // The core and the other wing are untouched
+        assert!(hit_parts(health.iter_mut(), &[0, 2]).is_empty());
+        // Finished parts don't die twice
+        assert!(hit_parts(health.iter_mut(), &[1]).is_empty());
+    }
+
+    #[test]
+    fn every_third_wave_is_a_boss() {
+        let bosses: Vec<u32> = (1..=9).filter(|&wave| is_boss_wave(wave)).collect();
+        assert_eq!(bosses, vec![3, 6, 9]);
+    }
+
+    #[test]
+    fn the_sway_returns_home_each_cycle() {
+        let cycle = std::f32::consts::TAU / SWAY_SPEED;
+        assert!(nalgebra_glm::length(&sway(cycle)) < 1e-4);
+        let quarter = sway(cycle / 4.0);
+        assert!((quarter.x - SWAY / 2.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::collision::{self, Swept};
+use crate::rect::Rect;
+use crate::{sprite::Sprite, Context};
+
+// In front of the invaders and the ship at z 0, so shots crossing them stay visible
+const BULLET_Z: f32 = 0.5;
+
+pub struct Bullet {
+    sprite: Sprite,
+    // In world units per second
+    velocity: nalgebra_glm::Vec2,
+    // Position before the last update, for swept collision checks
+    prev_pos: nalgebra_glm::Vec2,
+}
+impl Bullet {
+    pub fn new(mut sprite: Sprite, velocity: nalgebra_glm::Vec2, context: &Context) -> Self {
+        sprite.set_z(BULLET_Z, context);
+        let prev_pos = sprite.position();
+        Self {
+            sprite,
+            velocity,
+            prev_pos,
+        }
+    }
+
+    pub fn update(&mut self, context: &Context, dt: f32) {
+        self.prev_pos = self.sprite.position();
+        self.sprite.move_by(&(self.velocity * dt), context);
+    }
+
+    // Its box now and where it moved from during the last update
+    pub fn swept(&self) -> Swept {
+        Swept {
+            rect: self.sprite.rect(),
+            prev_pos: self.prev_pos,
+        }
+    }
+
+    // Past the top of area for the player's bullets, below the bottom for the
+    // enemies'
+    pub fn has_left(&self, area: Rect) -> bool {
+        let y = self.sprite.position().y;
+        y > area.pos.y + area.size.y || y + self.sprite.size().y < area.pos.y
+    }
+
+    pub fn get_sprite(&self) -> &Sprite {
+        &self.sprite
+    }
+}
+
+// Owns every bullet in flight and drops them once they leave the play area
+#[derive(Default)]
+pub struct Bullets {
+    bullets: Vec<Bullet>,
+}
+impl Bullets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bullet: Bullet) {
+        self.bullets.push(bullet);
+    }
+
+    pub fn update(&mut self, context: &Context, area: Rect, dt: f32) {
+        for bullet in &mut self.bullets {
+            bullet.update(context, dt);
+        }
+        self.bullets.retain(|bullet| !bullet.has_left(area));
+    }
+
+    pub fn clear(&mut self) {
+        self.bullets.clear();
+    }
+
+    // Indices are positions in sprites()
+    pub fn destroy(&mut self, indices: &[usize]) {
+        collision::remove_indices(&mut self.bullets, indices);
+    }
+
+    pub fn swept(&self) -> Vec<Swept> {
+        self.bullets.iter().map(Bullet::swept).collect()
+    }
+
+    pub fn sprites(&self) -> Vec<&Sprite> {
+        self.bullets.iter().map(Bullet::get_sprite).collect()
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::{Duration, Instant};
+
+pub const FIXED_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// Past this many steps in one frame the rest of the backlog is dropped, catching
+// up on a long stall would only make the next frame slower
+pub const MAX_CATCHUP_STEPS: u32 = 5;
+
+// Accumulates real time and hands it out in whole fixed steps, so the
+// simulation runs at the same speed regardless of the frame rate
+pub struct Clock {
+    last: Instant,
+    accumulator: Duration,
+    step: Duration,
+    max_catchup_steps: u32,
+    paused: bool,
+}
+
+impl Clock {
+    // max_catchup_steps is at least one, a frame always gets to advance the simulation
+    pub fn new(step: Duration, max_catchup_steps: u32) -> Self {
+        Self {
+            last: Instant::now(),
+            accumulator: Duration::ZERO,
+            step,
+            max_catchup_steps: max_catchup_steps.max(1),
+            paused: false,
+        }
+    }
+
+    // Starts counting from now, e.g. once loading is done so it isn't replayed
+    pub fn reset(&mut self) {
+        self.last = Instant::now();
+        self.accumulator = Duration::ZERO;
+    }
+
+    // Time passing while paused is dropped rather than banked, so resuming
+    // doesn't replay the pause as a burst of updates
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    // The next tick only counts time from here
+    pub fn resume(&mut self) {
+        self.resume_at(Instant::now());
+    }
+
+    fn resume_at(&mut self, now: Instant) {
+        if self.paused {
+            self.paused = false;
+            self.last = now;
+        }
+    }
+
+    // Real time since the last call, zero while paused. Feed it to advance.
+    pub fn elapsed(&mut self) -> Duration {
+        self.elapsed_at(Instant::now())
+    }
+
+    fn elapsed_at(&mut self, now: Instant) -> Duration {
+        let elapsed = now - self.last;
+        self.last = now;
+        if self.paused {
+            return Duration::ZERO;
+        }
+        elapsed
+    }
+
+    // Leftover time smaller than a step carries over to the next frame
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_catchup_steps {
+                eprintln!(
+                    "Slow frame, dropping {:.1}ms of simulation",
+                    self.accumulator.as_secs_f64() * 1000.0
+                );
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    pub fn dt(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+}
+
+// Absorbs rounding in the step length, a second isn't a whole number of
+// FIXED_STEPs once they're truncated to nanoseconds
+pub const TIMER_EPSILON: f64 = 1e-6;
+
+// Counts down in f64 seconds, summing thousands of tiny f32 steps would drift.
+// The simulation still hands out f32 dts, they're widened here.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timer {
+    left: f64,
+}
+
+impl Timer {
+    pub fn start(&mut self, seconds: f64) {
+        self.left = seconds;
+    }
+
+    // Only returns true on the update that runs the timer out
+    pub fn update(&mut self, dt: f32) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        self.left -= f64::from(dt);
+        !self.is_running()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.left > TIMER_EPSILON
+    }
+
+    // Seconds until it runs out, zero once it has
+    pub fn left(&self) -> f64 {
+        if self.is_running() {
+            self.left
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_frame_runs_whole_steps_and_carries_the_rest() {
+        let mut clock = Clock::new(FIXED_STEP, MAX_CATCHUP_STEPS);
+        assert_eq!(clock.advance(Duration::from_millis(50)), 3);
+        assert_eq!(
+            clock.accumulator,
+            Duration::from_millis(50) - 3 * FIXED_STEP
+        );
+
+        assert_eq!(clock.advance(FIXED_STEP - clock.accumulator), 1);
+        assert_eq!(clock.accumulator, Duration::ZERO);
+    }
+
+    #[test]
+    fn stall_only_runs_max_catchup_steps() {
+        let mut clock = Clock::new(FIXED_STEP, 8);
+        assert_eq!(clock.advance(Duration::from_secs(2)), 8);
+        // The excess is dropped, not spread over the following frames
+        assert_eq!(clock.accumulator, Duration::ZERO);
+        assert_eq!(clock.advance(FIXED_STEP), 1);
+    }
+
+    #[test]
+    fn paused_time_is_not_banked() {
+        let mut clock = Clock::new(FIXED_STEP, MAX_CATCHUP_STEPS);
+        let start = clock.last;
+
+        clock.pause();
+        let elapsed = clock.elapsed_at(start + Duration::from_secs(3));
+        assert_eq!(clock.advance(elapsed), 0);
+        clock.resume_at(start + Duration::from_secs(3));
+        let elapsed = clock.elapsed_at(start + Duration::from_secs(3) + FIXED_STEP);
+        assert_eq!(clock.advance(elapsed), 1);
+        assert_eq!(clock.accumulator, Duration::ZERO);
+    }
+
+    #[test]
+    fn resume_without_pause_keeps_counting() {
+        let mut clock = Clock::new(FIXED_STEP, MAX_CATCHUP_STEPS);
+        let start = clock.last;
+
+        clock.resume_at(start + FIXED_STEP);
+        let elapsed = clock.elapsed_at(start + 2 * FIXED_STEP);
+        assert_eq!(clock.advance(elapsed), 2);
+    }
+
+    #[test]
+    fn timer_fires_once_over_sub_millisecond_frames() {
+        let frame = Duration::from_micros(250);
+        let mut clock = Clock::new(FIXED_STEP, MAX_CATCHUP_STEPS);
+        let mut timer = Timer::default();
+        timer.start(1.0);
+
+        let mut fired_at = Vec::new();
+        for i in 1..=12_000 {
+            for _ in 0..clock.advance(frame) {
+                if timer.update(clock.dt()) {
+                    fired_at.push(frame * i);
+                }
+            }
+        }
+
+        assert_eq!(fired_at.len(), 1);
+        let error = fired_at[0].as_secs_f64() - 1.0;
+        assert!(
+            error.abs() < frame.as_secs_f64(),
+            "fired at {:?}",
+            fired_at[0]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use crate::{rect::Rect, WORLD_HEIGHT, WORLD_WIDTH};
+
+// Roughly an enemy plus its spacing, so most sprites land in one or two cells
+pub const DEFAULT_CELL_SIZE: f32 = 16.0;
+
+// Whether two axis aligned boxes overlap, boxes that only touch don't count
+pub fn overlaps(
+    a_pos: nalgebra_glm::Vec2,
+    a_size: nalgebra_glm::Vec2,
+    b_pos: nalgebra_glm::Vec2,
+    b_size: nalgebra_glm::Vec2,
+) -> bool {
+    a_pos.x < b_pos.x + b_size.x
+        && b_pos.x < a_pos.x + a_size.x
+        && a_pos.y < b_pos.y + b_size.y
+        && b_pos.y < a_pos.y + a_size.y
+}
+
+// A projectile's box at the end of a step, prev_pos is where it started the step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Swept {
+    pub rect: Rect,
+    pub prev_pos: nalgebra_glm::Vec2,
+}
+
+impl Swept {
+    // Covers the box at both ends of the step
+    fn bounds(&self) -> Rect {
+        let min = nalgebra_glm::min2(&self.prev_pos, &self.rect.pos);
+        let max = nalgebra_glm::max2(&self.prev_pos, &self.rect.pos) + self.rect.size;
+        Rect {
+            pos: min,
+            size: max - min,
+        }
+    }
+}
+
+// When the projectile first overlaps the target while moving from prev_pos to its
+// current position, as a fraction of the step. Tests the path of its corner
+// against the target grown by the projectile's size, one axis at a time.
+fn sweep_time(target: &Rect, projectile: &Swept) -> Option<f32> {
+    let min = target.pos - projectile.rect.size;
+    let max = target.pos + target.size;
+    let start = projectile.prev_pos;
+    let travel = projectile.rect.pos - start;
+
+    let (mut enter, mut exit) = (f32::NEG_INFINITY, f32::INFINITY);
+    for axis in 0..2 {
+        if travel[axis] == 0.0 {
+            // Never moves along this axis, so it has to stay inside the whole step
+            if start[axis] <= min[axis] || start[axis] >= max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (min[axis] - start[axis]) / travel[axis];
+        let t1 = (max[axis] - start[axis]) / travel[axis];
+        enter = enter.max(t0.min(t1));
+        exit = exit.min(t0.max(t1));
+    }
+    // Strictly less, like overlaps boxes that only touch don't count
+    (enter < exit && enter <= 1.0 && exit > 0.0).then(|| enter.max(0.0))
+}
+
+// Buckets boxes into fixed size cells over the world so a query only has to
+// look at boxes in the cells it overlaps. A box spanning several cells is
+// stored in each of them, boxes outside the world are kept in the edge cells.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        let cols = (WORLD_WIDTH / cell_size).ceil().max(1.0) as usize;
+        let rows = (WORLD_HEIGHT / cell_size).ceil().max(1.0) as usize;
+        Self {
+            cell_size,
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, pos: nalgebra_glm::Vec2, size: nalgebra_glm::Vec2) {
+        let (cols, rows) = self.cell_range(pos, size);
+        for row in rows {
+            for col in cols.clone() {
+                self.cells[row * self.cols + col].push(index);
+            }
+        }
+    }
+
+    // Candidates only, each index once, callers still have to test the boxes
+    pub fn query(
+        &self,
+        pos: nalgebra_glm::Vec2,
+        size: nalgebra_glm::Vec2,
+    ) -> impl Iterator<Item = usize> {
+        let (cols, rows) = self.cell_range(pos, size);
+        let mut candidates = Vec::new();
+        for row in rows {
+            for col in cols.clone() {
+                candidates.extend_from_slice(&self.cells[row * self.cols + col]);
+            }
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates.into_iter()
+    }
+
+    // Inclusive on both ends, so a box whose edge sits exactly on a cell
+    // boundary is also put in the cell past it
+    fn cell_range(
+        &self,
+        pos: nalgebra_glm::Vec2,
+        size: nalgebra_glm::Vec2,
+    ) -> (
+        std::ops::RangeInclusive<usize>,
+        std::ops::RangeInclusive<usize>,
+    ) {
+        let cell =
+            |v: f32, count: usize| ((v / self.cell_size).floor().max(0.0) as usize).min(count - 1);
+        let max = pos + size;
+        (
+            cell(pos.x, self.cols)..=cell(max.x, self.cols),
+            cell(pos.y, self.rows)..=cell(max.y, self.rows),
+        )
+    }
+}
+
+// Pairs each projectile with the first target it hits, a target is only hit
+// once. Returns (target, projectile) indices into the given slices. A projectile
+// travelling further than a target's height in one step would skip over it, so
+// its whole path is tested against that target instead of just its end.
+pub fn find_hits(
+    grid: &mut SpatialGrid,
+    targets: &[Rect],
+    projectiles: &[Swept],
+) -> Vec<(usize, usize)> {
+    grid.clear();
+    for (i, target) in targets.iter().enumerate() {
+        grid.insert(i, target.pos, target.size);
+    }
+
+    let mut hits: Vec<(usize, usize)> = Vec::new();
+    for (j, projectile) in projectiles.iter().enumerate() {
+        let travel = nalgebra_glm::length(&(projectile.rect.pos - projectile.prev_pos));
+        let bounds = projectile.bounds();
+        let hit = grid
+            .query(bounds.pos, bounds.size)
+            .filter(|i| hits.iter().all(|(hit, _)| hit != i))
+            .filter_map(|i| {
+                let target = &targets[i];
+                let time = if travel > target.size.y {
+                    sweep_time(target, projectile)?
+                } else if overlaps(
+                    target.pos,
+                    target.size,
+                    projectile.rect.pos,
+                    projectile.rect.size,
+                ) {
+                    1.0
+                } else {
+                    return None;
+                };
+                Some((i, time))
+            })
+            // The earliest along the path, candidates come in index order for ties
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i);
+        if let Some(i) = hit {
+            hits.push((i, j));
+        }
+    }
+    hits
+}
+
+// Drops the items at the given indices, e.g. the ones find_hits reported, and
+// keeps the rest in order
+pub fn remove_indices<T>(items: &mut Vec<T>, indices: &[usize]) {
+    let mut index = 0;
+    items.retain(|_| {
+        let keep = !indices.contains(&index);
+        index += 1;
+        keep
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
+        Rect {
+            pos: nalgebra_glm::vec2(x, y),
+            size: nalgebra_glm::vec2(w, h),
+        }
+    }
+
+    fn at_rest(rect: Rect) -> Swept {
+        Swept {
+            rect,
+            prev_pos: rect.pos,
+        }
+    }
+
+    // Half the boxes snapped to cell boundaries, some spanning several cells or
+    // sticking out of the world
+    fn random_rect(rng: &mut fastrand::Rng, max_size: f32) -> Rect {
+        let mut coord = |range: f32, offset: f32| {
+            let v = rng.f32() * range + offset;
+            if rng.bool() {
+                (v / DEFAULT_CELL_SIZE).round() * DEFAULT_CELL_SIZE
+            } else {
+                v
+            }
+        };
+        let x = coord(WORLD_WIDTH + 20.0, -10.0);
+        let y = coord(WORLD_HEIGHT + 20.0, -10.0);
+        let w = coord(max_size, 0.0).max(0.5);
+        let h = coord(max_size, 0.0).max(0.5);
+        rect(x, y, w, h)
+    }
+
+    // Same rules as find_hits without the grid: the lowest target index that
+    // overlaps and wasn't already hit
+    fn brute_force_hits(targets: &[Rect], projectiles: &[Swept]) -> Vec<(usize, usize)> {
+        let mut hits: Vec<(usize, usize)> = Vec::new();
+        for (j, p) in projectiles.iter().map(|p| &p.rect).enumerate() {
+            let hit = (0..targets.len())
+                .filter(|i| hits.iter().all(|(hit, _)| hit != i))
+                .find(|&i| overlaps(targets[i].pos, targets[i].size, p.pos, p.size));
+            if let Some(i) = hit {
+                hits.push((i, j));
+            }
+        }
+        hits
+    }
+
+    #[test]
+    fn touching_boxes_do_not_overlap() {
+        let a = rect(0.0, 0.0, 16.0, 16.0);
+        let b = rect(16.0, 0.0, 16.0, 16.0);
+        assert!(!overlaps(a.pos, a.size, b.pos, b.size));
+        let c = rect(15.9, 15.9, 1.0, 1.0);
+        assert!(overlaps(a.pos, a.size, c.pos, c.size));
+    }
+
+    #[test]
+    fn box_on_a_cell_boundary_is_found_from_both_cells() {
+        let mut grid = SpatialGrid::new(DEFAULT_CELL_SIZE);
+        let target = rect(16.0, 16.0, 16.0, 16.0);
+        grid.insert(0, target.pos, target.size);
+
+        let left = rect(15.0, 20.0, 1.5, 1.0);
+        let above = rect(20.0, 31.5, 1.0, 4.0);
+        assert_eq!(grid.query(left.pos, left.size).collect::<Vec<_>>(), [0]);
+        assert_eq!(grid.query(above.pos, above.size).collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn grid_matches_brute_force_on_random_layouts() {
+        let mut rng = fastrand::Rng::with_seed(0x5EED);
+        let mut grid = SpatialGrid::new(DEFAULT_CELL_SIZE);
+        let mut total_hits = 0;
+        for _ in 0..200 {
+            let targets: Vec<Rect> = (0..rng.usize(0..60))
+                .map(|_| random_rect(&mut rng, 40.0))
+                .collect();
+            let projectiles: Vec<Swept> = (0..rng.usize(0..30))
+                .map(|_| at_rest(random_rect(&mut rng, 10.0)))
+                .collect();
+
+            let hits = find_hits(&mut grid, &targets, &projectiles);
+            assert_eq!(hits, brute_force_hits(&targets, &projectiles));
+            total_hits += hits.len();
+        }
+        // The layouts have to actually collide for the comparison to mean much
+        assert!(total_hits > 100, "only {total_hits} hits");
+    }
+
+    #[test]
+    fn fast_bullet_cannot_tunnel_through_a_thin_target() {
+        let mut grid = SpatialGrid::new(DEFAULT_CELL_SIZE);
+        let targets = [rect(100.0, 100.0, 11.0, 8.0)];
+        // Ends the step above the target having started below it
+        let bullet = Swept {
+            rect: rect(105.0, 112.0, 1.0, 4.0),
+            prev_pos: nalgebra_glm::vec2(105.0, 90.0),
+        };
+        assert!(!overlaps(
+            targets[0].pos,
+            targets[0].size,
+            bullet.rect.pos,
+            bullet.rect.size
+        ));
+
+        assert_eq!(find_hits(&mut grid, &targets, &[bullet]), [(0, 0)]);
+    }
+
+    #[test]
+    fn swept_path_beside_a_target_misses() {
+        let mut grid = SpatialGrid::new(DEFAULT_CELL_SIZE);
+        let targets = [rect(100.0, 100.0, 11.0, 8.0)];
+        let bullet = Swept {
+            rect: rect(120.0, 112.0, 1.0, 4.0),
+            prev_pos: nalgebra_glm::vec2(120.0, 90.0),
+        };
+        assert!(find_hits(&mut grid, &targets, &[bullet]).is_empty());
+    }
+
+    #[test]
+    fn sweep_hits_the_nearest_target_first() {
+        let mut grid = SpatialGrid::new(DEFAULT_CELL_SIZE);
+        let targets = [rect(100.0, 140.0, 11.0, 8.0), rect(100.0, 110.0, 11.0, 8.0)];
+        let bullet = Swept {
+            rect: rect(105.0, 160.0, 1.0, 4.0),
+            prev_pos: nalgebra_glm::vec2(105.0, 90.0),
+        };
+        assert_eq!(find_hits(&mut grid, &targets, &[bullet]), [(1, 0)]);
+    }
+
+    #[test]
+    fn removing_indices_keeps_the_rest_in_order() {
+        let mut items = vec!['a', 'b', 'c', 'd', 'e'];
+        remove_indices(&mut items, &[3, 0, 3]);
+        assert_eq!(items, ['b', 'c', 'e']);
+        remove_indices(&mut items, &[]);
+        assert_eq!(items, ['b', 'c', 'e']);
+    }
+}
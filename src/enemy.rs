@@ -0,0 +1,983 @@
+use std::sync::Arc;
+
+use crate::animation::{AnimatedSprite, Animation, PlayMode};
+use crate::atlas;
+use crate::bullet::Bullet;
+use crate::clock::{self, Timer};
+use crate::collision;
+use crate::rect::Rect;
+use crate::renderer::Renderer;
+use crate::texture::{SolidTexture, TextureError};
+use crate::{
+    assets, sprite::Sprite, texture_cache::TextureCache, Context, WORLD_HEIGHT, WORLD_WIDTH,
+};
+
+// Narrower than the ship it's drawn with
+const ENEMY_SIZE: (f32, f32) = (11.0, 8.0);
+// Distance between the origins of neighbouring enemies, in world units
+const SPACING: (f32, f32) = (16.0, 12.0);
+// Gap between the top row and the top of the play area
+const TOP_MARGIN: f32 = 4.0;
+// Rows at the top of the world kept for the score line
+const DEFAULT_HUD_MARGIN: f32 = 16.0;
+// In world units per second
+const MARCH_SPEED: f32 = 10.0;
+const DROP_DISTANCE: f32 = 4.0;
+// Slack for the float error of clamping the drop to the floor
+const LANDED_EPSILON: f32 = 1e-3;
+// There's no invader art yet, so reuse the player texture tinted green
+const TINT: [f32; 4] = [0.4, 1.0, 0.4, 1.0];
+// Reddens enemies that have taken a hit but are still alive
+const DAMAGE_TINT: [f32; 4] = [1.0, 0.5, 0.5, 1.0];
+// Seconds a surviving enemy shows DAMAGE_TINT after a hit
+const DAMAGE_FLASH: f64 = 0.15;
+const DEFAULT_HEALTH: u32 = 1;
+// Marching wiggle, the second frame pulls the wings in by a texel on each side
+const WIGGLE_FRAMES: [[f32; 4]; 2] = [[0.0, 0.0, 1.0, 1.0], [1.0 / 13.0, 0.0, 11.0 / 13.0, 1.0]];
+// Seconds per wiggle frame
+const WIGGLE_FRAME_DURATION: f32 = 0.4;
+
+// Seconds between enemy shots, for the whole formation
+const DEFAULT_FIRE_INTERVAL: f64 = 1.2;
+// Each wave fires this much more often than the last, down to the floor as a
+// fraction of FormationOptions::fire_interval
+const WAVE_FIRE_SPEEDUP: f64 = 0.9;
+const WAVE_FIRE_FLOOR: f64 = 0.4;
+const BULLET_SIZE: (f32, f32) = (1.0, 4.0);
+// In world units per second, downwards, on the first wave
+const BULLET_SPEED: f32 = 60.0;
+// Each wave's shots are this much faster than the last's, up to the max
+const BULLET_SPEED_PER_WAVE: f32 = 1.1;
+const MAX_BULLET_SPEED: f32 = 100.0;
+const BULLET_TINT: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+
+// Override the defaults without a rebuild, see EdgeBehavior::parse and
+// ShootStrategy::parse for the accepted names
+const EDGE_BEHAVIOR_ENV: &str = "VADERS_EDGE_BEHAVIOR";
+const SHOOT_STRATEGY_ENV: &str = "VADERS_SHOOT_STRATEGY";
+
+// What the formation does when it reaches a side of the world
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeBehavior {
+    // Reverses and drops a row, the classic march
+    #[default]
+    Bounce,
+    // Enemies leaving one side come back in on the other
+    Wrap,
+    // Stops at the edge and descends a row at marching speed, then marches back
+    StopAndDescend,
+}
+
+impl EdgeBehavior {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "bounce" => Some(Self::Bounce),
+            "wrap" => Some(Self::Wrap),
+            "stop_and_descend" => Some(Self::StopAndDescend),
+            _ => None,
+        }
+    }
+}
+
+// Which enemy fires next, only the lowest enemy of a column ever shoots
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShootStrategy {
+    #[default]
+    RandomColumn,
+    // The column closest to the player
+    NearestColumn,
+    // A random column, with the bullet flying towards the player
+    Aimed,
+}
+
+impl ShootStrategy {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "random_column" => Some(Self::RandomColumn),
+            "nearest_column" => Some(Self::NearestColumn),
+            "aimed" => Some(Self::Aimed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormationOptions {
+    pub edge_behavior: EdgeBehavior,
+    pub shoot_strategy: ShootStrategy,
+    // Seconds between shots
+    pub fire_interval: f64,
+    // Hits each enemy takes before it dies
+    pub health: u32,
+    // World units at the top reserved for the HUD, the formation spawns below them
+    // and only ever moves sideways and down
+    pub hud_margin: f32,
+    // Fixed seed for the firing RNG, None seeds it randomly
+    pub seed: Option<u64>,
+    // Enemy shots on the first wave, in world units per second, multiplied by
+    // bullet_speed_per_wave every wave after up to max_bullet_speed
+    pub bullet_speed: f32,
+    pub bullet_speed_per_wave: f32,
+    pub max_bullet_speed: f32,
+}
+
+impl Default for FormationOptions {
+    fn default() -> Self {
+        Self {
+            edge_behavior: EdgeBehavior::default(),
+            shoot_strategy: ShootStrategy::default(),
+            fire_interval: DEFAULT_FIRE_INTERVAL,
+            seed: None,
+            health: DEFAULT_HEALTH,
+            hud_margin: DEFAULT_HUD_MARGIN,
+            bullet_speed: BULLET_SPEED,
+            bullet_speed_per_wave: BULLET_SPEED_PER_WAVE,
+            max_bullet_speed: MAX_BULLET_SPEED,
+        }
+    }
+}
+
+impl FormationOptions {
+    // The defaults, with the settings available as environment variables taken
+    // from there. Unknown names are reported and ignored.
+    pub fn from_env() -> Self {
+        let mut options = Self::default();
+        if let Some(behavior) = env_option(EDGE_BEHAVIOR_ENV, EdgeBehavior::parse) {
+            options.edge_behavior = behavior;
+        }
+        if let Some(strategy) = env_option(SHOOT_STRATEGY_ENV, ShootStrategy::parse) {
+            options.shoot_strategy = strategy;
+        }
+        options
+    }
+}
+
+pub fn env_option<T>(name: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+    let parsed = parse(&value);
+    if parsed.is_none() {
+        eprintln!("Ignoring unknown {name} {value:?}");
+    }
+    parsed
+}
+
+// Lets the formation, or a boss, fire once every interval seconds, leftover
+// time carries over so the cadence doesn't drift
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FireCooldown {
+    interval: f64,
+    left: f64,
+}
+
+impl FireCooldown {
+    pub fn new(interval: f64) -> Self {
+        Self {
+            interval,
+            left: interval,
+        }
+    }
+
+    // Whether the formation may fire this step
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.left -= f64::from(dt);
+        if self.left > clock::TIMER_EPSILON {
+            return false;
+        }
+        self.left += self.interval;
+        true
+    }
+}
+
+pub struct Enemy {
+    sprite: AnimatedSprite,
+    // Column in the formation, only the lowest enemy of a column shoots
+    col: u32,
+    health: Health,
+}
+impl Enemy {
+    pub fn get_sprite(&self) -> &Sprite {
+        self.sprite.get_sprite()
+    }
+}
+
+pub struct Health {
+    left: u32,
+    // Runs after a hit the enemy survived
+    flash: Timer,
+}
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self {
+            left: max.max(1),
+            flash: Timer::default(),
+        }
+    }
+
+    // Whether this hit was the killing one, later hits on a dead enemy don't count
+    pub fn hit(&mut self) -> bool {
+        if self.left == 0 {
+            return false;
+        }
+        self.left -= 1;
+        if self.left > 0 {
+            self.flash.start(DAMAGE_FLASH);
+        }
+        self.left == 0
+    }
+
+    // Returns true on the step the flash ends
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.flash.update(dt)
+    }
+
+    pub fn tint_effect(&self) -> Option<[f32; 4]> {
+        self.flash.is_running().then_some(DAMAGE_TINT)
+    }
+}
+
+pub struct Formation {
+    enemies: Vec<Enemy>,
+    rows: u32,
+    cols: u32,
+    // Kept to spawn the next wave
+    texture: Arc<wgpu::Texture>,
+    sampler: Arc<wgpu::Sampler>,
+    // 1.0 marching right, -1.0 marching left
+    direction: f32,
+    // Still to descend before marching on, see EdgeBehavior::StopAndDescend
+    descent_left: f32,
+    bullet_texture: SolidTexture,
+    fire_cooldown: FireCooldown,
+    rng: fastrand::Rng,
+    options: FormationOptions,
+    play_area: Rect,
+    // From 1, later waves fire more often
+    wave: u32,
+}
+impl Formation {
+    pub fn new(
+        rows: u32,
+        cols: u32,
+        options: FormationOptions,
+        context: &Context,
+        textures: &mut TextureCache,
+        sampler: Arc<wgpu::Sampler>,
+        renderer: &Renderer,
+    ) -> Result<Self, TextureError> {
+        let texture = textures.get_or_embedded(context, "player.png", assets::PLAYER_PNG)?;
+        let play_area = play_area(options.hud_margin);
+
+        let mut formation = Self {
+            enemies: Vec::new(),
+            rows,
+            cols,
+            texture,
+            sampler,
+            direction: 1.0,
+            descent_left: 0.0,
+            bullet_texture: textures.solid(context),
+            fire_cooldown: FireCooldown::new(options.fire_interval),
+            play_area,
+            wave: 1,
+            rng: options
+                .seed
+                .map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed),
+            options,
+        };
+        formation.respawn(context, renderer);
+        Ok(formation)
+    }
+
+    // A rows by cols wave from now on, spawned straight away
+    pub fn respawn_as(&mut self, rows: u32, cols: u32, context: &Context, renderer: &Renderer) {
+        self.rows = rows;
+        self.cols = cols;
+        self.respawn(context, renderer);
+    }
+
+    // No enemies until the next respawn
+    pub fn clear(&mut self) {
+        self.enemies.clear();
+    }
+
+    // Starts the firing RNG over, so a restarted run fires like the first one
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = fastrand::Rng::with_seed(seed);
+    }
+
+    // A full wave back at the top, marching right again. The firing RNG carries
+    // on so a seeded game stays reproducible across waves.
+    pub fn respawn(&mut self, context: &Context, renderer: &Renderer) {
+        let size = nalgebra_glm::vec2(ENEMY_SIZE.0, ENEMY_SIZE.1);
+        self.enemies.clear();
+        for (col, pos) in grid_positions(self.rows, self.cols, self.play_area) {
+            let mut sprite = Sprite::from_atlas_entry(
+                pos,
+                atlas::PLAYER_SHIP,
+                Some(size),
+                &self.texture,
+                renderer.sprite_layouts(),
+                &self.sampler,
+                context,
+            );
+            sprite.set_tint(TINT, context);
+            let wiggle = Animation::new(
+                WIGGLE_FRAMES.to_vec(),
+                WIGGLE_FRAME_DURATION,
+                PlayMode::Loop,
+            );
+            self.enemies.push(Enemy {
+                sprite: AnimatedSprite::new(sprite, wiggle, context),
+                col,
+                health: Health::new(self.options.health),
+            });
+        }
+        self.direction = 1.0;
+        self.descent_left = 0.0;
+        self.fire_cooldown =
+            FireCooldown::new(wave_fire_interval(self.options.fire_interval, self.wave));
+    }
+
+    // Takes effect from the next respawn, apart from the bullet speed
+    pub fn set_wave(&mut self, wave: u32) {
+        self.wave = wave.max(1);
+    }
+
+    fn bullet_speed(&self) -> f32 {
+        let options = &self.options;
+        wave_bullet_speed(
+            options.bullet_speed,
+            options.bullet_speed_per_wave,
+            options.max_bullet_speed,
+            self.wave,
+        )
+    }
+
+    pub fn update(&mut self, context: &Context, dt: f32) {
+        let Some(bounds) = self.bounds() else {
+            return;
+        };
+
+        let dx = self.direction * MARCH_SPEED * dt;
+        let behavior = self.options.edge_behavior;
+        let floor = self.play_area.pos.y;
+        let step = march(behavior, bounds, dx, self.descent_left, floor);
+        self.descent_left = step.descent_left;
+        if step.reverse {
+            self.direction = -self.direction;
+        }
+
+        for enemy in &mut self.enemies {
+            let mut pos = enemy.get_sprite().position() + step.offset;
+            if behavior == EdgeBehavior::Wrap {
+                pos.x = wrap_x(pos.x, enemy.get_sprite().size().x);
+            }
+            enemy.sprite.get_sprite_mut().set_position(pos, context);
+            enemy.sprite.update(context, dt);
+            if enemy.health.update(dt) {
+                enemy.sprite.get_sprite_mut().set_tint_effect(None, context);
+            }
+        }
+    }
+
+    // Every fire_interval the lowest enemy of a column picked by the shoot strategy
+    // drops a bullet. target is the player's center.
+    pub fn try_fire(
+        &mut self,
+        context: &Context,
+        renderer: &Renderer,
+        target: nalgebra_glm::Vec2,
+        dt: f32,
+    ) -> Option<Bullet> {
+        if !self.fire_cooldown.update(dt) {
+            return None;
+        }
+
+        let shooters = self.bottom_row();
+        let centers: Vec<f32> = shooters
+            .iter()
+            .map(|&i| {
+                let sprite = self.enemies[i].get_sprite();
+                sprite.position().x + sprite.size().x / 2.0
+            })
+            .collect();
+        let strategy = self.options.shoot_strategy;
+        let pick = pick_shooter(strategy, &centers, target.x, &mut self.rng)?;
+        let shooter = self.enemies[shooters[pick]].get_sprite();
+
+        let size = nalgebra_glm::vec2(BULLET_SIZE.0, BULLET_SIZE.1);
+        let pos =
+            shooter.position() + nalgebra_glm::vec2((shooter.size().x - size.x) / 2.0, -size.y);
+        let speed = self.bullet_speed();
+        let velocity = match strategy {
+            ShootStrategy::Aimed => aim(pos + size / 2.0, target, speed),
+            ShootStrategy::RandomColumn | ShootStrategy::NearestColumn => {
+                nalgebra_glm::vec2(0.0, -speed)
+            }
+        };
+        let mut sprite = Sprite::new(
+            pos,
+            size,
+            &self.bullet_texture.texture,
+            renderer.sprite_layouts(),
+            &self.bullet_texture.sampler,
+            context,
+        );
+        sprite.set_tint(BULLET_TINT, context);
+        // Points the long side of the bullet along its path, 0 is straight down
+        sprite.set_rotation(velocity.x.atan2(-velocity.y), context);
+        Some(Bullet::new(sprite, velocity, context))
+    }
+
+    // Indices of the lowest remaining enemy in each column
+    fn bottom_row(&self) -> Vec<usize> {
+        let mut lowest: Vec<usize> = Vec::new();
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            match lowest
+                .iter_mut()
+                .find(|j| self.enemies[**j].col == enemy.col)
+            {
+                Some(j) => {
+                    if enemy.get_sprite().position().y < self.enemies[*j].get_sprite().position().y
+                    {
+                        *j = i;
+                    }
+                }
+                None => lowest.push(i),
+            }
+        }
+        lowest
+    }
+
+    // Smallest rect around every enemy, None once they're all dead
+    fn bounds(&self) -> Option<Rect> {
+        self.enemies
+            .iter()
+            .fold(None, |bounds: Option<Rect>, enemy| {
+                let sprite = enemy.get_sprite();
+                let (min, max) = (sprite.position(), sprite.position() + sprite.size());
+                let (min, max) = match bounds {
+                    None => (min, max),
+                    Some(bounds) => (
+                        nalgebra_glm::min2(&bounds.pos, &min),
+                        nalgebra_glm::max2(&(bounds.pos + bounds.size), &max),
+                    ),
+                };
+                Some(Rect {
+                    pos: min,
+                    size: max - min,
+                })
+            })
+    }
+
+    // Whether the formation has come down to the bottom of the play area, where
+    // it stops descending
+    pub fn has_landed(&self) -> bool {
+        self.bounds()
+            .is_some_and(|bounds| bounds.pos.y <= self.play_area.pos.y + LANDED_EPSILON)
+    }
+
+    // Takes one health off each hit enemy and returns the ones that died, which are
+    // still in the formation until destroy(). Indices are positions in sprites().
+    pub fn damage(&mut self, indices: &[usize], context: &Context) -> Vec<usize> {
+        let mut killed = Vec::new();
+        for &index in indices {
+            let enemy = &mut self.enemies[index];
+            if enemy.health.hit() {
+                killed.push(index);
+            } else if enemy.health.left > 0 {
+                let effect = enemy.health.tint_effect();
+                enemy
+                    .sprite
+                    .get_sprite_mut()
+                    .set_tint_effect(effect, context);
+            }
+        }
+        killed
+    }
+
+    // Indices are positions in sprites()
+    pub fn destroy(&mut self, indices: &[usize]) {
+        collision::remove_indices(&mut self.enemies, indices);
+    }
+
+    pub fn sprites(&self) -> Vec<&Sprite> {
+        self.enemies.iter().map(Enemy::get_sprite).collect()
+    }
+
+    // The world minus the HUD margin
+    pub fn play_area(&self) -> Rect {
+        self.play_area
+    }
+}
+
+// The world below the top hud_margin units
+pub fn play_area(hud_margin: f32) -> Rect {
+    let height = (WORLD_HEIGHT - hud_margin.max(0.0)).max(0.0);
+    Rect {
+        pos: nalgebra_glm::vec2(0.0, 0.0),
+        size: nalgebra_glm::vec2(WORLD_WIDTH, height),
+    }
+}
+
+// Column and bottom left corner of every enemy, row by row. The grid is centered
+// horizontally in area and rows stack downwards from its top.
+fn grid_positions(rows: u32, cols: u32, area: Rect) -> Vec<(u32, nalgebra_glm::Vec2)> {
+    let width = (cols.max(1) - 1) as f32 * SPACING.0 + ENEMY_SIZE.0;
+    let left = area.pos.x + (area.size.x - width) / 2.0;
+    let top = area.pos.y + area.size.y - TOP_MARGIN - ENEMY_SIZE.1;
+
+    (0..rows)
+        .flat_map(|row| {
+            (0..cols).map(move |col| {
+                let pos =
+                    nalgebra_glm::vec2(left + col as f32 * SPACING.0, top - row as f32 * SPACING.1);
+                (col, pos)
+            })
+        })
+        .collect()
+}
+
+// How often wave fires, wave 1 at the base interval
+fn wave_fire_interval(base: f64, wave: u32) -> f64 {
+    let speedup = WAVE_FIRE_SPEEDUP.powi(wave.max(1) as i32 - 1);
+    base * speedup.max(WAVE_FIRE_FLOOR)
+}
+
+// The cap wins over a base speed that's already past it
+fn wave_bullet_speed(base: f32, per_wave: f32, max: f32, wave: u32) -> f32 {
+    (base * per_wave.powi(wave.max(1) as i32 - 1)).min(max)
+}
+
+// Whether moving the formation by dx would push an edge enemy out of the world
+fn hits_edge(min_x: f32, max_x: f32, dx: f32) -> bool {
+    min_x + dx < 0.0 || max_x + dx > WORLD_WIDTH
+}
+
+// Index into shooter_xs, the centers of the shooting enemies. None without shooters.
+fn pick_shooter(
+    strategy: ShootStrategy,
+    shooter_xs: &[f32],
+    player_x: f32,
+    rng: &mut fastrand::Rng,
+) -> Option<usize> {
+    if shooter_xs.is_empty() {
+        return None;
+    }
+    match strategy {
+        ShootStrategy::RandomColumn | ShootStrategy::Aimed => Some(rng.usize(..shooter_xs.len())),
+        ShootStrategy::NearestColumn => shooter_xs
+            .iter()
+            .map(|x| (x - player_x).abs())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i),
+    }
+}
+
+// speed towards target, straight down when already on top of it
+fn aim(from: nalgebra_glm::Vec2, target: nalgebra_glm::Vec2, speed: f32) -> nalgebra_glm::Vec2 {
+    let to_target = target - from;
+    if nalgebra_glm::length(&to_target) <= f32::EPSILON {
+        return nalgebra_glm::vec2(0.0, -speed);
+    }
+    nalgebra_glm::normalize(&to_target) * speed
+}
+
+// How the whole formation moves in one step
+#[derive(Debug, PartialEq)]
+struct March {
+    offset: nalgebra_glm::Vec2,
+    reverse: bool,
+    // Carried over to the next step, see Formation::descent_left
+    descent_left: f32,
+}
+
+impl March {
+    fn sideways(dx: f32) -> Self {
+        Self {
+            offset: nalgebra_glm::vec2(dx, 0.0),
+            reverse: false,
+            descent_left: 0.0,
+        }
+    }
+}
+
+// bounds is the formation's, see Formation::bounds. Drops never take it below floor.
+fn march(behavior: EdgeBehavior, bounds: Rect, dx: f32, descent_left: f32, floor: f32) -> March {
+    let (min_x, max_x) = (bounds.pos.x, bounds.pos.x + bounds.size.x);
+    let mut step = match behavior {
+        // Descends as fast as it marches, reversing once the row is done
+        EdgeBehavior::StopAndDescend if descent_left > 0.0 => {
+            let drop = descent_left.min(dx.abs());
+            March {
+                offset: nalgebra_glm::vec2(0.0, -drop),
+                reverse: drop >= descent_left,
+                descent_left: descent_left - drop,
+            }
+        }
+        // Edges are handled per enemy by wrap_x
+        EdgeBehavior::Wrap => March::sideways(dx),
+        _ if !hits_edge(min_x, max_x, dx) => March::sideways(dx),
+        EdgeBehavior::Bounce => March {
+            offset: nalgebra_glm::vec2(0.0, -DROP_DISTANCE),
+            reverse: true,
+            descent_left: 0.0,
+        },
+        EdgeBehavior::StopAndDescend => March {
+            offset: nalgebra_glm::vec2(0.0, 0.0),
+            reverse: false,
+            descent_left: DROP_DISTANCE,
+        },
+    };
+    step.offset.y = step.offset.y.max((floor - bounds.pos.y).min(0.0));
+    step
+}
+
+// An enemy entirely past one side reappears just outside the other, then
+// marches back in
+fn wrap_x(x: f32, width: f32) -> f32 {
+    let span = WORLD_WIDTH + width;
+    if x >= WORLD_WIDTH {
+        x - span
+    } else if x + width <= 0.0 {
+        x + span
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A formation spanning min_x..max_x, well above the floor
+    fn bounds(min_x: f32, max_x: f32) -> Rect {
+        Rect {
+            pos: nalgebra_glm::vec2(min_x, 100.0),
+            size: nalgebra_glm::vec2(max_x - min_x, 40.0),
+        }
+    }
+
+    #[test]
+    fn later_waves_fire_more_often_down_to_a_floor() {
+        assert_eq!(wave_fire_interval(1.2, 1), 1.2);
+        assert!((wave_fire_interval(1.2, 3) - 1.2 * 0.81).abs() < 1e-9);
+        assert_eq!(wave_fire_interval(1.2, 40), 1.2 * WAVE_FIRE_FLOOR);
+    }
+
+    #[test]
+    fn later_waves_shoot_faster_up_to_the_cap() {
+        let speed = |wave| wave_bullet_speed(60.0, 1.1, 100.0, wave);
+        assert_eq!(speed(1), 60.0);
+        assert!(speed(3) > speed(1));
+        assert!((speed(3) - 60.0 * 1.21).abs() < 1e-4);
+        assert_eq!(speed(20), 100.0);
+    }
+
+    #[test]
+    fn grid_spawns_below_the_hud_margin() {
+        let margin = 30.0;
+        let positions = grid_positions(5, 11, play_area(margin));
+        assert_eq!(positions.len(), 55);
+        let top = positions
+            .iter()
+            .map(|(_, pos)| pos.y + ENEMY_SIZE.1)
+            .fold(f32::MIN, f32::max);
+        assert!(top <= WORLD_HEIGHT - margin);
+        assert_eq!(top, WORLD_HEIGHT - margin - TOP_MARGIN);
+        // Columns are numbered left to right on every row
+        assert_eq!(positions[11].0, 0);
+        assert!(positions[10].1.x > positions[0].1.x);
+    }
+
+    #[test]
+    fn nearest_column_picks_the_shooter_closest_to_the_player() {
+        let mut rng = fastrand::Rng::with_seed(1);
+        let xs = [20.0, 60.0, 100.0, 140.0];
+        let pick = |x| pick_shooter(ShootStrategy::NearestColumn, &xs, x, &mut rng.clone());
+        assert_eq!(pick(0.0), Some(0));
+        assert_eq!(pick(90.0), Some(2));
+        assert_eq!(pick(500.0), Some(3));
+        assert_eq!(
+            pick_shooter(ShootStrategy::NearestColumn, &[], 10.0, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn random_column_uses_the_injected_rng() {
+        let xs = [20.0, 60.0, 100.0, 140.0];
+        let picks = |seed| {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            (0..16)
+                .map(|_| pick_shooter(ShootStrategy::RandomColumn, &xs, 0.0, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(picks(7), picks(7));
+        assert!(picks(7)
+            .iter()
+            .all(|pick| pick.is_some_and(|i| i < xs.len())));
+    }
+
+    #[test]
+    fn two_health_survives_one_hit_and_dies_on_the_second() {
+        let mut health = Health::new(2);
+        assert!(!health.hit());
+        assert_eq!(health.left, 1);
+        assert!(health.hit());
+        // Already dead, a second bullet in the same frame doesn't kill it again
+        assert!(!health.hit());
+    }
+
+    #[test]
+    fn damage_flashes_only_for_a_moment() {
+        let mut health = Health::new(2);
+        assert_eq!(health.tint_effect(), None);
+        health.hit();
+        assert_eq!(health.tint_effect(), Some(DAMAGE_TINT));
+
+        let dt = clock::FIXED_STEP.as_secs_f32();
+        let steps = (DAMAGE_FLASH / f64::from(dt)).ceil() as usize;
+        let ended: Vec<bool> = (0..steps + 2).map(|_| health.update(dt)).collect();
+        assert_eq!(ended.iter().filter(|&&ended| ended).count(), 1);
+        assert_eq!(health.tint_effect(), None);
+    }
+
+    #[test]
+    fn a_damaged_invader_is_tinted_until_the_flash_ends() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let renderer = Renderer::init(
+            &context,
+            &crate::test_surface_config(458, 380),
+            &mut textures,
+        );
+        let sampler = Arc::new(crate::texture::create_sampler(
+            &context,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::AddressMode::ClampToEdge,
+        ));
+        let options = FormationOptions {
+            health: 2,
+            ..Default::default()
+        };
+        let mut formation =
+            Formation::new(1, 1, options, &context, &mut textures, sampler, &renderer).unwrap();
+
+        assert!(formation.damage(&[0], &context).is_empty());
+        let flashed: [f32; 4] = std::array::from_fn(|i| TINT[i] * DAMAGE_TINT[i]);
+        assert_eq!(formation.sprites()[0].tint(), flashed);
+
+        formation.update(&context, DAMAGE_FLASH as f32 * 2.0);
+        assert_eq!(formation.sprites()[0].tint(), TINT);
+    }
+
+    #[test]
+    fn default_health_dies_on_the_first_hit() {
+        assert!(Health::new(FormationOptions::default().health).hit());
+        assert!(Health::new(0).hit());
+    }
+
+    #[test]
+    fn cooldown_gates_fire_frequency() {
+        let mut cooldown = FireCooldown::new(1.2);
+        let dt = clock::FIXED_STEP.as_secs_f32();
+        // Ten seconds of steps
+        let shots = (0..600).filter(|_| cooldown.update(dt)).count();
+        assert_eq!(shots, 8);
+    }
+
+    #[test]
+    fn aimed_shots_fly_at_the_target() {
+        let velocity = aim(
+            nalgebra_glm::vec2(100.0, 100.0),
+            nalgebra_glm::vec2(130.0, 60.0),
+            BULLET_SPEED,
+        );
+        assert!((nalgebra_glm::length(&velocity) - BULLET_SPEED).abs() < 1e-3);
+        assert!((velocity.x / velocity.y - 30.0 / -40.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn bounce_reverses_and_drops_at_the_edge() {
+        let step = march(
+            EdgeBehavior::Bounce,
+            bounds(100.0, WORLD_WIDTH - 1.0),
+            2.0,
+            0.0,
+            0.0,
+        );
+        assert_eq!(
+            step,
+            March {
+                offset: nalgebra_glm::vec2(0.0, -DROP_DISTANCE),
+                reverse: true,
+                descent_left: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn bounce_marches_across_and_drops_a_row_per_edge() {
+        let dx = MARCH_SPEED * clock::FIXED_STEP.as_secs_f32();
+        let start = bounds(29.0, 200.0);
+        let mut formation = start;
+        let mut direction = 1.0;
+        let mut reversals = 0;
+        let mut step_for = |ticks: usize, formation: &mut Rect| {
+            for _ in 0..ticks {
+                let step = march(EdgeBehavior::Bounce, *formation, direction * dx, 0.0, 0.0);
+                formation.pos += step.offset;
+                if step.reverse {
+                    direction = -direction;
+                    reversals += 1;
+                }
+                assert!(formation.pos.x >= 0.0);
+                assert!(formation.pos.x + formation.size.x <= WORLD_WIDTH);
+            }
+        };
+
+        // A second of ticks adds up to a second of marching, still short of the edge
+        step_for(60, &mut formation);
+        assert!((formation.pos.x - (start.pos.x + MARCH_SPEED)).abs() < 1e-3);
+        assert_eq!(formation.pos.y, start.pos.y);
+
+        // Half a minute goes back and forth a few times, one row down per edge
+        step_for(30 * 60, &mut formation);
+        assert!(reversals >= 4);
+        let dropped = start.pos.y - formation.pos.y;
+        assert!((dropped - reversals as f32 * DROP_DISTANCE).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stop_and_descend_stops_at_the_edge() {
+        let step = march(
+            EdgeBehavior::StopAndDescend,
+            bounds(1.0, 100.0),
+            -2.0,
+            0.0,
+            0.0,
+        );
+        assert_eq!(
+            step,
+            March {
+                offset: nalgebra_glm::vec2(0.0, 0.0),
+                reverse: false,
+                descent_left: DROP_DISTANCE,
+            }
+        );
+    }
+
+    #[test]
+    fn stop_and_descend_drops_one_row_per_edge_then_marches_back() {
+        let dx = MARCH_SPEED * clock::FIXED_STEP.as_secs_f32();
+        let start = bounds(WORLD_WIDTH - 20.0, WORLD_WIDTH - 1.0);
+        let mut formation = start;
+        let mut direction = 1.0;
+        let mut descent_left = 0.0;
+        let mut reversals = 0;
+        // Two seconds, long enough to reach the right edge but not the left one
+        for _ in 0..120 {
+            let step = march(
+                EdgeBehavior::StopAndDescend,
+                formation,
+                direction * dx,
+                descent_left,
+                0.0,
+            );
+            formation.pos += step.offset;
+            descent_left = step.descent_left;
+            if step.reverse {
+                direction = -direction;
+                reversals += 1;
+            }
+        }
+        assert_eq!(reversals, 1);
+        assert!((formation.pos.y - (start.pos.y - DROP_DISTANCE)).abs() < 1e-3);
+        assert!(formation.pos.x < start.pos.x);
+    }
+
+    #[test]
+    fn drops_stop_at_the_floor() {
+        let mut formation = bounds(100.0, WORLD_WIDTH - 1.0);
+        formation.pos.y = 21.0;
+        let step = march(EdgeBehavior::Bounce, formation, 2.0, 0.0, 20.0);
+        assert_eq!(step.offset, nalgebra_glm::vec2(0.0, -1.0));
+
+        // Already down, nothing left to drop
+        formation.pos.y = 20.0;
+        for behavior in [EdgeBehavior::Bounce, EdgeBehavior::StopAndDescend] {
+            let step = march(behavior, formation, 2.0, DROP_DISTANCE, 20.0);
+            assert_eq!(step.offset.y, 0.0);
+        }
+    }
+
+    #[test]
+    fn wrap_keeps_marching_past_the_edge() {
+        let step = march(
+            EdgeBehavior::Wrap,
+            bounds(100.0, WORLD_WIDTH - 1.0),
+            2.0,
+            0.0,
+            0.0,
+        );
+        assert_eq!(step, March::sideways(2.0));
+
+        // Fully off the right side comes back fully off the left side and vice versa
+        assert_eq!(wrap_x(WORLD_WIDTH + 1.0, 11.0), -10.0);
+        assert_eq!(wrap_x(-12.0, 11.0), WORLD_WIDTH - 1.0);
+        // Still partly visible, left alone
+        assert_eq!(wrap_x(WORLD_WIDTH - 5.0, 11.0), WORLD_WIDTH - 5.0);
+    }
+
+    #[test]
+    fn marches_freely_away_from_the_edges() {
+        for behavior in [
+            EdgeBehavior::Bounce,
+            EdgeBehavior::Wrap,
+            EdgeBehavior::StopAndDescend,
+        ] {
+            assert_eq!(
+                march(behavior, bounds(50.0, 150.0), 2.0, 0.0, 0.0),
+                March::sideways(2.0)
+            );
+        }
+    }
+
+    #[test]
+    fn edges_are_hit_only_when_crossed() {
+        assert!(!hits_edge(0.0, WORLD_WIDTH, 0.0));
+        assert!(!hits_edge(10.0, 100.0, -10.0));
+        assert!(hits_edge(10.0, 100.0, -10.5));
+        assert!(!hits_edge(10.0, WORLD_WIDTH - 2.0, 2.0));
+        assert!(hits_edge(10.0, WORLD_WIDTH - 2.0, 2.5));
+    }
+
+    #[test]
+    fn edge_behavior_names_parse_case_insensitively() {
+        assert_eq!(EdgeBehavior::parse("Wrap"), Some(EdgeBehavior::Wrap));
+        assert_eq!(
+            EdgeBehavior::parse(" stop_and_descend "),
+            Some(EdgeBehavior::StopAndDescend)
+        );
+        assert_eq!(EdgeBehavior::parse("bounce"), Some(EdgeBehavior::Bounce));
+        assert_eq!(EdgeBehavior::parse("sideways"), None);
+    }
+
+    #[test]
+    fn shoot_strategy_names_parse_case_insensitively() {
+        assert_eq!(
+            ShootStrategy::parse("NEAREST_COLUMN"),
+            Some(ShootStrategy::NearestColumn)
+        );
+        assert_eq!(ShootStrategy::parse("aimed"), Some(ShootStrategy::Aimed));
+        assert_eq!(
+            ShootStrategy::parse("random_column"),
+            Some(ShootStrategy::RandomColumn)
+        );
+        assert_eq!(ShootStrategy::parse(""), None);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::powerup::PowerUpKind;
+
+// Things that happened during an update, for the systems that react to them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    // pos is the center of the invader
+    InvaderKilled { pos: nalgebra_glm::Vec2 },
+    // pos is the center of the player
+    PlayerHit { pos: nalgebra_glm::Vec2 },
+    WaveCleared,
+    BonusAwarded { points: u32 },
+    PowerUpCollected { kind: PowerUpKind },
+}
+
+// Filled during the update pass and drained once it's over, so the systems that
+// cause events don't need to know which ones listen
+#[derive(Debug, Default)]
+pub struct Events {
+    queue: Vec<GameEvent>,
+}
+
+impl Events {
+    pub fn push(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GameEvent> {
+        self.queue.iter()
+    }
+
+    pub fn kills(&self) -> usize {
+        self.iter()
+            .filter(|event| matches!(event, GameEvent::InvaderKilled { .. }))
+            .count()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
@@ -0,0 +1,128 @@
+use crate::clock::Timer;
+use crate::event::GameEvent;
+use crate::rect::Rect;
+use crate::{WORLD_HEIGHT, WORLD_WIDTH};
+
+// Red at half strength, fading out over a third of a second
+const DEFAULT_COLOR: wgpu::Color = wgpu::Color {
+    r: 1.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.5,
+};
+const DEFAULT_DURATION: f64 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlashOptions {
+    // Its alpha is the strength right after the hit
+    pub color: wgpu::Color,
+    // Seconds until it has faded out
+    pub duration: f64,
+}
+
+impl Default for FlashOptions {
+    fn default() -> Self {
+        Self {
+            color: DEFAULT_COLOR,
+            duration: DEFAULT_DURATION,
+        }
+    }
+}
+
+// Tints the whole world for a moment after the player is hit, the game goes on
+// underneath
+#[derive(Debug)]
+pub struct ScreenFlash {
+    options: FlashOptions,
+    timer: Timer,
+}
+
+impl ScreenFlash {
+    pub fn new(options: FlashOptions) -> Self {
+        Self {
+            options,
+            timer: Timer::default(),
+        }
+    }
+
+    // A hit during a flash starts it over at full strength
+    pub fn observe(&mut self, event: &GameEvent) {
+        if let GameEvent::PlayerHit { .. } = event {
+            self.timer.start(self.options.duration);
+        }
+    }
+
+    // Ends it early, for a new run
+    pub fn clear(&mut self) {
+        self.timer = Timer::default();
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.timer.update(dt);
+    }
+
+    // Fades linearly from the configured alpha to nothing
+    pub fn alpha(&self) -> f64 {
+        if !self.timer.is_running() || self.options.duration <= 0.0 {
+            return 0.0;
+        }
+        self.options.color.a * self.timer.left() / self.options.duration
+    }
+
+    // The rect to draw over the world and its color, None once faded out
+    pub fn overlay(&self) -> Option<(Rect, wgpu::Color)> {
+        let alpha = self.alpha();
+        if alpha <= 0.0 {
+            return None;
+        }
+        let rect = Rect {
+            pos: nalgebra_glm::vec2(0.0, 0.0),
+            size: nalgebra_glm::vec2(WORLD_WIDTH, WORLD_HEIGHT),
+        };
+        Some((
+            rect,
+            wgpu::Color {
+                a: alpha,
+                ..self.options.color
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit() -> GameEvent {
+        GameEvent::PlayerHit {
+            pos: nalgebra_glm::vec2(0.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn the_flash_decays_to_nothing_over_its_duration() {
+        let mut flash = ScreenFlash::new(FlashOptions::default());
+        assert!(flash.overlay().is_none());
+        flash.observe(&GameEvent::WaveCleared);
+        assert!(flash.overlay().is_none());
+
+        flash.observe(&hit());
+        assert_eq!(flash.alpha(), DEFAULT_COLOR.a);
+        let mut last = flash.alpha();
+        for _ in 0..9 {
+            flash.update(DEFAULT_DURATION as f32 / 10.0);
+            let alpha = flash.alpha();
+            assert!(alpha < last, "{alpha} after {last}");
+            last = alpha;
+        }
+        assert!((last - DEFAULT_COLOR.a / 10.0).abs() < 1e-6);
+
+        flash.update(DEFAULT_DURATION as f32 / 10.0);
+        assert_eq!(flash.alpha(), 0.0);
+        assert!(flash.overlay().is_none());
+
+        flash.observe(&hit());
+        flash.clear();
+        assert_eq!(flash.alpha(), 0.0);
+    }
+}
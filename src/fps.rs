@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const DEFAULT_WINDOW: usize = 60;
+
+// Averages the time between the last `window` frames
+pub struct FpsCounter {
+    frame_times: VecDeque<Duration>,
+    window: usize,
+    // Kept alongside the ring buffer so the average doesn't resum every frame
+    total: Duration,
+    last: Option<Instant>,
+}
+
+impl Default for FpsCounter {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
+
+impl FpsCounter {
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            frame_times: VecDeque::with_capacity(window),
+            window,
+            total: Duration::ZERO,
+            last: None,
+        }
+    }
+
+    // Call once per frame, the first call only starts the timer
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            self.record(now - last);
+        }
+        self.last = Some(now);
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.window {
+            if let Some(oldest) = self.frame_times.pop_front() {
+                self.total -= oldest;
+            }
+        }
+        self.frame_times.push_back(frame_time);
+        self.total += frame_time;
+    }
+
+    // 0 until a frame has been recorded
+    pub fn average_fps(&self) -> f32 {
+        if self.total.is_zero() {
+            return 0.0;
+        }
+        self.frame_times.len() as f32 / self.total.as_secs_f32()
+    }
+
+    // The most recent frame, not the average
+    pub fn frame_time_ms(&self) -> f32 {
+        self.frame_times
+            .back()
+            .map_or(0.0, |frame_time| frame_time.as_secs_f32() * 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_frames_reads_as_zero() {
+        assert_eq!(FpsCounter::default().average_fps(), 0.0);
+    }
+
+    #[test]
+    fn average_covers_only_the_window() {
+        let mut fps = FpsCounter::new(2);
+        fps.record(Duration::from_millis(100));
+        assert!((fps.average_fps() - 10.0).abs() < 1e-3);
+
+        // The 100ms frame falls out once two more have been recorded
+        fps.record(Duration::from_millis(20));
+        fps.record(Duration::from_millis(20));
+        assert!((fps.average_fps() - 50.0).abs() < 1e-3);
+        assert!((fps.frame_time_ms() - 20.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,116 @@
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const FRAME_LOG_ENV: &str = "VADERS_FRAME_LOG";
+const FLUSH_INTERVAL: u64 = 120;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    pub update: Duration,
+    pub render: Duration,
+    pub present: Duration,
+}
+
+// Appends one CSV row of timings per frame, only created when VADERS_FRAME_LOG is set
+pub struct FrameLog {
+    writer: BufWriter<File>,
+    frame: u64,
+    last_frame: Instant,
+}
+
+impl FrameLog {
+    pub fn from_env() -> Option<Self> {
+        let path = env::var_os(FRAME_LOG_ENV)?;
+        match Self::create(&path) {
+            Ok(log) => Some(log),
+            Err(err) => {
+                eprintln!(
+                    "Could not open frame log {}: {err}",
+                    Path::new(&path).display()
+                );
+                None
+            }
+        }
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,dt_ms,update_ms,render_ms,present_ms")?;
+
+        Ok(Self {
+            writer,
+            frame: 0,
+            last_frame: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, timings: &FrameTimings) -> io::Result<()> {
+        let now = Instant::now();
+        let dt = now - self.last_frame;
+        self.last_frame = now;
+
+        writeln!(
+            self.writer,
+            "{},{:.3},{:.3},{:.3},{:.3}",
+            self.frame,
+            millis(dt),
+            millis(timings.update),
+            millis(timings.render),
+            millis(timings.present),
+        )?;
+
+        self.frame += 1;
+        if self.frame.is_multiple_of(FLUSH_INTERVAL) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for FrameLog {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_row_per_recorded_frame() {
+        let path = env::temp_dir().join(format!("vaders-frame-log-{}.csv", std::process::id()));
+        let mut log = FrameLog::create(&path).unwrap();
+        let timings = FrameTimings {
+            update: Duration::from_micros(1500),
+            render: Duration::from_millis(2),
+            present: Duration::from_millis(3),
+        };
+        log.record(&timings).unwrap();
+        log.record(&timings).unwrap();
+        log.flush().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let rows: Vec<_> = contents.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], "frame,dt_ms,update_ms,render_ms,present_ms");
+        for (frame, row) in rows[1..].iter().enumerate() {
+            let columns: Vec<_> = row.split(',').collect();
+            assert_eq!(columns.len(), 5);
+            assert_eq!(columns[0], frame.to_string());
+            assert_eq!(columns[2..], ["1.500", "2.000", "3.000"]);
+        }
+    }
+}
@@ -0,0 +1,1497 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use winit::dpi::PhysicalSize;
+use winit::event::MouseButton;
+use winit::window::Window;
+
+#[cfg(feature = "audio")]
+use crate::audio::{Audio, AudioOptions, SoundId};
+use crate::boss::{self, Boss};
+use crate::bullet::Bullets;
+use crate::collision::{self, SpatialGrid};
+use crate::enemy::{self, Formation, FormationOptions};
+use crate::event::{Events, GameEvent};
+use crate::flash::{FlashOptions, ScreenFlash};
+use crate::fps::FpsCounter;
+use crate::input::{self, Action, InputMap, InputSnapshot};
+#[cfg(feature = "debug")]
+use crate::latency::InputLatency;
+use crate::loading::Loading;
+use crate::menu::{MenuItem, PauseMenu, TitleChoice, TitleMenu};
+use crate::particle::Emitter;
+use crate::player::{Player, PlayerOptions};
+use crate::powerup::PowerUps;
+use crate::profile::PerformanceProfile;
+use crate::rect::Rect;
+use crate::renderer::{self, RenderTarget, Renderer, RendererOptions, ViewportPass};
+use crate::schedule::WaveSchedule;
+use crate::score::{ExtraLives, Score};
+use crate::settings::Settings;
+use crate::sprite::Sprite;
+use crate::surface::{Surface, SurfaceCreationError};
+use crate::text::{self, TextRenderer};
+use crate::texture::{self, SolidTexture, TextureError};
+use crate::texture_cache::TextureCache;
+use crate::{assets, Context};
+
+const FORMATION_ROWS: u32 = 5;
+const FORMATION_COLS: u32 = 11;
+// Awarded on top of the kills when the last invader of a wave dies
+const WAVE_BONUS: u32 = 100;
+const DEFAULT_EXTRA_LIFE_SCORES: [u32; 2] = [1500, 5000];
+// Any text for a reproducible run, see seed_from_str
+const SEED_ENV: &str = "VADERS_SEED";
+// Picked when no seed is given, so every run has one that can be passed on.
+// Without 0/O and 1/I, which are easy to mix up when read off the screen.
+const SEED_NAME_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const SEED_NAME_LEN: usize = 6;
+// Set to anything to turn off the flash on a hit, for photosensitive players
+const NO_FLASH_ENV: &str = "VADERS_NO_FLASH";
+// Comma separated scores, like "1000,3000", replacing the defaults
+const EXTRA_LIFE_SCORES_ENV: &str = "VADERS_EXTRA_LIFE_SCORES";
+
+// World units per font pixel
+const TEXT_SCALE: f32 = 2.0;
+// "LOADING" is 7 glyphs, 54 units wide at this scale, centered in the world
+const LOADING_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(87.0, 100.0);
+// "PAUSED" is 6 glyphs, 46 units wide at this scale, centered in the world
+const PAUSED_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(91.0, 100.0);
+// Lines like "> RESUME" are 8 glyphs, 62 units wide, centered under "PAUSED"
+const PAUSE_MENU_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(83.0, 84.0);
+// "> PRACTICE WAVE 9" is 17 glyphs, 134 units wide, centered in the world
+const MENU_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(47.0, 100.0);
+// "GAME OVER" is 9 glyphs, 70 units wide, centered in the world
+const GAME_OVER_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(79.0, 100.0);
+// "PRESS FIRE" under "GAME OVER", 10 glyphs, 78 units wide, centered in the world
+const RESTART_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(75.0, 84.0);
+// Bottom left corner, below the player
+const LIVES_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(4.0, 14.0);
+// Just above the lives line
+const DIAGNOSTICS_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(4.0, 26.0);
+// Top left corner, in the HUD margin above the formation
+const SCORE_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(4.0, 186.0);
+// Under the score line
+const FPS_TEXT_POS: nalgebra_glm::Vec2 = nalgebra_glm::Vec2::new(4.0, 174.0);
+// Set to anything to show the FPS counter from the start, F3 toggles it
+const SHOW_FPS_ENV: &str = "VADERS_SHOW_FPS";
+// Set to anything to show the version, seed and wave above the lives line, for
+// bug reports. Screenshots include it.
+const DIAGNOSTICS_ENV: &str = "VADERS_DIAGNOSTICS";
+// Set to anything to draw sprites with Renderer::render_instanced, which ignores
+// custom sprite shaders
+const INSTANCED_ENV: &str = "VADERS_INSTANCED";
+// Set to anything to quit on Action::Menu straight away instead of opening the
+// pause menu, for kiosks
+const INSTANT_EXIT_ENV: &str = "VADERS_INSTANT_EXIT";
+// Set to anything to fire whenever the cooldown allows, for players who can't
+// keep tapping fire
+const AUTOFIRE_ENV: &str = "VADERS_AUTOFIRE";
+// The shield ring around the ship, see Player::set_shield_shader
+const SHIELD_SHADER: &str = include_str!("shield.wgsl");
+// Dark blue, the space behind everything
+const SPACE_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.01,
+    g: 0.01,
+    b: 0.05,
+    a: 1.0,
+};
+// World units shown across the zoom inset, a sixth of the world each way
+const ZOOM_SPAN: nalgebra_glm::Vec2 =
+    nalgebra_glm::Vec2::new(crate::WORLD_WIDTH / 6.0, crate::WORLD_HEIGHT / 6.0);
+// Internal frame pixels between the zoom inset and the frame's top right corner
+const ZOOM_MARGIN: u32 = 8;
+// Twice the world, every world unit is 2x2 pixels of the internal frame
+const INTERNAL_RESOLUTION: PhysicalSize<u32> = PhysicalSize::new(458, 380);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    // Until the images decoding in the background are in
+    Loading,
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+impl GameState {
+    // Only a running or paused game can be toggled, menus stay where they are
+    pub fn toggle_pause(self) -> Self {
+        match self {
+            GameState::Playing => GameState::Paused,
+            GameState::Paused => GameState::Playing,
+            state => state,
+        }
+    }
+}
+
+// How a run is set up, kept for every restart
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameOptions {
+    pub player: PlayerOptions,
+    pub formation: FormationOptions,
+    // Each is worth a life the first time a run's score reaches it
+    pub extra_life_scores: Vec<u32>,
+    // None skips the flash on a hit
+    pub flash: Option<FlashOptions>,
+    // Text the run's RNG seed is derived from, None or empty picks a random one.
+    // Replaces formation.seed.
+    pub seed: Option<String>,
+    // None detects one from the adapter in a windowed game, headless ones use High
+    pub profile: Option<PerformanceProfile>,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        Self {
+            player: PlayerOptions::default(),
+            formation: FormationOptions::default(),
+            extra_life_scores: DEFAULT_EXTRA_LIFE_SCORES.to_vec(),
+            flash: Some(FlashOptions::default()),
+            seed: None,
+            profile: None,
+        }
+    }
+}
+
+impl GameOptions {
+    // The defaults, with the formation's and these settings taken from the
+    // environment where set
+    pub fn from_env() -> Self {
+        let mut options = Self {
+            formation: FormationOptions::from_env(),
+            ..Self::default()
+        };
+        if let Some(scores) = enemy::env_option(EXTRA_LIFE_SCORES_ENV, parse_scores) {
+            options.extra_life_scores = scores;
+        }
+        options.seed = std::env::var(SEED_ENV).ok();
+        options.profile = PerformanceProfile::from_env();
+        if std::env::var_os(NO_FLASH_ENV).is_some() {
+            options.flash = None;
+        }
+        options
+    }
+}
+
+// FNV-1a of the trimmed, uppercased text, None if that's empty. The HUD only
+// shows uppercase, so a seed typed back in from a screenshot gives the same run.
+pub fn seed_from_str(seed: &str) -> Option<u64> {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let seed = seed.trim().to_uppercase();
+    if seed.is_empty() {
+        return None;
+    }
+    Some(seed.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    }))
+}
+
+// Everything needed to replay a run from a bug report
+fn diagnostic_line(seed_name: &str, wave: u32) -> String {
+    format!(
+        "V{}  SEED {seed_name}  WAVE {wave}",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+fn random_seed_name() -> String {
+    (0..SEED_NAME_LEN)
+        .map(|_| char::from(SEED_NAME_CHARS[fastrand::usize(..SEED_NAME_CHARS.len())]))
+        .collect()
+}
+
+// None if any of the comma separated scores isn't a number
+fn parse_scores(value: &str) -> Option<Vec<u32>> {
+    value
+        .split(',')
+        .map(|score| score.trim().parse().ok())
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum GameInitError {
+    Surface(SurfaceCreationError),
+    Texture(TextureError),
+}
+
+impl std::fmt::Display for GameInitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameInitError::Surface(err) => write!(f, "{err}"),
+            GameInitError::Texture(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for GameInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameInitError::Surface(err) => Some(err),
+            GameInitError::Texture(err) => Some(err),
+        }
+    }
+}
+
+impl From<SurfaceCreationError> for GameInitError {
+    fn from(err: SurfaceCreationError) -> Self {
+        GameInitError::Surface(err)
+    }
+}
+
+impl From<TextureError> for GameInitError {
+    fn from(err: TextureError) -> Self {
+        GameInitError::Texture(err)
+    }
+}
+
+pub struct Game {
+    // None for a headless game, which only draws into targets it's handed
+    surface: Option<Surface>,
+    renderer: Renderer,
+    player: Player,
+    formation: Formation,
+    // Instead of the formation on boss waves, until its last part goes
+    boss: Option<Boss>,
+    // The boss's parts and shots
+    solid: SolidTexture,
+    bullets: Bullets,
+    enemy_bullets: Bullets,
+    power_ups: PowerUps,
+    state: GameState,
+    loading: Loading,
+    // Counts up from 1 each time the formation is cleared, or each scheduled wave
+    wave: u32,
+    // Replaces the next wave on clearing the last while it has waves left
+    schedule: Option<WaveSchedule>,
+    // Simulation seconds since the run started, for the schedule
+    run_time: f64,
+    pause_menu: PauseMenu,
+    title_menu: TitleMenu,
+    // Picked on the title screen, hits are ignored and the high score is left
+    // alone. Restarts keep it and start_wave.
+    practice: bool,
+    start_wave: u32,
+    instant_exit: bool,
+    autofire: bool,
+    // Picked Quit, the event loop exits once it sees this
+    quit_requested: bool,
+    input_map: InputMap,
+    text: TextRenderer,
+    // Shown on the HUD, seed is derived from it
+    seed_name: String,
+    seed: u64,
+    score: Score,
+    extra_lives: ExtraLives,
+    particles: Emitter,
+    flash: Option<ScreenFlash>,
+    // Kept between steps so its cells aren't reallocated every update
+    grid: SpatialGrid,
+    // Pushed during update, handed to the listening systems at its end
+    events: Events,
+    // None where there's no config dir, the high score then isn't kept
+    score_path: Option<PathBuf>,
+    settings: Settings,
+    // Like score_path, settings changes are then lost on exit
+    settings_path: Option<PathBuf>,
+    fps: FpsCounter,
+    show_fps: bool,
+    diagnostics: bool,
+    instanced: bool,
+    // F4, a magnified view around the ship in the corner
+    zoom_inset: bool,
+    #[cfg(feature = "debug")]
+    latency: InputLatency,
+    internal_resolution: PhysicalSize<u32>,
+    #[cfg(feature = "audio")]
+    audio: Audio,
+}
+
+impl Game {
+    pub fn new(
+        context: &Context,
+        window: Arc<Window>,
+        textures: &mut TextureCache,
+        sampler: &Arc<wgpu::Sampler>,
+        options: GameOptions,
+    ) -> Result<Self, GameInitError> {
+        let profile = options
+            .profile
+            .unwrap_or_else(|| PerformanceProfile::detect(context.adapter.get_info().device_type));
+        let surface = Surface::new(context, window, profile.msaa_samples())?;
+        let config = surface.config().clone();
+        let sample_count = surface.sample_count();
+        let internal_resolution = internal_resolution_for(surface.scale_factor(), profile);
+        Self::init(
+            context,
+            Some(surface),
+            &config,
+            RendererOptions {
+                sample_count,
+                internal_resolution: Some(internal_resolution),
+                ..Default::default()
+            },
+            textures,
+            sampler,
+            GameOptions {
+                profile: Some(profile),
+                ..options
+            },
+        )
+    }
+
+    // Without a window, for driving frames from tests. config only provides the
+    // size and format of the targets handed to draw. Unlike new this starts on
+    // the title, with every image already loaded.
+    #[cfg(test)]
+    pub fn headless(
+        context: &Context,
+        config: &wgpu::SurfaceConfiguration,
+        textures: &mut TextureCache,
+        sampler: &Arc<wgpu::Sampler>,
+        options: GameOptions,
+    ) -> Result<Self, GameInitError> {
+        let mut game = Self::init(
+            context,
+            None,
+            config,
+            RendererOptions {
+                internal_resolution: Some(INTERNAL_RESOLUTION),
+                ..Default::default()
+            },
+            textures,
+            sampler,
+            options,
+        )?;
+        // Tests start on the title, not waiting on the decoding thread
+        game.loading.wait(context)?;
+        game.state = GameState::Menu;
+        Ok(game)
+    }
+
+    fn init(
+        context: &Context,
+        surface: Option<Surface>,
+        config: &wgpu::SurfaceConfiguration,
+        renderer_options: RendererOptions,
+        textures: &mut TextureCache,
+        sampler: &Arc<wgpu::Sampler>,
+        options: GameOptions,
+    ) -> Result<Self, GameInitError> {
+        let mut renderer = Renderer::init_with(context, config, textures, renderer_options);
+        renderer.set_clear_color(SPACE_COLOR);
+        // The ship and invaders are built on its blank texture while it decodes
+        let mut loading = Loading::default();
+        textures.get_or_embedded_in_background(
+            context,
+            "player.png",
+            assets::PLAYER_PNG,
+            &mut loading,
+        )?;
+        let mut player = Player::init(context, textures, sampler, &renderer, options.player)?;
+        let shaders = renderer.prewarm(context, &[SHIELD_SHADER]);
+        player.set_shield_shader(shaders[0]);
+        let seed_name = match options.seed {
+            Some(name) if seed_from_str(&name).is_some() => name.trim().to_uppercase(),
+            _ => random_seed_name(),
+        };
+        let seed = seed_from_str(&seed_name).unwrap_or_default();
+        let formation = Formation::new(
+            FORMATION_ROWS,
+            FORMATION_COLS,
+            FormationOptions {
+                seed: Some(seed),
+                ..options.formation
+            },
+            context,
+            textures,
+            sampler.clone(),
+            &renderer,
+        )?;
+
+        let score_path = Score::default_path();
+        let score = score_path.as_ref().map(Score::load).unwrap_or_default();
+        let settings_path = Settings::default_path();
+        let settings = settings_path
+            .as_ref()
+            .map(Settings::load)
+            .unwrap_or_default();
+        #[cfg(feature = "audio")]
+        let mut audio = Audio::new(AudioOptions::default());
+        #[cfg(feature = "audio")]
+        audio.set_volumes(settings.sfx_gain(), settings.music_gain());
+
+        Ok(Self {
+            surface,
+            renderer,
+            player,
+            formation,
+            bullets: Bullets::new(),
+            enemy_bullets: Bullets::new(),
+            boss: None,
+            solid: textures.solid(context),
+            power_ups: PowerUps::new(textures.solid(context), seed),
+            state: GameState::Loading,
+            loading,
+            wave: 1,
+            schedule: None,
+            run_time: 0.0,
+            pause_menu: PauseMenu::default(),
+            title_menu: TitleMenu::default(),
+            practice: false,
+            start_wave: 1,
+            instant_exit: std::env::var_os(INSTANT_EXIT_ENV).is_some(),
+            autofire: std::env::var_os(AUTOFIRE_ENV).is_some(),
+            quit_requested: false,
+            input_map: InputMap::from_env(),
+            text: TextRenderer::new(context),
+            seed_name,
+            seed,
+            score,
+            extra_lives: ExtraLives::new(options.extra_life_scores),
+            particles: Emitter::new(options.profile.unwrap_or_default().explosion_particles()),
+            flash: options.flash.map(ScreenFlash::new),
+            grid: SpatialGrid::new(collision::DEFAULT_CELL_SIZE),
+            events: Events::default(),
+            score_path,
+            settings,
+            settings_path,
+            fps: FpsCounter::default(),
+            show_fps: std::env::var_os(SHOW_FPS_ENV).is_some(),
+            diagnostics: std::env::var_os(DIAGNOSTICS_ENV).is_some(),
+            instanced: std::env::var_os(INSTANCED_ENV).is_some(),
+            zoom_inset: false,
+            #[cfg(feature = "debug")]
+            latency: InputLatency::default(),
+            internal_resolution: renderer_options
+                .internal_resolution
+                .unwrap_or(INTERNAL_RESOLUTION),
+            #[cfg(feature = "audio")]
+            audio,
+        })
+    }
+
+    // Waves spawn at the schedule's times instead, the first one replacing the
+    // formation the game starts with. Clearing a wave early leaves the play
+    // area empty until the next one, waves after the schedule's last come as usual.
+    pub fn with_schedule(mut self, schedule: WaveSchedule) -> Self {
+        self.formation.clear();
+        self.wave = 0;
+        self.schedule = Some(schedule);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    // Only a game being played advances, see update
+    pub fn is_running(&self) -> bool {
+        self.state == GameState::Playing
+    }
+
+    pub fn wants_to_quit(&self) -> bool {
+        self.quit_requested
+    }
+
+    // Once per redraw, whether or not the frame ends up presented
+    pub fn record_frame(&mut self) {
+        self.fps.tick();
+    }
+
+    pub fn toggle_fps_display(&mut self) {
+        self.show_fps = !self.show_fps;
+    }
+
+    pub fn toggle_zoom_inset(&mut self) {
+        self.zoom_inset = !self.zoom_inset;
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.settings.toggle_mute();
+        self.settings_changed();
+    }
+
+    // step is added to the master volume, which unmutes
+    pub fn change_volume(&mut self, step: f32) {
+        let volume = self.settings.master_volume() + step;
+        self.settings.set_master_volume(volume);
+        self.settings_changed();
+    }
+
+    // Heard straight away and kept for the next run
+    fn settings_changed(&mut self) {
+        #[cfg(feature = "audio")]
+        self.audio
+            .set_volumes(self.settings.sfx_gain(), self.settings.music_gain());
+        let Some(path) = self.settings_path.as_ref() else {
+            return;
+        };
+        if let Err(err) = self.settings.save(path) {
+            eprintln!("Could not save settings to {}: {err}", path.display());
+        }
+    }
+
+    #[cfg(test)]
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+
+    #[cfg(test)]
+    pub fn score(&self) -> u32 {
+        self.score.current()
+    }
+
+    #[cfg(test)]
+    pub fn invader_count(&self) -> usize {
+        self.formation.sprites().len()
+    }
+
+    #[cfg(test)]
+    pub fn boss_parts(&self) -> usize {
+        self.boss.as_ref().map_or(0, |boss| boss.sprites().len())
+    }
+
+    // Kills the whole formation at once, as the last shot of a wave would
+    #[cfg(test)]
+    pub fn clear_wave(&mut self) {
+        let targets: Vec<Rect> = self
+            .formation
+            .sprites()
+            .iter()
+            .map(|sprite| sprite.rect())
+            .collect();
+        let all: Vec<usize> = (0..targets.len()).collect();
+        self.formation.destroy(&all);
+        for event in kill_events(&targets, &all, 0, WAVE_BONUS) {
+            self.events.push(event);
+        }
+    }
+
+    // As an enemy bullet reaching the ship would
+    #[cfg(test)]
+    pub fn hit_player(&mut self, context: &Context) {
+        self.player.hit(context);
+    }
+
+    // Ends the run as losing the last life would
+    #[cfg(test)]
+    pub fn end_run(&mut self) {
+        self.game_over();
+    }
+
+    #[cfg(test)]
+    pub fn invader_positions(&self) -> Vec<nalgebra_glm::Vec2> {
+        self.formation
+            .sprites()
+            .iter()
+            .map(|sprite| sprite.position())
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub fn shot_count(&self) -> usize {
+        self.bullets.sprites().len()
+    }
+
+    #[cfg(test)]
+    pub fn lives(&self) -> u8 {
+        self.player.lives()
+    }
+
+    #[cfg(test)]
+    pub fn enemy_bullet_positions(&self) -> Vec<nalgebra_glm::Vec2> {
+        self.enemy_bullets
+            .sprites()
+            .iter()
+            .map(|sprite| sprite.position())
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub fn player_position(&self) -> nalgebra_glm::Vec2 {
+        self.player.get_sprite().position()
+    }
+
+    // Drains the input event and repeat queues, call once per frame. Holding the
+    // left mouse button steers the ship towards the cursor.
+    pub fn input_snapshot(&self) -> InputSnapshot {
+        let mut snapshot = self
+            .input_map
+            .snapshot(&input::take_events(), &input::take_repeats());
+        if let Some(surface) = self.surface.as_ref() {
+            let pointer = input::is_mouse_pressed(MouseButton::Left).then(|| {
+                self.renderer
+                    .screen_to_world(input::cursor_position(), surface.config())
+            });
+            snapshot.set_pointer(pointer);
+        }
+        // Only while playing, on the title screen it would start the game by itself
+        if self.autofire && self.state == GameState::Playing {
+            snapshot.merge(&InputSnapshot::pressing(&[Action::Fire]));
+        }
+        snapshot
+    }
+
+    pub fn request_resize(&mut self, size: PhysicalSize<u32>) {
+        if let Some(surface) = self.surface.as_mut() {
+            surface.request_resize(size);
+        }
+    }
+
+    pub fn scale_factor_changed(&mut self, scale_factor: f64, size: PhysicalSize<u32>) {
+        if let Some(surface) = self.surface.as_mut() {
+            surface.set_scale_factor(scale_factor, size);
+        }
+    }
+
+    // Switches between Fifo (vsync) and Immediate (uncapped), Immediate falls
+    // back to Fifo where the surface doesn't support it
+    pub fn toggle_vsync(&mut self, context: &Context) {
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+        let mode = match surface.present_mode() {
+            wgpu::PresentMode::Fifo => wgpu::PresentMode::Immediate,
+            _ => wgpu::PresentMode::Fifo,
+        };
+        surface.set_present_mode(context, mode);
+    }
+
+    // Fifo, Mailbox, Immediate, skipping modes the surface doesn't support
+    // None for a headless game
+    pub fn present_mode(&self) -> Option<wgpu::PresentMode> {
+        self.surface.as_ref().map(Surface::present_mode)
+    }
+
+    pub fn cycle_present_mode(&mut self, context: &Context) {
+        if let Some(surface) = self.surface.as_mut() {
+            let mode = surface.cycle_present_mode(context);
+            eprintln!("Present mode {mode:?}");
+        }
+    }
+
+    // One fixed step of the simulation, nothing moves unless the game is running
+    pub fn update(&mut self, context: &Context, input: &InputSnapshot, dt: f32) {
+        if self.state == GameState::Loading {
+            self.poll_loading(context);
+            return;
+        }
+        if !self.is_running() {
+            return;
+        }
+        // The ship and the shots stay under the HUD rather than going through it
+        self.run_time += f64::from(dt);
+        self.spawn_scheduled_wave(context);
+        let play_area = self.formation.play_area();
+        self.player.update(context, input, play_area, dt);
+        self.formation.update(context, dt);
+        if self.formation.has_landed() {
+            self.game_over();
+            return;
+        }
+        self.bullets.update(context, play_area, dt);
+        let player = self.player.get_sprite();
+        let target = player.position() + player.size() / 2.0;
+        if let Some(bullet) = self.formation.try_fire(context, &self.renderer, target, dt) {
+            self.enemy_bullets.push(bullet);
+        }
+        if let Some(boss) = self.boss.as_mut() {
+            boss.update(context, dt);
+            for bullet in boss.try_fire(context, &self.renderer, dt) {
+                self.enemy_bullets.push(bullet);
+            }
+        }
+        self.enemy_bullets.update(context, play_area, dt);
+        self.power_ups.update(context, play_area, dt);
+        self.particles.update(dt);
+        if let Some(flash) = self.flash.as_mut() {
+            flash.update(dt);
+        }
+        #[cfg(feature = "audio")]
+        self.audio.update(dt);
+        self.resolve_bullet_hits(context);
+        self.resolve_boss_hits(context);
+        self.resolve_player_hits(context);
+        self.collect_power_ups();
+        self.dispatch_events(context);
+    }
+
+    fn spawn_scheduled_wave(&mut self, context: &Context) {
+        let Some(wave) = self
+            .schedule
+            .as_mut()
+            .and_then(|schedule| schedule.due(self.run_time))
+        else {
+            return;
+        };
+        self.wave += 1;
+        self.formation.set_wave(self.wave);
+        self.formation
+            .respawn_as(wave.rows, wave.cols, context, &self.renderer);
+    }
+
+    fn dispatch_events(&mut self, context: &Context) {
+        self.power_ups
+            .spawn_drops(self.events.iter(), context, &self.renderer);
+        for event in self.events.iter() {
+            self.particles.observe(event);
+            if let Some(flash) = self.flash.as_mut() {
+                flash.observe(event);
+            }
+        }
+        let scheduled = self
+            .schedule
+            .as_ref()
+            .is_some_and(|schedule| !schedule.is_done());
+        if !scheduled
+            && self
+                .events
+                .iter()
+                .any(|&event| event == GameEvent::WaveCleared)
+        {
+            self.next_wave(context);
+        }
+        self.score.apply(&self.events);
+        for _ in 0..self.extra_lives.reached(self.score.current()) {
+            self.player.add_life();
+        }
+        #[cfg(feature = "audio")]
+        self.audio.observe(&self.events);
+        self.events.clear();
+    }
+
+    // A failed decode ends the game the way a failed init would
+    fn poll_loading(&mut self, context: &Context) {
+        match self.loading.poll(context) {
+            Ok(true) => self.state = GameState::Menu,
+            Ok(false) => {}
+            Err(err) => {
+                eprintln!("{err}");
+                self.quit_requested = true;
+            }
+        }
+    }
+
+    // Just-pressed only holds for one frame, so these are checked once per
+    // frame rather than per fixed step
+    pub fn handle_input(&mut self, context: &Context, input: &InputSnapshot) {
+        #[cfg(feature = "debug")]
+        let before = (self.state, self.bullets.sprites().len());
+        self.apply_input(context, input);
+        // The first frame the input took effect in, a shot or a change of screen
+        #[cfg(feature = "debug")]
+        if (self.state, self.bullets.sprites().len()) != before {
+            self.latency.record(input.first_event(), Instant::now());
+        }
+    }
+
+    #[cfg(all(test, feature = "debug"))]
+    pub fn input_latency(&self) -> Option<Duration> {
+        self.latency.last()
+    }
+
+    fn apply_input(&mut self, context: &Context, input: &InputSnapshot) {
+        match self.state {
+            GameState::Loading => return,
+            // The press that starts the game doesn't also fire
+            GameState::Menu => {
+                match self.title_menu.handle_input(input) {
+                    Some(TitleChoice::Play) => self.state = GameState::Playing,
+                    Some(TitleChoice::Practice { wave }) => self.start_practice(context, wave),
+                    None => {}
+                }
+                return;
+            }
+            GameState::Paused => {
+                self.handle_pause_menu(context, input);
+                return;
+            }
+            GameState::GameOver => {
+                if input.is_just_pressed(Action::Fire) {
+                    self.restart(context);
+                }
+                return;
+            }
+            GameState::Playing => {}
+        }
+        if self.instant_exit && input.is_just_pressed(Action::Menu) {
+            self.quit_requested = true;
+            return;
+        }
+        if input.is_just_pressed(Action::Pause) || input.is_just_pressed(Action::Menu) {
+            self.state = self.state.toggle_pause();
+            self.pause_menu.open();
+            return;
+        }
+        let shot = self.player.shoot(context, input, &self.renderer);
+        if shot.is_empty() {
+            return;
+        }
+        for bullet in shot {
+            self.bullets.push(bullet);
+        }
+        #[cfg(feature = "audio")]
+        self.audio.play(SoundId::Shoot);
+    }
+
+    // Pause or the menu key again resumes, the same as picking Resume
+    fn handle_pause_menu(&mut self, context: &Context, input: &InputSnapshot) {
+        if input.is_just_pressed(Action::Pause) || input.is_just_pressed(Action::Menu) {
+            self.state = self.state.toggle_pause();
+            return;
+        }
+        match self.pause_menu.handle_input(input) {
+            Some(MenuItem::Resume) => self.state = self.state.toggle_pause(),
+            Some(MenuItem::Restart) => self.restart(context),
+            Some(MenuItem::Quit) => self.quit_requested = true,
+            None => {}
+        }
+    }
+
+    // Returns how long presenting took, or None if the frame was skipped. Headless
+    // games have nothing to present and always skip.
+    pub fn render(&mut self, context: &Context) -> Result<Option<Duration>, wgpu::SurfaceError> {
+        let Some(mut surface) = self.surface.take() else {
+            return Ok(None);
+        };
+        let result = self.render_to_surface(context, &mut surface);
+        self.surface = Some(surface);
+        result
+    }
+
+    fn render_to_surface(
+        &mut self,
+        context: &Context,
+        surface: &mut Surface,
+    ) -> Result<Option<Duration>, wgpu::SurfaceError> {
+        if let Some(size) = surface.apply_pending_resize(context) {
+            self.renderer.resize(context, size.width, size.height);
+        }
+        let frame = match surface.next_texture() {
+            Ok(frame) => frame,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                surface.reconfigure(context);
+                return Ok(None);
+            }
+            Err(wgpu::SurfaceError::Timeout) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(surface.config().view_formats[0]),
+            ..Default::default()
+        });
+
+        self.draw(
+            context,
+            &surface.render_target(&view),
+            Some(surface.present_mode()),
+        );
+
+        let present_start = Instant::now();
+        frame.present();
+        Ok(Some(present_start.elapsed()))
+    }
+
+    // The whole frame, HUD included, into target. present_mode is shown next to
+    // the FPS when known.
+    pub fn draw(
+        &mut self,
+        context: &Context,
+        target: &RenderTarget,
+        present_mode: Option<wgpu::PresentMode>,
+    ) {
+        self.queue_hud(context, present_mode);
+        if self.zoom_inset {
+            self.draw_with_zoom_inset(context, target);
+            return;
+        }
+        self.renderer.draw_particles(self.particles.instances());
+        let instanced = self.instanced;
+        let (renderer, sprites) = self.scene();
+        if instanced {
+            renderer.render_instanced(target, context, sprites);
+        } else {
+            renderer.render(target, context, sprites);
+        }
+    }
+
+    // The current frame as a PNG, at the window's size. Headless games use the
+    // internal resolution.
+    pub fn save_screenshot(&mut self, context: &Context) {
+        let size = self
+            .surface
+            .as_ref()
+            .map_or(INTERNAL_RESOLUTION, |surface| {
+                PhysicalSize::new(surface.config().width, surface.config().height)
+            });
+        self.queue_hud(context, None);
+        self.renderer.draw_particles(self.particles.instances());
+        let (renderer, sprites) = self.scene();
+        let texture = renderer.render_to_texture(context, size, sprites);
+        let pixels = match texture::read_texture_to_rgba(context, &texture) {
+            Ok(pixels) => pixels,
+            Err(err) => {
+                eprintln!("Could not read back the screenshot: {err}");
+                return;
+            }
+        };
+
+        let dir = dirs::picture_dir().unwrap_or_else(|| PathBuf::from("."));
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = screenshot_path(&dir, secs);
+        match image::save_buffer(
+            &path,
+            &pixels,
+            size.width,
+            size.height,
+            image::ColorType::Rgba8,
+        ) {
+            Ok(()) => eprintln!("Saved screenshot {}", path.display()),
+            Err(err) => eprintln!("Could not save screenshot {}: {err}", path.display()),
+        }
+    }
+
+    // The whole frame, then the area around the ship again at twice the
+    // size. Without particles, which viewport passes don't draw.
+    fn draw_with_zoom_inset(&mut self, context: &Context, target: &RenderTarget) {
+        let size = self.internal_resolution;
+        let player = self.player.get_sprite();
+        let center = player.position() + player.size() / 2.0;
+        let (renderer, sprites) = self.scene();
+        let passes = [
+            ViewportPass::new(
+                [0, 0, size.width, size.height],
+                renderer::letterbox_projection(size.width, size.height),
+                sprites.clone(),
+            ),
+            ViewportPass::new(zoom_viewport(size), zoom_projection(center), sprites),
+        ];
+        renderer.render_viewports(target, context, &passes);
+    }
+
+    // Everything drawn in world space, with the renderer borrowed alongside
+    fn scene(&mut self) -> (&mut Renderer, Vec<&Sprite>) {
+        // Nothing to see until the textures are filled in
+        if self.state == GameState::Loading {
+            return (&mut self.renderer, self.text.sprites());
+        }
+        let mut sprites = self.formation.sprites();
+        sprites.extend(self.boss.iter().flat_map(Boss::sprites));
+        sprites.extend(self.bullets.sprites());
+        sprites.extend(self.enemy_bullets.sprites());
+        sprites.extend(self.power_ups.sprites());
+        sprites.push(self.player.get_sprite());
+        sprites.extend(self.player.shield_sprite());
+        sprites.extend(self.text.sprites());
+        (&mut self.renderer, sprites)
+    }
+
+    // The HUD text and the hit flash over it for the next frame
+    fn queue_hud(&mut self, context: &Context, present_mode: Option<wgpu::PresentMode>) {
+        self.text.clear();
+        if self.state == GameState::Loading {
+            self.text.draw_string(
+                context,
+                &self.renderer,
+                "LOADING",
+                LOADING_TEXT_POS,
+                TEXT_SCALE,
+            );
+            return;
+        }
+        let hud = format!(
+            "SCORE {:05}  HI {:05}",
+            self.score.current(),
+            self.score.high()
+        );
+        self.text
+            .draw_string(context, &self.renderer, &hud, SCORE_TEXT_POS, TEXT_SCALE);
+        let lives = if self.practice {
+            format!("PRACTICE  WAVE {}", self.wave)
+        } else {
+            format!("LIVES {}  WAVE {}", self.player.lives(), self.wave)
+        };
+        self.text
+            .draw_string(context, &self.renderer, &lives, LIVES_TEXT_POS, TEXT_SCALE);
+        if self.settings.is_muted() {
+            // Right-aligned on the score line
+            let x = crate::WORLD_WIDTH - SCORE_TEXT_POS.x - text::line_width("MUTED", TEXT_SCALE);
+            let pos = nalgebra_glm::vec2(x, SCORE_TEXT_POS.y);
+            self.text
+                .draw_string(context, &self.renderer, "MUTED", pos, TEXT_SCALE);
+        }
+        let seed = format!("SEED {}", self.seed_name);
+        // Right-aligned on the lives line
+        let x = crate::WORLD_WIDTH - LIVES_TEXT_POS.x - text::line_width(&seed, TEXT_SCALE);
+        let pos = nalgebra_glm::vec2(x, LIVES_TEXT_POS.y);
+        self.text
+            .draw_string(context, &self.renderer, &seed, pos, TEXT_SCALE);
+        if self.diagnostics {
+            let line = diagnostic_line(&self.seed_name, self.wave);
+            self.text.draw_string(
+                context,
+                &self.renderer,
+                &line,
+                DIAGNOSTICS_TEXT_POS,
+                TEXT_SCALE,
+            );
+        }
+        if self.show_fps {
+            let mut fps = format!(
+                "FPS {:.0} {:.1}MS",
+                self.fps.average_fps(),
+                self.fps.frame_time_ms()
+            );
+            if let Some(mode) = present_mode {
+                fps += &format!(" {mode:?}");
+            }
+            self.text
+                .draw_string(context, &self.renderer, &fps, FPS_TEXT_POS, TEXT_SCALE);
+        }
+        match self.state {
+            GameState::Loading => {}
+            GameState::Menu => self.text.draw_string(
+                context,
+                &self.renderer,
+                &self.title_menu.lines(),
+                MENU_TEXT_POS,
+                TEXT_SCALE,
+            ),
+            GameState::Paused => {
+                self.text.draw_string(
+                    context,
+                    &self.renderer,
+                    "PAUSED",
+                    PAUSED_TEXT_POS,
+                    TEXT_SCALE,
+                );
+                self.text.draw_string(
+                    context,
+                    &self.renderer,
+                    &self.pause_menu.lines(),
+                    PAUSE_MENU_POS,
+                    TEXT_SCALE,
+                );
+            }
+            GameState::GameOver => {
+                self.text.draw_string(
+                    context,
+                    &self.renderer,
+                    "GAME OVER",
+                    GAME_OVER_TEXT_POS,
+                    TEXT_SCALE,
+                );
+                self.text.draw_string(
+                    context,
+                    &self.renderer,
+                    "PRESS FIRE",
+                    RESTART_TEXT_POS,
+                    TEXT_SCALE,
+                );
+            }
+            GameState::Playing => {}
+        }
+        // Updates stop once the run is over, a flash from the last hit wouldn't fade
+        let flash = self
+            .flash
+            .as_ref()
+            .filter(|_| self.state != GameState::GameOver);
+        if let Some((rect, color)) = flash.and_then(ScreenFlash::overlay) {
+            self.renderer.draw_rect(rect, color);
+        }
+    }
+
+    // Any enemy bullet touching the player costs a life, the bullet is used up
+    // even while the player is invulnerable
+    fn resolve_player_hits(&mut self, context: &Context) {
+        let player = self.player.get_sprite();
+        let hits: Vec<usize> = self
+            .enemy_bullets
+            .sprites()
+            .iter()
+            .enumerate()
+            // Enemy bullets fly tilted, so test the quads as drawn
+            .filter(|(_, bullet)| bullet.touches(player.rect()))
+            .map(|(i, _)| i)
+            .collect();
+        if hits.is_empty() {
+            return;
+        }
+        self.enemy_bullets.destroy(&hits);
+
+        let center = player.position() + player.size() / 2.0;
+        if !self.player.hit(context) {
+            return;
+        }
+        self.events.push(GameEvent::PlayerHit { pos: center });
+        if !self.player.is_alive() {
+            self.game_over();
+        }
+    }
+
+    // Practice from wave on, the same run restarts the same way
+    fn start_practice(&mut self, context: &Context, wave: u32) {
+        self.practice = true;
+        self.start_wave = wave;
+        self.player.set_invincible(true);
+        self.score.set_practice(true);
+        self.restart(context);
+    }
+
+    fn collect_power_ups(&mut self) {
+        for kind in self.power_ups.collect(self.player.get_sprite().rect()) {
+            self.player.apply(kind);
+            self.events.push(GameEvent::PowerUpCollected { kind });
+        }
+    }
+
+    // A new run from the first wave, or practice's, the high score is all that's
+    // kept
+    fn restart(&mut self, context: &Context) {
+        self.score.reset();
+        self.extra_lives.reset();
+        if let Some(flash) = self.flash.as_mut() {
+            flash.clear();
+        }
+        self.player.reset(context);
+        self.formation.reseed(self.seed);
+        self.power_ups.reseed(self.seed);
+        self.power_ups.clear();
+        self.bullets.clear();
+        self.enemy_bullets.clear();
+        self.run_time = 0.0;
+        match self.schedule.as_mut() {
+            Some(schedule) => {
+                schedule.reset();
+                self.formation.clear();
+                self.boss = None;
+                self.wave = 0;
+            }
+            None => {
+                self.wave = self.start_wave;
+                self.spawn_wave(context);
+            }
+        }
+        self.state = GameState::Playing;
+    }
+
+    // Shots still in the air carry over into the new wave
+    fn next_wave(&mut self, context: &Context) {
+        self.wave += 1;
+        self.spawn_wave(context);
+    }
+
+    // A formation for self.wave, or a boss on its own on boss waves
+    fn spawn_wave(&mut self, context: &Context) {
+        self.formation.set_wave(self.wave);
+        if boss::is_boss_wave(self.wave) {
+            self.formation.clear();
+            let area = self.formation.play_area();
+            self.boss = Some(Boss::new(self.solid.clone(), area, context, &self.renderer));
+        } else {
+            self.boss = None;
+            self.formation.respawn(context, &self.renderer);
+        }
+    }
+
+    fn game_over(&mut self) {
+        self.state = GameState::GameOver;
+        self.save_high_score();
+    }
+
+    pub fn save_high_score(&self) {
+        let Some(path) = self.score_path.as_ref() else {
+            return;
+        };
+        if let Err(err) = self.score.save(path) {
+            eprintln!("Could not save high score to {}: {err}", path.display());
+        }
+    }
+
+    // Uses up every bullet that hit an enemy, enemies out of health are removed
+    fn resolve_bullet_hits(&mut self, context: &Context) {
+        let targets: Vec<Rect> = self
+            .formation
+            .sprites()
+            .iter()
+            .map(|sprite| sprite.rect())
+            .collect();
+        let hits = collision::find_hits(&mut self.grid, &targets, &self.bullets.swept());
+        if hits.is_empty() {
+            return;
+        }
+
+        let (hit, shots): (Vec<usize>, Vec<usize>) = hits.into_iter().unzip();
+        self.bullets.destroy(&shots);
+        // Only enemies out of health score and explode
+        let killed = self.formation.damage(&hit, context);
+        self.formation.destroy(&killed);
+        let remaining = self.formation.sprites().len();
+        for event in kill_events(&targets, &killed, remaining, WAVE_BONUS) {
+            self.events.push(event);
+        }
+    }
+
+    // Like resolve_bullet_hits, for each part the boss has left
+    fn resolve_boss_hits(&mut self, context: &Context) {
+        let Some(boss) = self.boss.as_mut() else {
+            return;
+        };
+        let targets: Vec<Rect> = boss.sprites().iter().map(|sprite| sprite.rect()).collect();
+        let hits = collision::find_hits(&mut self.grid, &targets, &self.bullets.swept());
+        if hits.is_empty() {
+            return;
+        }
+
+        let (hit, shots): (Vec<usize>, Vec<usize>) = hits.into_iter().unzip();
+        self.bullets.destroy(&shots);
+        let killed = boss.damage(&hit, context);
+        boss.destroy(&killed);
+        for event in kill_events(&targets, &killed, boss.sprites().len(), boss::BOSS_BONUS) {
+            self.events.push(event);
+        }
+        if boss.is_beaten() {
+            self.boss = None;
+        }
+    }
+}
+
+// HiDPI monitors get a whole multiple of INTERNAL_RESOLUTION per scale step, so
+// the upscale to their extra pixels stays sharp, up to the profile's cap. Picked
+// once, when the window opens.
+fn internal_resolution_for(scale_factor: f64, profile: PerformanceProfile) -> PhysicalSize<u32> {
+    let multiple = (scale_factor.round().max(1.0) as u32).min(profile.max_render_scale());
+    PhysicalSize::new(
+        INTERNAL_RESOLUTION.width * multiple,
+        INTERNAL_RESOLUTION.height * multiple,
+    )
+}
+
+// A third of the frame each way in its top right corner, viewports count y down
+fn zoom_viewport(frame: PhysicalSize<u32>) -> [u32; 4] {
+    let width = frame.width / 3;
+    let height = frame.height / 3;
+    [
+        frame.width - width - ZOOM_MARGIN,
+        ZOOM_MARGIN,
+        width,
+        height,
+    ]
+}
+
+// The world around center, ZOOM_SPAN across, squeezed into the inset. The inset
+// has the world's aspect, so nothing is stretched.
+fn zoom_projection(center: nalgebra_glm::Vec2) -> nalgebra_glm::Mat4 {
+    let min = center - ZOOM_SPAN / 2.0;
+    let max = center + ZOOM_SPAN / 2.0;
+    nalgebra_glm::ortho_rh_zo(min.x, max.x, min.y, max.y, -1.0, 1.0)
+}
+
+// One file per second, a second screenshot within it replaces the first
+fn screenshot_path(dir: &Path, unix_secs: u64) -> PathBuf {
+    dir.join(format!("vaders-{unix_secs}.png"))
+}
+
+// What killing the targets at killed leaves behind, remaining is how many
+// invaders or boss parts survive. Killing the last one is worth bonus.
+fn kill_events(targets: &[Rect], killed: &[usize], remaining: usize, bonus: u32) -> Vec<GameEvent> {
+    let mut events: Vec<GameEvent> = killed
+        .iter()
+        .map(|&i| GameEvent::InvaderKilled {
+            pos: targets[i].pos + targets[i].size / 2.0,
+        })
+        .collect();
+    if !killed.is_empty() && remaining == 0 {
+        events.push(GameEvent::WaveCleared);
+        events.push(GameEvent::BonusAwarded { points: bonus });
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(x: f32) -> Rect {
+        Rect {
+            pos: nalgebra_glm::vec2(x, 100.0),
+            size: nalgebra_glm::vec2(10.0, 8.0),
+        }
+    }
+
+    #[test]
+    fn a_kill_reaches_the_particle_and_audio_listeners() {
+        let targets = [target(0.0), target(20.0)];
+        let mut events = Events::default();
+        for event in kill_events(&targets, &[1], 1, WAVE_BONUS) {
+            events.push(event);
+        }
+        assert_eq!(
+            events.iter().collect::<Vec<_>>(),
+            [&GameEvent::InvaderKilled {
+                pos: nalgebra_glm::vec2(25.0, 104.0)
+            }]
+        );
+
+        let mut particles = Emitter::new(PerformanceProfile::High.explosion_particles());
+        for event in events.iter() {
+            particles.observe(event);
+        }
+        assert!(particles.instances().count() > 0);
+        #[cfg(feature = "audio")]
+        assert_eq!(
+            crate::audio::sounds_for(&events),
+            [crate::audio::SoundId::Explosion]
+        );
+    }
+
+    #[test]
+    fn the_last_kill_clears_the_wave_with_a_bonus() {
+        let events = kill_events(&[target(0.0)], &[0], 0, WAVE_BONUS);
+        assert_eq!(
+            events[1..],
+            [
+                GameEvent::WaveCleared,
+                GameEvent::BonusAwarded { points: WAVE_BONUS }
+            ]
+        );
+        assert!(kill_events(&[target(0.0)], &[], 0, WAVE_BONUS).is_empty());
+    }
+
+    #[test]
+    fn a_boss_is_only_beaten_with_its_last_part() {
+        let parts = [target(0.0), target(20.0), target(40.0)];
+        let events = kill_events(&parts, &[1], 2, boss::BOSS_BONUS);
+        assert_eq!(events.len(), 1);
+        assert!(!events.contains(&GameEvent::WaveCleared));
+
+        let events = kill_events(&parts, &[0, 2], 0, boss::BOSS_BONUS);
+        assert_eq!(
+            events[2..],
+            [
+                GameEvent::WaveCleared,
+                GameEvent::BonusAwarded {
+                    points: boss::BOSS_BONUS
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn kills_in_one_update_chain() {
+        let mut events = Events::default();
+        let mut score = Score::default();
+        for event in kill_events(
+            &[target(0.0), target(20.0), target(40.0)],
+            &[0, 1],
+            1,
+            WAVE_BONUS,
+        ) {
+            events.push(event);
+        }
+        score.apply(&events);
+        assert_eq!(score.current(), 2 * 2 * crate::score::POINTS_PER_INVADER);
+    }
+
+    #[test]
+    fn seed_strings_hash_to_the_same_seed() {
+        assert_eq!(seed_from_str("vaders123"), seed_from_str("vaders123"));
+        assert_eq!(seed_from_str(" vaders123 "), seed_from_str("VADERS123"));
+        assert_ne!(seed_from_str("vaders123"), seed_from_str("vaders124"));
+        assert_eq!(seed_from_str(" "), None);
+
+        // The same seed, the same shooters
+        let shots = |seed| {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            (0..8).map(|_| rng.usize(..11)).collect::<Vec<_>>()
+        };
+        let seed = seed_from_str("vaders123").unwrap();
+        assert_eq!(shots(seed), shots(seed));
+
+        let name = random_seed_name();
+        assert_eq!(name.len(), SEED_NAME_LEN);
+        assert!(seed_from_str(&name).is_some());
+    }
+
+    #[test]
+    fn the_diagnostic_line_has_the_seed_and_wave() {
+        let line = diagnostic_line("VADERS123", 4);
+        assert!(line.contains("SEED VADERS123"), "{line}");
+        assert!(line.contains("WAVE 4"), "{line}");
+        assert!(line.starts_with(&format!("V{}", env!("CARGO_PKG_VERSION"))));
+    }
+
+    #[test]
+    fn extra_life_scores_parse_from_a_list() {
+        assert_eq!(parse_scores("1000, 3000"), Some(vec![1000, 3000]));
+        assert_eq!(parse_scores("1000,lots"), None);
+    }
+
+    #[test]
+    fn hidpi_scale_factors_multiply_the_internal_resolution() {
+        let high = PerformanceProfile::High;
+        assert_eq!(internal_resolution_for(1.0, high), INTERNAL_RESOLUTION);
+        assert_eq!(internal_resolution_for(0.75, high), INTERNAL_RESOLUTION);
+        assert_eq!(
+            internal_resolution_for(2.0, high),
+            PhysicalSize::new(916, 760)
+        );
+        assert_eq!(internal_resolution_for(1.25, high), INTERNAL_RESOLUTION);
+        assert_eq!(
+            internal_resolution_for(1.5, high),
+            PhysicalSize::new(916, 760)
+        );
+    }
+
+    #[test]
+    fn low_profiles_keep_the_base_internal_resolution() {
+        let low = PerformanceProfile::Low;
+        assert_eq!(internal_resolution_for(2.0, low), INTERNAL_RESOLUTION);
+        assert_eq!(
+            internal_resolution_for(3.0, PerformanceProfile::Medium),
+            PhysicalSize::new(916, 760)
+        );
+    }
+
+    #[test]
+    fn the_zoom_inset_sits_in_the_top_right_corner_around_the_ship() {
+        assert_eq!(
+            zoom_viewport(INTERNAL_RESOLUTION),
+            [458 - 152 - ZOOM_MARGIN, ZOOM_MARGIN, 152, 126]
+        );
+
+        let center = nalgebra_glm::vec2(100.0, 50.0);
+        let projection = zoom_projection(center);
+        let ndc = |x: f32, y: f32| {
+            let clip = projection * nalgebra_glm::vec4(x, y, 0.0, 1.0);
+            nalgebra_glm::vec2(clip.x, clip.y)
+        };
+        assert_eq!(ndc(center.x, center.y), nalgebra_glm::vec2(0.0, 0.0));
+        let corner = center + ZOOM_SPAN / 2.0;
+        assert!(
+            nalgebra_glm::distance(&ndc(corner.x, corner.y), &nalgebra_glm::vec2(1.0, 1.0)) < 1e-5
+        );
+    }
+
+    #[test]
+    fn screenshots_are_named_after_the_time_taken() {
+        assert_eq!(
+            screenshot_path(Path::new("/pictures"), 1700000000),
+            Path::new("/pictures/vaders-1700000000.png")
+        );
+    }
+
+    #[test]
+    fn pause_toggles_only_a_running_game() {
+        assert_eq!(GameState::Playing.toggle_pause(), GameState::Paused);
+        assert_eq!(GameState::Paused.toggle_pause(), GameState::Playing);
+        assert_eq!(GameState::Menu.toggle_pause(), GameState::Menu);
+        assert_eq!(GameState::GameOver.toggle_pause(), GameState::GameOver);
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gilrs::{Axis, Button, Gilrs};
+
+use crate::input::Action;
+
+// Stick deflection below this on an axis is treated as resting
+pub const DEFAULT_DEAD_ZONE: f32 = 0.25;
+// Overrides the dead zone, e.g. for a worn stick that drifts
+const DEAD_ZONE_ENV: &str = "VADERS_DEAD_ZONE";
+
+const ACTION_COUNT: usize = Action::ALL.len();
+
+// Combined state of every connected gamepad, read by InputMap alongside the keyboard
+static ACTIONS: [AtomicBool; ACTION_COUNT] = [const { AtomicBool::new(false) }; ACTION_COUNT];
+// Snapshot of ACTIONS taken by poll, used for edge detection
+static PREV_ACTIONS: [AtomicBool; ACTION_COUNT] = [const { AtomicBool::new(false) }; ACTION_COUNT];
+
+pub struct Gamepads {
+    gilrs: Gilrs,
+    dead_zone: f32,
+}
+
+impl Gamepads {
+    // None if the platform's gamepad backend isn't available
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => {
+                let mut gamepads = Self {
+                    gilrs,
+                    dead_zone: DEFAULT_DEAD_ZONE,
+                };
+                if let Some(dead_zone) = dead_zone_from_env() {
+                    gamepads.set_dead_zone(dead_zone);
+                }
+                Some(gamepads)
+            }
+            Err(err) => {
+                eprintln!("Gamepads unavailable: {err}");
+                None
+            }
+        }
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: f32) {
+        self.dead_zone = dead_zone.clamp(0.0, 1.0);
+    }
+
+    // Call once per frame before the game reads input
+    pub fn poll(&mut self) {
+        // Draining the events is what updates gilrs' cached gamepad state
+        while self.gilrs.next_event().is_some() {}
+
+        let mut pressed = [false; ACTION_COUNT];
+        for (_, gamepad) in self.gilrs.gamepads() {
+            let stick = stick_actions(
+                gamepad.value(Axis::LeftStickX),
+                gamepad.value(Axis::LeftStickY),
+                self.dead_zone,
+            );
+            let buttons = [
+                (Action::MoveLeft, Button::DPadLeft),
+                (Action::MoveRight, Button::DPadRight),
+                (Action::MoveUp, Button::DPadUp),
+                (Action::MoveDown, Button::DPadDown),
+                (Action::Fire, Button::South),
+                (Action::Pause, Button::Start),
+                (Action::Menu, Button::Select),
+            ];
+            for action in stick {
+                pressed[action as usize] = true;
+            }
+            for (action, button) in buttons {
+                pressed[action as usize] |= gamepad.is_pressed(button);
+            }
+        }
+
+        for (i, pressed) in pressed.into_iter().enumerate() {
+            PREV_ACTIONS[i].store(ACTIONS[i].load(Ordering::Relaxed), Ordering::Relaxed);
+            ACTIONS[i].store(pressed, Ordering::Relaxed);
+        }
+    }
+}
+
+fn dead_zone_from_env() -> Option<f32> {
+    let value = std::env::var(DEAD_ZONE_ENV).ok()?;
+    let dead_zone = parse_dead_zone(&value);
+    if dead_zone.is_none() {
+        eprintln!("Ignoring {DEAD_ZONE_ENV} {value:?}, expected a number");
+    }
+    dead_zone
+}
+
+fn parse_dead_zone(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|dead_zone: &f32| dead_zone.is_finite())
+}
+
+// Stick up is positive y in gilrs, matching the world's y axis
+fn stick_actions(x: f32, y: f32, dead_zone: f32) -> impl Iterator<Item = Action> {
+    [
+        (x < -dead_zone, Action::MoveLeft),
+        (x > dead_zone, Action::MoveRight),
+        (y > dead_zone, Action::MoveUp),
+        (y < -dead_zone, Action::MoveDown),
+    ]
+    .into_iter()
+    .filter_map(|(active, action)| active.then_some(action))
+}
+
+pub fn is_action_pressed(action: Action) -> bool {
+    ACTIONS[action as usize].load(Ordering::Relaxed)
+}
+
+pub fn is_action_just_pressed(action: Action) -> bool {
+    is_action_pressed(action) && !PREV_ACTIONS[action as usize].load(Ordering::Relaxed)
+}
+
+pub fn is_action_just_released(action: Action) -> bool {
+    !is_action_pressed(action) && PREV_ACTIONS[action as usize].load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stick_inside_the_dead_zone_is_resting() {
+        assert_eq!(stick_actions(0.2, -0.2, 0.25).count(), 0);
+        assert_eq!(
+            stick_actions(-0.3, 0.9, 0.25).collect::<Vec<_>>(),
+            [Action::MoveLeft, Action::MoveUp]
+        );
+        assert_eq!(
+            stick_actions(0.5, -0.1, 0.25).collect::<Vec<_>>(),
+            [Action::MoveRight]
+        );
+        // A zero dead zone still ignores a centered stick
+        assert_eq!(stick_actions(0.0, 0.0, 0.0).count(), 0);
+    }
+
+    #[test]
+    fn dead_zone_overrides_must_be_numbers() {
+        assert_eq!(parse_dead_zone(" 0.1 "), Some(0.1));
+        assert_eq!(parse_dead_zone("NaN"), None);
+        assert_eq!(parse_dead_zone("loose"), None);
+    }
+}
@@ -1,17 +1,727 @@
-use winit::{event::ElementState, keyboard::KeyCode};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
 
-static mut KEYS: [bool; 256] = [false; 256];
+use winit::{
+    event::{ElementState, MouseButton},
+    keyboard::KeyCode,
+};
+
+// Oldest events are dropped past this so an undrained queue can't grow forever,
+// applies to both the event and the repeat queue
+const MAX_QUEUED_EVENTS: usize = 256;
+// Comma separated rebindings on top of the defaults, e.g.
+// "MoveLeft=A,MoveRight=D,Fire=Enter", see parse_binding
+const KEYS_ENV: &str = "VADERS_KEYS";
+
+static KEYS: [AtomicBool; 256] = [const { AtomicBool::new(false) }; 256];
+// Snapshot of KEYS taken by new_frame, used for edge detection
+static PREV_KEYS: [AtomicBool; 256] = [const { AtomicBool::new(false) }; 256];
+static MOUSE_BUTTONS: [AtomicBool; 16] = [const { AtomicBool::new(false) }; 16];
+// Physical pixels, as delivered by winit
+static CURSOR: Mutex<(f64, f64)> = Mutex::new((0.0, 0.0));
+static FOCUSED: AtomicBool = AtomicBool::new(true);
+static EVENTS: Mutex<VecDeque<InputEvent>> = Mutex::new(VecDeque::new());
+static REPEATS: Mutex<VecDeque<KeyCode>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputEvent {
+    pub key: KeyCode,
+    pub state: ElementState,
+    pub time: Instant,
+}
 
 pub fn register_key_state(key: KeyCode, state: ElementState) {
-    unsafe {
-        KEYS[key as usize] = match state {
-            ElementState::Pressed => true,
-            ElementState::Released => false,
-        };
+    KEYS[key as usize].store(state.is_pressed(), Ordering::Relaxed);
+
+    let mut events = EVENTS.lock().unwrap();
+    if events.len() == MAX_QUEUED_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(InputEvent {
+        key,
+        state,
+        time: Instant::now(),
+    });
+}
+
+// Drains every press/release since the last call, in the order they happened
+pub fn take_events() -> Vec<InputEvent> {
+    EVENTS.lock().unwrap().drain(..).collect()
+}
+
+// OS auto-repeat while a key is held, for menus and text entry
+pub fn register_key_repeat(key: KeyCode) {
+    let mut repeats = REPEATS.lock().unwrap();
+    if repeats.len() == MAX_QUEUED_EVENTS {
+        repeats.pop_front();
     }
+    repeats.push_back(key);
+}
+
+pub fn take_repeats() -> Vec<KeyCode> {
+    REPEATS.lock().unwrap().drain(..).collect()
 }
 
 pub fn is_key_pressed(key: KeyCode) -> bool {
-    unsafe { KEYS[key as usize] }
+    KEYS[key as usize].load(Ordering::Relaxed)
+}
+
+pub fn is_key_just_pressed(key: KeyCode) -> bool {
+    is_key_pressed(key) && !PREV_KEYS[key as usize].load(Ordering::Relaxed)
+}
+
+pub fn is_key_just_released(key: KeyCode) -> bool {
+    !is_key_pressed(key) && PREV_KEYS[key as usize].load(Ordering::Relaxed)
+}
+
+// Call once per frame after the game has read input
+pub fn new_frame() {
+    for (prev, key) in PREV_KEYS.iter().zip(&KEYS) {
+        prev.store(key.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+}
+
+pub fn register_focus(focused: bool) {
+    FOCUSED.store(focused, Ordering::Relaxed);
+}
+
+pub fn is_window_focused() -> bool {
+    FOCUSED.load(Ordering::Relaxed)
+}
+
+pub fn register_cursor_moved(x: f64, y: f64) {
+    *CURSOR.lock().unwrap() = (x, y);
+}
+
+pub fn cursor_position() -> (f64, f64) {
+    *CURSOR.lock().unwrap()
+}
+
+pub fn register_mouse_button(button: MouseButton, state: ElementState) {
+    if let Some(index) = mouse_button_index(button) {
+        MOUSE_BUTTONS[index].store(state.is_pressed(), Ordering::Relaxed);
+    }
+}
+
+pub fn is_mouse_pressed(button: MouseButton) -> bool {
+    mouse_button_index(button).is_some_and(|index| MOUSE_BUTTONS[index].load(Ordering::Relaxed))
+}
+
+// Extra buttons past what MOUSE_BUTTONS can hold are ignored
+fn mouse_button_index(button: MouseButton) -> Option<usize> {
+    let index = match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Back => 3,
+        MouseButton::Forward => 4,
+        MouseButton::Other(other) => 5 + other as usize,
+    };
+    (index < MOUSE_BUTTONS.len()).then_some(index)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    Pause,
+    // Opens the pause menu, or quits with VADERS_INSTANT_EXIT
+    Menu,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::Fire,
+        Action::Pause,
+        Action::Menu,
+    ];
+
+    // The variant name, ignoring case
+    fn parse(name: &str) -> Option<Self> {
+        Action::ALL
+            .into_iter()
+            .find(|action| format!("{action:?}").eq_ignore_ascii_case(name))
+    }
+}
+
+// Logical actions to the key that triggers them, one key per action
+pub struct InputMap {
+    bindings: HashMap<Action, KeyCode>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::from([
+                (Action::MoveLeft, KeyCode::ArrowLeft),
+                (Action::MoveRight, KeyCode::ArrowRight),
+                (Action::MoveUp, KeyCode::ArrowUp),
+                (Action::MoveDown, KeyCode::ArrowDown),
+                (Action::Fire, KeyCode::Space),
+                (Action::Pause, KeyCode::KeyP),
+                (Action::Menu, KeyCode::Escape),
+            ]),
+        }
+    }
+}
+
+impl InputMap {
+    // The default bindings with VADERS_KEYS applied, entries that don't parse are
+    // skipped
+    pub fn from_env() -> Self {
+        let mut map = Self::default();
+        let Ok(value) = std::env::var(KEYS_ENV) else {
+            return map;
+        };
+        for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+            match parse_binding(entry) {
+                Some((action, key)) => map.bind(action, key),
+                None => eprintln!("Ignoring {KEYS_ENV} entry {entry:?}, expected Action=Key"),
+            }
+        }
+        map
+    }
+
+    // Everything the game reads this frame, taken once so every fixed step sees
+    // the same input and tests can hand the game input directly. events are the
+    // ones queued since the last snapshot, see take_events, repeats likewise.
+    pub fn snapshot(&self, events: &[InputEvent], repeats: &[KeyCode]) -> InputSnapshot {
+        let actions = Action::ALL.into_iter();
+        InputSnapshot {
+            pressed: actions
+                .clone()
+                .filter(|&action| self.is_action_pressed(action))
+                .collect(),
+            just_pressed: actions
+                .clone()
+                .filter(|&action| {
+                    self.is_action_just_pressed(action)
+                        || self.happened_in(action, ElementState::Pressed, events)
+                })
+                .collect(),
+            just_released: actions
+                .clone()
+                .filter(|&action| {
+                    self.is_action_just_released(action)
+                        || self.happened_in(action, ElementState::Released, events)
+                })
+                .collect(),
+            repeated: actions
+                .filter(|action| {
+                    self.bindings
+                        .get(action)
+                        .is_some_and(|key| repeats.contains(key))
+                })
+                .collect(),
+            pointer: None,
+            focused: is_window_focused(),
+            #[cfg(feature = "debug")]
+            first_event: events
+                .iter()
+                .filter(|event| self.bindings.values().any(|&key| key == event.key))
+                .map(|event| event.time)
+                .min(),
+        }
+    }
+
+    // A tap that was already released again by the end of the frame never shows
+    // up in the key state, only in the queue
+    fn happened_in(&self, action: Action, state: ElementState, events: &[InputEvent]) -> bool {
+        self.bindings.get(&action).is_some_and(|&key| {
+            events
+                .iter()
+                .any(|event| event.key == key && event.state == state)
+        })
+    }
+
+    // Replaces whatever key the action had before
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.bindings.insert(action, key);
+    }
+
+    // Gamepads are folded in here so callers don't care where input came from
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|&key| is_key_pressed(key))
+            || gamepad_action_pressed(action)
+    }
+
+    pub fn is_action_just_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|&key| is_key_just_pressed(key))
+            || gamepad_action_just_pressed(action)
+    }
+
+    pub fn is_action_just_released(&self, action: Action) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|&key| is_key_just_released(key))
+            || gamepad_action_just_released(action)
+    }
+}
+
+// "Fire=Enter" or "moveleft = KeyA", keys are letters, digits, arrows and a few
+// named keys
+fn parse_binding(entry: &str) -> Option<(Action, KeyCode)> {
+    let (action, key) = entry.split_once('=')?;
+    Some((Action::parse(action.trim())?, parse_key(key.trim())?))
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KeyA,
+        KeyCode::KeyB,
+        KeyCode::KeyC,
+        KeyCode::KeyD,
+        KeyCode::KeyE,
+        KeyCode::KeyF,
+        KeyCode::KeyG,
+        KeyCode::KeyH,
+        KeyCode::KeyI,
+        KeyCode::KeyJ,
+        KeyCode::KeyK,
+        KeyCode::KeyL,
+        KeyCode::KeyM,
+        KeyCode::KeyN,
+        KeyCode::KeyO,
+        KeyCode::KeyP,
+        KeyCode::KeyQ,
+        KeyCode::KeyR,
+        KeyCode::KeyS,
+        KeyCode::KeyT,
+        KeyCode::KeyU,
+        KeyCode::KeyV,
+        KeyCode::KeyW,
+        KeyCode::KeyX,
+        KeyCode::KeyY,
+        KeyCode::KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit0,
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+    let name = name.to_ascii_lowercase();
+    // winit's names work too, "KeyA" and "Digit1"
+    let short = name
+        .strip_prefix("key")
+        .or_else(|| name.strip_prefix("digit"))
+        .unwrap_or(&name);
+    if let [c] = short.as_bytes() {
+        return match c {
+            b'a'..=b'z' => Some(LETTERS[usize::from(c - b'a')]),
+            b'0'..=b'9' => Some(DIGITS[usize::from(c - b'0')]),
+            _ => None,
+        };
+    }
+    let key = match name.as_str() {
+        "space" => KeyCode::Space,
+        "enter" => KeyCode::Enter,
+        "escape" => KeyCode::Escape,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "left" | "arrowleft" => KeyCode::ArrowLeft,
+        "right" | "arrowright" => KeyCode::ArrowRight,
+        "up" | "arrowup" => KeyCode::ArrowUp,
+        "down" | "arrowdown" => KeyCode::ArrowDown,
+        "shift" | "shiftleft" => KeyCode::ShiftLeft,
+        "shiftright" => KeyCode::ShiftRight,
+        "ctrl" | "controlleft" => KeyCode::ControlLeft,
+        "controlright" => KeyCode::ControlRight,
+        "alt" | "altleft" => KeyCode::AltLeft,
+        "altright" => KeyCode::AltRight,
+        _ => return None,
+    };
+    Some(key)
+}
+
+// The actions held, newly pressed and let go in one frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputSnapshot {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+    // Held long enough for the OS to auto-repeat the key
+    repeated: HashSet<Action>,
+    // Where the mouse is held down, in world units
+    pointer: Option<nalgebra_glm::Vec2>,
+    focused: bool,
+    // When the oldest event for a bound key was registered
+    #[cfg(feature = "debug")]
+    first_event: Option<Instant>,
+}
+
+impl Default for InputSnapshot {
+    // Nothing held, in a focused window
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            repeated: HashSet::new(),
+            pointer: None,
+            focused: true,
+            #[cfg(feature = "debug")]
+            first_event: None,
+        }
+    }
+}
+
+impl InputSnapshot {
+    // The first frame of holding actions down, they count as pressed and just pressed
+    pub fn pressing(actions: &[Action]) -> Self {
+        Self {
+            pressed: actions.iter().copied().collect(),
+            just_pressed: actions.iter().copied().collect(),
+            ..Self::default()
+        }
+    }
+
+    // The frame actions are let go again
+    #[cfg(test)]
+    pub fn releasing(actions: &[Action]) -> Self {
+        Self {
+            just_released: actions.iter().copied().collect(),
+            ..Self::default()
+        }
+    }
+
+    // Everything other also holds, pressed or released counts as well. The
+    // pointer and focus stay this snapshot's.
+    pub fn merge(&mut self, other: &InputSnapshot) {
+        self.pressed.extend(&other.pressed);
+        self.just_pressed.extend(&other.just_pressed);
+        self.just_released.extend(&other.just_released);
+        self.repeated.extend(&other.repeated);
+    }
+
+    #[cfg(feature = "debug")]
+    pub fn first_event(&self) -> Option<Instant> {
+        self.first_event
+    }
+
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn is_just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn is_just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    // Just pressed or auto-repeating, for stepping through menus by holding a key
+    pub fn is_just_pressed_or_repeated(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action) || self.repeated.contains(&action)
+    }
+
+    pub fn pointer(&self) -> Option<nalgebra_glm::Vec2> {
+        self.pointer
+    }
+
+    // The input itself is in window pixels, only the game knows the mapping into
+    // the world
+    pub fn set_pointer(&mut self, pointer: Option<nalgebra_glm::Vec2>) {
+        self.pointer = pointer;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_action_pressed(action: Action) -> bool {
+    crate::gamepad::is_action_pressed(action)
 }
 
+#[cfg(not(feature = "gamepad"))]
+fn gamepad_action_pressed(_action: Action) -> bool {
+    false
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_action_just_pressed(action: Action) -> bool {
+    crate::gamepad::is_action_just_pressed(action)
+}
+
+#[cfg(not(feature = "gamepad"))]
+fn gamepad_action_just_pressed(_action: Action) -> bool {
+    false
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_action_just_released(action: Action) -> bool {
+    crate::gamepad::is_action_just_released(action)
+}
+
+#[cfg(not(feature = "gamepad"))]
+fn gamepad_action_just_released(_action: Action) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::MutexGuard;
+
+    // The input state is global, so tests touching it take turns
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn serial() -> MutexGuard<'static, ()> {
+        SERIAL
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn just_pressed_lasts_one_frame() {
+        let _guard = serial();
+        register_key_state(KeyCode::KeyF, ElementState::Pressed);
+        assert!(is_key_just_pressed(KeyCode::KeyF));
+
+        new_frame();
+        assert!(is_key_pressed(KeyCode::KeyF));
+        assert!(!is_key_just_pressed(KeyCode::KeyF));
+
+        register_key_state(KeyCode::KeyF, ElementState::Released);
+        assert!(is_key_just_released(KeyCode::KeyF));
+
+        new_frame();
+        assert!(!is_key_just_released(KeyCode::KeyF));
+    }
+
+    #[test]
+    fn keys_written_on_one_thread_are_read_on_another() {
+        let _guard = serial();
+        let writer = std::thread::spawn(|| {
+            for _ in 0..1000 {
+                register_key_state(KeyCode::KeyG, ElementState::Pressed);
+                register_key_state(KeyCode::KeyG, ElementState::Released);
+            }
+            register_key_state(KeyCode::KeyG, ElementState::Pressed);
+        });
+        let reader = std::thread::spawn(|| {
+            for _ in 0..1000 {
+                is_key_pressed(KeyCode::KeyG);
+            }
+        });
+        writer.join().unwrap();
+        reader.join().unwrap();
+
+        assert!(is_key_pressed(KeyCode::KeyG));
+        register_key_state(KeyCode::KeyG, ElementState::Released);
+        take_events();
+    }
+
+    #[test]
+    fn repeats_drain_then_come_back_empty() {
+        let _guard = serial();
+        take_repeats();
+        register_key_state(KeyCode::KeyH, ElementState::Pressed);
+        for _ in 0..3 {
+            register_key_repeat(KeyCode::KeyH);
+        }
+
+        assert_eq!(take_repeats(), [KeyCode::KeyH; 3]);
+        assert!(take_repeats().is_empty());
+
+        register_key_state(KeyCode::KeyH, ElementState::Released);
+        take_events();
+    }
+
+    #[test]
+    fn held_keys_repeat_their_action() {
+        let _guard = serial();
+        take_events();
+        take_repeats();
+        register_key_state(KeyCode::ArrowDown, ElementState::Pressed);
+        let map = InputMap::default();
+        map.snapshot(&take_events(), &take_repeats());
+        new_frame();
+
+        // Still held, but only the repeats make it count again
+        let snapshot = map.snapshot(&take_events(), &take_repeats());
+        assert!(!snapshot.is_just_pressed_or_repeated(Action::MoveDown));
+        register_key_repeat(KeyCode::ArrowDown);
+        let snapshot = map.snapshot(&take_events(), &take_repeats());
+        assert!(snapshot.is_just_pressed_or_repeated(Action::MoveDown));
+        assert!(!snapshot.is_just_pressed(Action::MoveDown));
+
+        register_key_state(KeyCode::ArrowDown, ElementState::Released);
+        new_frame();
+        take_events();
+    }
+
+    #[test]
+    fn cursor_and_mouse_buttons_round_trip() {
+        let _guard = serial();
+        register_cursor_moved(120.5, 64.0);
+        register_mouse_button(MouseButton::Left, ElementState::Pressed);
+
+        assert_eq!(cursor_position(), (120.5, 64.0));
+        assert!(is_mouse_pressed(MouseButton::Left));
+        assert!(!is_mouse_pressed(MouseButton::Right));
+
+        register_mouse_button(MouseButton::Left, ElementState::Released);
+        assert!(!is_mouse_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn focus_follows_registered_events() {
+        let _guard = serial();
+        register_focus(false);
+        assert!(!is_window_focused());
+        assert!(!InputMap::default().snapshot(&[], &[]).is_focused());
+
+        register_focus(true);
+        assert!(is_window_focused());
+    }
+
+    #[test]
+    fn two_presses_in_one_frame_queue_two_events() {
+        let _guard = serial();
+        take_events();
+        for state in [
+            ElementState::Pressed,
+            ElementState::Released,
+            ElementState::Pressed,
+            ElementState::Released,
+        ] {
+            register_key_state(KeyCode::KeyJ, state);
+        }
+
+        let events = take_events();
+        let states: Vec<_> = events.iter().map(|event| event.state).collect();
+        assert_eq!(
+            states,
+            [
+                ElementState::Pressed,
+                ElementState::Released,
+                ElementState::Pressed,
+                ElementState::Released,
+            ]
+        );
+        assert!(events.iter().all(|event| event.key == KeyCode::KeyJ));
+        assert!(events.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+
+    #[test]
+    fn a_tap_inside_one_frame_still_fires() {
+        let _guard = serial();
+        take_events();
+        register_key_state(KeyCode::Space, ElementState::Pressed);
+        register_key_state(KeyCode::Space, ElementState::Released);
+
+        let snapshot = InputMap::default().snapshot(&take_events(), &[]);
+        assert!(snapshot.is_just_pressed(Action::Fire));
+        assert!(snapshot.is_just_released(Action::Fire));
+        assert!(!snapshot.is_pressed(Action::Fire));
+        // The queue was drained, the next frame doesn't see the tap again
+        new_frame();
+        let snapshot = InputMap::default().snapshot(&take_events(), &[]);
+        assert!(!snapshot.is_just_pressed(Action::Fire));
+    }
+
+    #[test]
+    fn escape_opens_the_menu_by_default() {
+        let _guard = serial();
+        take_events();
+        register_key_state(KeyCode::Escape, ElementState::Pressed);
+        let snapshot = InputMap::default().snapshot(&take_events(), &[]);
+        assert!(snapshot.is_just_pressed(Action::Menu));
+        register_key_state(KeyCode::Escape, ElementState::Released);
+        take_events();
+    }
+
+    #[test]
+    fn bindings_parse_actions_and_key_names() {
+        assert_eq!(
+            parse_binding("MoveLeft=A"),
+            Some((Action::MoveLeft, KeyCode::KeyA))
+        );
+        assert_eq!(
+            parse_binding(" fire = Enter "),
+            Some((Action::Fire, KeyCode::Enter))
+        );
+        assert_eq!(
+            parse_binding("Pause=Digit1"),
+            Some((Action::Pause, KeyCode::Digit1))
+        );
+        assert_eq!(
+            parse_binding("MoveUp=KeyW"),
+            Some((Action::MoveUp, KeyCode::KeyW))
+        );
+        assert_eq!(parse_binding("Jump=Space"), None);
+        assert_eq!(parse_binding("Fire=Hyper"), None);
+        assert_eq!(parse_binding("Fire"), None);
+    }
+
+    #[test]
+    fn merging_keeps_both_snapshots_actions() {
+        let mut snapshot = InputSnapshot::pressing(&[Action::MoveLeft]);
+        snapshot.merge(&InputSnapshot::pressing(&[Action::Fire]));
+        assert!(snapshot.is_pressed(Action::MoveLeft));
+        assert!(snapshot.is_just_pressed(Action::Fire));
+        assert!(!snapshot.is_pressed(Action::MoveRight));
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn the_snapshot_keeps_the_oldest_bound_event_time() {
+        let first = Instant::now();
+        let event = |key, time| InputEvent {
+            key,
+            state: ElementState::Pressed,
+            time,
+        };
+        let later = first + std::time::Duration::from_millis(3);
+        let events = [
+            event(KeyCode::F11, first),
+            event(KeyCode::Space, later),
+            event(KeyCode::ArrowLeft, later),
+        ];
+        // F11 isn't bound to an action
+        let snapshot = InputMap::default().snapshot(&events, &[]);
+        assert_eq!(snapshot.first_event(), Some(later));
+        assert_eq!(InputSnapshot::default().first_event(), None);
+    }
+
+    #[test]
+    fn rebinding_move_left_replaces_the_old_key() {
+        let _guard = serial();
+        let mut map = InputMap::default();
+        map.bind(Action::MoveLeft, KeyCode::KeyA);
+
+        register_key_state(KeyCode::ArrowLeft, ElementState::Pressed);
+        assert!(!map.is_action_pressed(Action::MoveLeft));
+
+        register_key_state(KeyCode::ArrowLeft, ElementState::Released);
+        register_key_state(KeyCode::KeyA, ElementState::Pressed);
+        assert!(map.is_action_pressed(Action::MoveLeft));
+
+        register_key_state(KeyCode::KeyA, ElementState::Released);
+        take_events();
+    }
+}
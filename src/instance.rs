@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem::size_of;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::sprite::Sprite;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+    pub tint: [f32; 4],
+    pub uv_rect: [f32; 4],
+}
+
+impl InstanceRaw {
+    pub fn from_sprite(sprite: &Sprite) -> Self {
+        Self {
+            model: (*sprite.model_mat()).into(),
+            tint: sprite.tint(),
+            uv_rect: sprite.uv_rect(),
+        }
+    }
+
+    // One vec4 attribute per matrix column then the tint and UV rect, after the
+    // quad's two attributes
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+            2 => Float32x4,
+            3 => Float32x4,
+            4 => Float32x4,
+            5 => Float32x4,
+            6 => Float32x4,
+            7 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<InstanceRaw>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+// Splits sprites into consecutive runs sharing a texture and sampler, each run
+// becomes one instanced draw. Order is never changed, so blending stays correct.
+pub fn texture_runs(sprites: &[&Sprite]) -> Vec<Range<u32>> {
+    let mut runs: Vec<Range<u32>> = Vec::new();
+    for (i, sprite) in sprites.iter().enumerate() {
+        let i = i as u32;
+        match runs.last_mut() {
+            Some(run) if sprites[run.start as usize].texture_key() == sprite.texture_key() => {
+                run.end = i + 1;
+            }
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+// Stable reorder so sprites sharing a texture end up in one run. Only sprites with
+// the same layer and z are regrouped, each texture keeps the position of its first
+// sprite and sprites keep their order within a texture. Expects sprites already
+// in draw order, overlapping sprites that must stay in a given order need
+// different layers or z.
+pub fn batch_by_texture(sprites: &mut [&Sprite]) {
+    let keys: Vec<_> = sprites
+        .iter()
+        .map(|sprite| ((sprite.layer(), sprite.z()), sprite.texture_key()))
+        .collect();
+    let batched: Vec<&Sprite> = batch_order(&keys).iter().map(|&i| sprites[i]).collect();
+    sprites.copy_from_slice(&batched);
+}
+
+// The order batch_by_texture draws in, keys are (depth, texture) per item and only
+// items with equal depth next to each other are regrouped
+fn batch_order<D: PartialEq, T: Copy + Eq + Hash>(keys: &[(D, T)]) -> Vec<usize> {
+    let mut sort_keys = Vec::with_capacity(keys.len());
+    let mut group = 0;
+    let mut first_seen = HashMap::new();
+    for (i, (depth, texture)) in keys.iter().enumerate() {
+        if i > 0 && keys[i - 1].0 != *depth {
+            group += 1;
+            first_seen.clear();
+        }
+        sort_keys.push((group, *first_seen.entry(*texture).or_insert(i)));
+    }
+
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by_key(|&i| sort_keys[i]);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // How often a draw loop that skips redundant binds, like Renderer::draw_sprites,
+    // binds a texture for textures in this order
+    fn texture_binds<T: PartialEq>(textures: impl IntoIterator<Item = T>) -> usize {
+        let mut binds = 0;
+        let mut current = None;
+        for texture in textures {
+            if current.as_ref() != Some(&texture) {
+                binds += 1;
+                current = Some(texture);
+            }
+        }
+        binds
+    }
+
+    #[test]
+    fn batching_cuts_texture_binds_for_a_mixed_list() {
+        // Player, enemy and text textures interleaved at one depth, then bullets
+        // further front
+        let keys = [
+            (0, 'p'),
+            (0, 'e'),
+            (0, 't'),
+            (0, 'e'),
+            (0, 't'),
+            (0, 'e'),
+            (1, 'b'),
+            (1, 'e'),
+            (1, 'b'),
+        ];
+        let before = texture_binds(keys.iter().map(|(_, texture)| texture));
+        let order = batch_order(&keys);
+        let after = texture_binds(order.iter().map(|&i| keys[i].1));
+        assert_eq!(before, 9);
+        assert_eq!(after, 5);
+
+        // Depth groups stay in order, and so do items within a texture
+        assert_eq!(order, [0, 1, 3, 5, 2, 4, 6, 8, 7]);
+    }
+
+    #[test]
+    fn texture_binds_skips_repeats() {
+        assert_eq!(texture_binds(Vec::<u32>::new()), 0);
+        assert_eq!(texture_binds([1, 1, 1]), 1);
+        assert_eq!(texture_binds([1, 2, 1]), 3);
+    }
+}
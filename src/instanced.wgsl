@@ -0,0 +1,43 @@
+struct VertOut {
+    @builtin(position) pos: vec4f,
+    @location(0) tex_c: vec2f,
+    @location(1) tint: vec4f,
+}
+
+@group(0)
+@binding(0)
+var<uniform> projection: mat4x4<f32>;
+
+struct Instance {
+    @location(2) model_0: vec4f,
+    @location(3) model_1: vec4f,
+    @location(4) model_2: vec4f,
+    @location(5) model_3: vec4f,
+    @location(6) tint: vec4f,
+    // xy offset, zw scale
+    @location(7) uv_rect: vec4f,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec2f, @location(1) tex_coords: vec2f, instance: Instance) -> VertOut {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    var out: VertOut;
+    out.pos = projection * model * vec4f(pos, 0.0, 1.0);
+    out.tex_c = instance.uv_rect.xy + tex_coords * instance.uv_rect.zw;
+    out.tint = instance.tint;
+    return out;
+}
+
+// Only the sprite texture group, the model matrix, tint and UV rect come from
+// the instance buffer instead
+@group(1)
+@binding(0)
+var texture: texture_2d<f32>;
+@group(1)
+@binding(1)
+var samp: sampler;
+
+@fragment
+fn fs_main(vert: VertOut) -> @location(0) vec4f {
+    return textureSample(texture, samp, vert.tex_c) * vert.tint;
+}
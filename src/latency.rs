@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+// From winit registering an input event to the frame it first changed the game,
+// to check the input path isn't adding lag. Each measurement is logged.
+#[derive(Debug, Default)]
+pub struct InputLatency {
+    last: Option<Duration>,
+}
+
+impl InputLatency {
+    // registered is the snapshot's first event, None for input that never went
+    // through the queue, e.g. autofire or the test helpers
+    pub fn record(&mut self, registered: Option<Instant>, now: Instant) -> Option<Duration> {
+        let latency = now.saturating_duration_since(registered?);
+        eprintln!("Input latency {:.2}ms", latency.as_secs_f64() * 1000.0);
+        self.last = Some(latency);
+        self.last
+    }
+
+    #[cfg(test)]
+    pub fn last(&self) -> Option<Duration> {
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_runs_from_registration_to_now() {
+        let mut latency = InputLatency::default();
+        let registered = Instant::now();
+        let now = registered + Duration::from_millis(5);
+        assert_eq!(
+            latency.record(Some(registered), now),
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(latency.record(None, now), None);
+        assert_eq!(latency.last(), Some(Duration::from_millis(5)));
+    }
+}
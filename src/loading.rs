@@ -0,0 +1,89 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crate::texture::{self, TextureError};
+use crate::Context;
+
+type Decoded = Result<image::RgbaImage, TextureError>;
+
+// Images still being decoded on other threads. Each already has its texture,
+// created up front at the image's size, so sprites can be built on it straight
+// away and show the pixels once they're written.
+#[derive(Default)]
+pub struct Loading {
+    pending: Vec<(Arc<wgpu::Texture>, Receiver<Decoded>)>,
+}
+
+impl Loading {
+    pub fn decode(
+        &mut self,
+        texture: Arc<wgpu::Texture>,
+        job: impl FnOnce() -> Decoded + Send + 'static,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // Only fails once the game has given up on loading
+            let _ = sender.send(job());
+        });
+        self.add(texture, receiver);
+    }
+
+    fn add(&mut self, texture: Arc<wgpu::Texture>, receiver: Receiver<Decoded>) {
+        self.pending.push((texture, receiver));
+    }
+
+    // Writes whatever finished since the last poll, true once nothing is left
+    pub fn poll(&mut self, context: &Context) -> Result<bool, TextureError> {
+        let mut result = Ok(());
+        self.pending.retain(|(texture, receiver)| {
+            match receiver.try_recv() {
+                Ok(Ok(img)) => texture::write_image(context, texture, &img),
+                Ok(Err(err)) if result.is_ok() => result = Err(err),
+                Ok(Err(_)) => {}
+                Err(TryRecvError::Empty) => return true,
+                // The decoding thread panicked, its texture stays blank
+                Err(TryRecvError::Disconnected) => {}
+            }
+            false
+        });
+        result.map(|()| self.pending.is_empty())
+    }
+
+    // Blocks until every image is in, for headless games that start on the title
+    #[cfg(test)]
+    pub fn wait(&mut self, context: &Context) -> Result<(), TextureError> {
+        for (texture, receiver) in self.pending.drain(..) {
+            if let Ok(img) = receiver.recv() {
+                texture::write_image(context, &texture, &img?);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_finishes_once_the_decoder_sends() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let texture = Arc::new(texture::create_rgba_texture(&context, 1, 1, &[0; 4]));
+        let (sender, receiver) = mpsc::channel();
+        let mut loading = Loading::default();
+        loading.add(texture, receiver);
+        assert!(!loading.poll(&context).unwrap());
+
+        sender
+            .send(Ok(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([255; 4]),
+            )))
+            .unwrap();
+        assert!(loading.poll(&context).unwrap());
+    }
+}
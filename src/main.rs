@@ -1,51 +1,104 @@
+mod animation;
+mod assets;
+mod atlas;
+#[cfg(feature = "audio")]
+mod audio;
+mod boss;
+mod bullet;
+mod clock;
+mod collision;
+mod enemy;
+mod event;
+mod flash;
+mod fps;
+mod frame_log;
+mod game;
+#[cfg(feature = "gamepad")]
+mod gamepad;
 mod input;
+mod instance;
+#[cfg(feature = "debug")]
+mod latency;
+mod loading;
+mod menu;
+mod particle;
 mod player;
+mod powerup;
+mod profile;
+mod rect;
+mod renderer;
+mod schedule;
+mod score;
+mod settings;
 mod sprite;
+mod surface;
+mod tear_control;
+mod text;
+mod texture;
+mod texture_cache;
+mod upscale;
+mod window_mode;
 
-use std::mem::size_of;
 use std::sync::Arc;
-
-use bytemuck::{Pod, Zeroable};
-use player::Player;
-use sprite::Sprite;
-use wgpu::include_wgsl;
-use wgpu::{util::DeviceExt, Instance};
-use winit::dpi::PhysicalSize;
+use std::time::{Duration, Instant};
+
+use clock::Clock;
+use frame_log::{FrameLog, FrameTimings};
+use game::{Game, GameOptions};
+use input::InputSnapshot;
+use renderer::RenderTarget;
+use schedule::WaveSchedule;
+use tear_control::TearControl;
+use texture_cache::TextureCache;
+use wgpu::Instance;
+use window_mode::WindowMode;
+use winit::dpi::LogicalSize;
 use winit::event::*;
-use winit::event_loop::EventLoop;
-use winit::keyboard::{Key, KeyCode, NamedKey, PhysicalKey};
-use winit::window::Window;
-
-use image::io::Reader as ImageReader;
-
-#[repr(C)]
-#[derive(Clone, Copy, PartialEq, Pod, Zeroable)]
-struct Vertex {
-    pos: [f32; 2],
-    tex_coord: [f32; 2],
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::WindowBuilder;
+
+const WORLD_WIDTH: f32 = 229.0;
+const WORLD_HEIGHT: f32 = 190.0;
+// Initial window size in multiples of the world size
+const WINDOW_SCALE: f32 = 4.0;
+// Debug hotkey, see Game::cycle_present_mode
+const CYCLE_PRESENT_MODE_KEY: KeyCode = KeyCode::F9;
+const SCREENSHOT_KEY: KeyCode = KeyCode::F12;
+const ZOOM_INSET_KEY: KeyCode = KeyCode::F4;
+const MUTE_KEY: KeyCode = KeyCode::KeyM;
+// Master volume down and up by VOLUME_STEP
+const VOLUME_DOWN_KEY: KeyCode = KeyCode::Minus;
+const VOLUME_UP_KEY: KeyCode = KeyCode::Equal;
+const VOLUME_STEP: f32 = 0.1;
+// An adapter index or name, see AdapterSelection::parse
+const ADAPTER_ENV: &str = "VADERS_ADAPTER";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum AdapterSelection {
+    #[default]
+    HighPerformance,
+    Index(usize),
+    Name(String),
 }
 
-macro_rules! vert {
-    ( $( [$x:expr, $y:expr] ),* ) => {
-        [
-        $(
-            Vertex {
-                pos: $x,
-                tex_coord: $y,
-            },
-        )*
-        ]
-    };
-}
+impl AdapterSelection {
+    fn from_env() -> Self {
+        std::env::var(ADAPTER_ENV)
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
 
-const VERTICES: [Vertex; 6] = vert!(
-    [[0.0, 1.0], [0.0, 1.0]], // top left
-    [[1.0, 1.0], [1.0, 1.0]], // top right
-    [[0.0, 0.0], [0.0, 0.0]], // bottom left
-    [[0.0, 0.0], [0.0, 0.0]], // bottom left
-    [[1.0, 0.0], [1.0, 0.0]], // bottom right
-    [[1.0, 1.0], [1.0, 1.0]]  // top right
-);
+    // A number is an index into the enumerated adapters, anything else a name
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+        match value.parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) if value.is_empty() => Self::HighPerformance,
+            Err(_) => Self::Name(value.to_owned()),
+        }
+    }
+}
 
 struct Context {
     instance: wgpu::Instance,
@@ -54,21 +107,30 @@ struct Context {
     queue: wgpu::Queue,
 }
 impl Context {
-    async fn init() -> Result<Self, Box<dyn std::error::Error>> {
+    async fn init_with(selection: AdapterSelection) -> Result<Self, Box<dyn std::error::Error>> {
         let instance = Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
             ..Default::default()
         });
 
-        let adapter_options = wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            force_fallback_adapter: false,
-            ..Default::default()
+        let adapter = match Self::find_adapter(&instance, &selection) {
+            Some(adapter) => adapter,
+            None => {
+                if selection != AdapterSelection::HighPerformance {
+                    eprintln!("Adapter {selection:?} is not available, using the default one");
+                }
+
+                let adapter_options = wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    force_fallback_adapter: false,
+                    ..Default::default()
+                };
+                instance
+                    .request_adapter(&adapter_options)
+                    .await
+                    .ok_or("Could not aquire an adapter")?
+            }
         };
-        let adapter = instance
-            .request_adapter(&adapter_options)
-            .await
-            .ok_or("Could not aquire an adapter")?;
 
         let (device, queue) = adapter
             .request_device(
@@ -87,293 +149,409 @@ impl Context {
             queue,
         })
     }
-}
-
-struct Renderer {
-    vertex_buf: wgpu::Buffer,
-    pipeline: wgpu::RenderPipeline,
-    proj_bind_group: wgpu::BindGroup,
-    proj_buf: wgpu::Buffer,
-}
-
-impl Renderer {
-    fn init(context: &Context, surface_config: &wgpu::SurfaceConfiguration) -> Self {
-        let device = &context.device;
-
-        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let proj_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[
-                // Projection Matrix
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
-
-        let sprite_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[
-                    // Model Matrix
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    // Texture
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    // Sampler
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-            });
-
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[&proj_group_layout, &sprite_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
 
-        let vertex_buffers = [wgpu::VertexBufferLayout {
-            array_stride: 4 * size_of::<f32>() as u64,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 2 * size_of::<f32>() as u64,
-                    shader_location: 1,
-                },
-            ],
-        }];
-
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &vertex_buffers,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.view_formats[0],
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
-
-        let proj_mat = nalgebra_glm::ortho::<f32>(0.0, 229.0, 0.0, 190.0, -1.0, 1.0);
-
-        let proj_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice((&proj_mat).into()),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
-        });
-
-        let proj_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &proj_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: proj_buf.as_entire_binding(),
-            }],
-        });
-
-        Self {
-            vertex_buf,
-            pipeline,
-            proj_bind_group,
-            proj_buf,
+    fn find_adapter(instance: &Instance, selection: &AdapterSelection) -> Option<wgpu::Adapter> {
+        let adapters = || {
+            instance
+                .enumerate_adapters(wgpu::Backends::PRIMARY)
+                .into_iter()
+        };
+        match selection {
+            AdapterSelection::HighPerformance => None,
+            AdapterSelection::Index(index) => adapters().nth(*index),
+            AdapterSelection::Name(name) => {
+                adapters().find(|adapter| adapter.get_info().name == *name)
+            }
         }
     }
 
-    fn render(&mut self, target: &wgpu::TextureView, context: &Context, sprites: Vec<&Sprite>) {
-        let device = &context.device;
-        let queue = &context.queue;
-
-        let mut encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: target,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.2,
-                            g: 0.2,
-                            b: 0.2,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
+    fn enumerate_adapters(&self) -> Vec<wgpu::AdapterInfo> {
+        self.instance
+            .enumerate_adapters(wgpu::Backends::PRIMARY)
+            .iter()
+            .map(|adapter| adapter.get_info())
+            .collect()
+    }
+}
 
-            rpass.set_pipeline(&self.pipeline);
-            rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
-            rpass.set_bind_group(0, &self.proj_bind_group, &[]);
-            for sprite in sprites {
-                rpass.set_bind_group(1, sprite.get_bind_group(), &[]);
-                rpass.draw(0..VERTICES.len() as u32, 0..1);
-            }
+// For tests that need a device, None on machines without an adapter so those
+// tests can return early instead of failing
+#[cfg(test)]
+fn test_context() -> Option<Context> {
+    let runtime = tokio::runtime::Runtime::new().ok()?;
+    match runtime.block_on(Context::init_with(AdapterSelection::from_env())) {
+        Ok(context) => Some(context),
+        Err(err) => {
+            eprintln!("Skipping, no device: {err}");
+            None
         }
-
-        queue.submit(Some(encoder.finish()));
     }
 }
 
-struct Surface {
-    surface: wgpu::Surface<'static>,
-    config: wgpu::SurfaceConfiguration,
+// Stands in for a window's surface, the renderer only reads the size and format
+#[cfg(test)]
+fn test_surface_config(width: u32, height: u32) -> wgpu::SurfaceConfiguration {
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width,
+        height,
+        present_mode: wgpu::PresentMode::Fifo,
+        desired_maximum_frame_latency: 2,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: vec![format],
+    }
 }
-impl Surface {
-    fn new(context: &Context, window: Arc<Window>) -> Self {
-        let window_size = window.inner_size();
-        let width = window_size.width.max(1);
-        let height = window_size.height.max(1);
-
-        let surface = context.instance.create_surface(window).unwrap();
-
-        let mut config = surface
-            .get_default_config(&context.adapter, width, height)
-            .unwrap();
-        let format = config.format.remove_srgb_suffix();
-        config.format = format;
-        config.view_formats.push(format);
-
-        surface.configure(&context.device, &config);
 
-        Self { surface, config }
+// One frame of the main loop: fixed updates over elapsed, then render into the
+// window, or into offscreen for headless runs. Timings are only returned for
+// presented frames.
+fn step_frame(
+    context: &Context,
+    game: &mut Game,
+    clock: &mut Clock,
+    input: &InputSnapshot,
+    elapsed: Duration,
+    offscreen: Option<&RenderTarget>,
+) -> Result<Option<FrameTimings>, wgpu::SurfaceError> {
+    game.record_frame();
+    let update_start = Instant::now();
+    for _ in 0..clock.advance(elapsed) {
+        game.update(context, input, clock.dt());
     }
-
-    fn next_texture(&mut self) -> wgpu::SurfaceTexture {
-        self.surface.get_current_texture().unwrap()
+    game.handle_input(context, input);
+    if game.is_running() {
+        clock.resume();
+    } else {
+        clock.pause();
     }
 
-    fn resize(&mut self, context: &Context, size: PhysicalSize<u32>) {
-        let config = &mut self.config;
-        config.width = size.width.max(1);
-        config.height = size.height.max(1);
+    let render_start = Instant::now();
+    let present = match offscreen {
+        Some(target) => {
+            game.draw(context, target, None);
+            None
+        }
+        None => game.render(context)?,
+    };
+    let Some(present) = present else {
+        return Ok(None);
+    };
 
-        let surface = &mut self.surface;
-        surface.configure(&context.device, config);
-    }
+    Ok(Some(FrameTimings {
+        update: render_start - update_start,
+        render: render_start.elapsed() - present,
+        present,
+    }))
+}
 
-    fn config(&self) -> &wgpu::SurfaceConfiguration {
-        &self.config
+// What the event loop does after a redraw
+#[derive(Debug, PartialEq)]
+enum Redraw {
+    // There was no game yet
+    Skipped,
+    // With the frame's timings, if it was presented
+    Drawn(Option<FrameTimings>),
+    Exit,
+}
+
+// Some platforms deliver a redraw before Init, it's skipped until the game exists
+fn redraw(context: &Context, game: Option<&mut Game>, clock: &mut Clock) -> Redraw {
+    let Some(game) = game else {
+        return Redraw::Skipped;
+    };
+    let input = game.input_snapshot();
+    let elapsed = clock.elapsed();
+    let timings = match step_frame(context, game, clock, &input, elapsed, None) {
+        Ok(timings) => timings,
+        Err(err) => {
+            eprintln!("Could not acquire the next frame: {err}");
+            return Redraw::Exit;
+        }
+    };
+    input::new_frame();
+    // Escape only pauses, quitting goes through the pause menu
+    if game.wants_to_quit() {
+        return Redraw::Exit;
     }
+    Redraw::Drawn(timings)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let event_loop = EventLoop::new().unwrap();
-    event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
-    let window = Arc::new(winit::window::Window::new(&event_loop)?);
-    let context = Context::init().await?;
-    let mut surface = None;
-    let mut renderer = None;
-
-    let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-        ..Default::default()
-    });
+    event_loop.set_control_flow(ControlFlow::Poll);
+    let window = Arc::new(
+        WindowBuilder::new()
+            .with_title("vaders")
+            .with_inner_size(LogicalSize::new(
+                WORLD_WIDTH * WINDOW_SCALE,
+                WORLD_HEIGHT * WINDOW_SCALE,
+            ))
+            // Keeps the surface from ever being configured at 0x0
+            .with_min_inner_size(LogicalSize::new(WORLD_WIDTH, WORLD_HEIGHT))
+            .build(&event_loop)?,
+    );
+    let mut window_mode = WindowMode::default();
+    window_mode.apply(&window);
+    let selection = AdapterSelection::from_env();
+    let context = Context::init_with(selection.clone()).await?;
+    // Whoever picks an adapter by hand gets to see what else there is
+    if selection != AdapterSelection::default() {
+        for (index, info) in context.enumerate_adapters().iter().enumerate() {
+            eprintln!("Adapter {index}: {} ({:?})", info.name, info.backend);
+        }
+    }
+    let mut game = None;
 
-    let mut player = None;
+    let sampler = Arc::new(texture::create_sampler(
+        &context,
+        wgpu::AddressMode::ClampToEdge,
+        wgpu::AddressMode::ClampToEdge,
+    ));
 
-    let _ = event_loop.run(move |event, target| match event {
-        Event::NewEvents(StartCause::Init) => {
-            surface = Some(Surface::new(&context, window.clone()));
+    let mut textures = TextureCache::new();
 
-            renderer = Some(Renderer::init(&context, surface.as_ref().unwrap().config()));
+    let mut frame_log = FrameLog::from_env();
+    let mut tear_control = TearControl::from_env();
+    let mut clock = Clock::new(clock::FIXED_STEP, clock::MAX_CATCHUP_STEPS);
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = gamepad::Gamepads::new();
 
-            player = Some(Player::init(&context, &sampler, renderer.as_ref().unwrap()));
+    let _ = event_loop.run(move |event, target| match event {
+        Event::NewEvents(StartCause::Init) => {
+            let options = GameOptions::from_env();
+            game = match Game::new(&context, window.clone(), &mut textures, &sampler, options) {
+                Ok(game) => {
+                    clock.reset();
+                    match WaveSchedule::from_env() {
+                        Some(schedule) => Some(game.with_schedule(schedule)),
+                        None => Some(game),
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    target.exit();
+                    return;
+                }
+            };
+
+            window.request_redraw();
         }
+        // Tear control held the next frame until now
+        Event::NewEvents(StartCause::ResumeTimeReached { .. }) => window.request_redraw(),
         Event::WindowEvent { event, .. } => match event {
             WindowEvent::RedrawRequested => {
-                let surface = surface.as_mut().unwrap();
-                let frame = surface.next_texture();
-                let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
-                    format: Some(surface.config().view_formats[0]),
-                    ..Default::default()
-                });
+                #[cfg(feature = "gamepad")]
+                if let Some(gamepads) = gamepads.as_mut() {
+                    gamepads.poll();
+                }
+
+                let timings = match redraw(&context, game.as_mut(), &mut clock) {
+                    // Init requests the first real redraw
+                    Redraw::Skipped => return,
+                    Redraw::Exit => {
+                        target.exit();
+                        return;
+                    }
+                    Redraw::Drawn(timings) => timings,
+                };
+
+                if let (Some(log), Some(timings)) = (frame_log.as_mut(), timings) {
+                    if let Err(err) = log.record(&timings) {
+                        eprintln!("Could not write frame log, disabling it: {err}");
+                        frame_log = None;
+                    }
+                }
+
+                let mode = game.as_ref().and_then(Game::present_mode);
+                let refresh = window
+                    .current_monitor()
+                    .and_then(|monitor| monitor.refresh_rate_millihertz());
+                let next =
+                    mode.and_then(|mode| tear_control.next_frame(mode, refresh, Instant::now()));
+                match next {
+                    Some(next) => target.set_control_flow(ControlFlow::WaitUntil(next)),
+                    None => {
+                        target.set_control_flow(ControlFlow::Poll);
+                        window.request_redraw();
+                    }
+                }
+            }
 
-                player.as_mut().unwrap().update(&context);
+            WindowEvent::Resized(size) => {
+                if let Some(game) = game.as_mut() {
+                    game.request_resize(size);
+                }
+            }
 
-                let sprites = vec![player.as_ref().unwrap().get_sprite()];
+            // Moving between monitors with different DPI changes the physical size,
+            // a Resized usually follows and, being later, wins the debounce
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                if let Some(game) = game.as_mut() {
+                    game.scale_factor_changed(scale_factor, window.inner_size());
+                }
+            }
 
-                renderer.as_mut().unwrap().render(&view, &context, sprites);
+            WindowEvent::CloseRequested => {
+                target.exit();
+            }
 
-                frame.present();
+            WindowEvent::CursorMoved { position, .. } => {
+                input::register_cursor_moved(position.x, position.y);
+            }
 
-                window.request_redraw();
+            WindowEvent::MouseInput { state, button, .. } => {
+                input::register_mouse_button(button, state);
             }
 
-            WindowEvent::Resized(size) => {
-                let surface = surface.as_mut().unwrap();
-                surface.resize(&context, size);
+            WindowEvent::Focused(focused) => {
+                input::register_focus(focused);
             }
 
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
-                        logical_key: Key::Named(NamedKey::Escape),
+                        physical_key: PhysicalKey::Code(KeyCode::F10),
+                        state: ElementState::Pressed,
+                        repeat: false,
                         ..
                     },
                 ..
+            } => {
+                window_mode = window_mode.next();
+                window_mode.apply(&window);
+                if let Some(game) = game.as_mut() {
+                    game.request_resize(window.inner_size());
+                }
             }
-            | WindowEvent::CloseRequested => {
-                target.exit();
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F11),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                window_mode = window_mode.toggle_fullscreen();
+                window_mode.apply(&window);
+                if let Some(game) = game.as_mut() {
+                    game.request_resize(window.inner_size());
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F3),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    game.toggle_fps_display();
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(CYCLE_PRESENT_MODE_KEY),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    game.cycle_present_mode(&context);
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(ZOOM_INSET_KEY),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    game.toggle_zoom_inset();
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(MUTE_KEY),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    game.toggle_mute();
+                }
+            }
+
+            // Held keys repeat, so holding one sweeps the volume
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key @ (VOLUME_DOWN_KEY | VOLUME_UP_KEY)),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    let step = if key == VOLUME_UP_KEY {
+                        VOLUME_STEP
+                    } else {
+                        -VOLUME_STEP
+                    };
+                    game.change_volume(step);
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(SCREENSHOT_KEY),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    game.save_screenshot(&context);
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyV),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                if let Some(game) = game.as_mut() {
+                    game.toggle_vsync(&context);
+                }
             }
 
             WindowEvent::KeyboardInput {
@@ -389,10 +567,401 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 input::register_key_state(key, state);
             }
 
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        repeat: true,
+                        ..
+                    },
+                ..
+            } => {
+                input::register_key_repeat(key);
+            }
+
             _ => {}
         },
+        Event::LoopExiting => {
+            if let Some(log) = frame_log.as_mut() {
+                let _ = log.flush();
+            }
+            if let Some(game) = game.as_ref() {
+                game.save_high_score();
+            }
+        }
         _ => {}
     });
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use input::Action;
+
+    #[test]
+    fn adapter_selection_parses_indices_and_names() {
+        assert_eq!(AdapterSelection::parse("1"), AdapterSelection::Index(1));
+        assert_eq!(
+            AdapterSelection::parse(" NVIDIA GeForce RTX 3060 "),
+            AdapterSelection::Name("NVIDIA GeForce RTX 3060".to_owned())
+        );
+        assert_eq!(
+            AdapterSelection::parse(""),
+            AdapterSelection::HighPerformance
+        );
+    }
+
+    // A game without a window, each step drawn into an offscreen frame
+    struct Headless {
+        context: Context,
+        game: Game,
+        clock: Clock,
+        view: wgpu::TextureView,
+        depth: wgpu::TextureView,
+    }
+
+    impl Headless {
+        fn new() -> Option<Self> {
+            Self::with_options(GameOptions::default())
+        }
+
+        fn with_options(options: GameOptions) -> Option<Self> {
+            let context = test_context()?;
+            let (width, height) = (458, 380);
+            let config = test_surface_config(width, height);
+            let sampler = Arc::new(texture::create_sampler(
+                &context,
+                wgpu::AddressMode::ClampToEdge,
+                wgpu::AddressMode::ClampToEdge,
+            ));
+            let mut textures = TextureCache::new();
+            let game = Game::headless(&context, &config, &mut textures, &sampler, options).unwrap();
+
+            let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Test Frame"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let depth = renderer::create_depth_view(&context.device, width, height, 1);
+            Some(Self {
+                context,
+                game,
+                clock: Clock::new(clock::FIXED_STEP, clock::MAX_CATCHUP_STEPS),
+                view,
+                depth,
+            })
+        }
+
+        fn step(&mut self, actions: &[Action]) {
+            self.step_with(&InputSnapshot::pressing(actions));
+        }
+
+        fn step_with(&mut self, input: &InputSnapshot) {
+            let target = RenderTarget {
+                color: &self.view,
+                depth: &self.depth,
+                msaa: None,
+            };
+            let timings = step_frame(
+                &self.context,
+                &mut self.game,
+                &mut self.clock,
+                input,
+                clock::FIXED_STEP,
+                Some(&target),
+            )
+            .unwrap();
+            // Nothing is presented offscreen
+            assert!(timings.is_none());
+        }
+    }
+
+    #[test]
+    fn stepping_headless_moves_the_player() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        let start = headless.game.player_position();
+
+        // Nothing moves on the menu, fire starts the game
+        headless.step(&[Action::MoveRight]);
+        assert_eq!(headless.game.player_position(), start);
+        headless.step(&[Action::Fire]);
+        assert_eq!(headless.game.state(), game::GameState::Playing);
+
+        for _ in 0..10 {
+            headless.step(&[Action::MoveRight]);
+        }
+        assert!(headless.game.player_position().x > start.x);
+        assert_eq!(headless.game.player_position().y, start.y);
+    }
+
+    #[test]
+    fn escape_opens_the_pause_menu_instead_of_exiting() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        headless.step(&[Action::Fire]);
+
+        // Action::Menu is what Escape is bound to by default
+        headless.step(&[Action::Menu]);
+        assert_eq!(headless.game.state(), game::GameState::Paused);
+        assert!(!headless.game.wants_to_quit());
+
+        // Quitting is the last item on the menu
+        headless.step(&[Action::MoveDown]);
+        headless.step(&[Action::MoveDown]);
+        headless.step(&[Action::Fire]);
+        assert!(!headless.game.wants_to_quit());
+        headless.step_with(&InputSnapshot::releasing(&[Action::Fire]));
+        assert!(headless.game.wants_to_quit());
+    }
+
+    #[test]
+    fn fire_after_game_over_starts_a_new_run() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        headless.step(&[Action::Fire]);
+        headless.game.clear_wave();
+        headless.step(&[]);
+        assert!(headless.game.score() > 0);
+
+        headless.game.end_run();
+        assert_eq!(headless.game.state(), game::GameState::GameOver);
+        headless.step(&[Action::Fire]);
+        assert_eq!(headless.game.state(), game::GameState::Playing);
+        assert_eq!(headless.game.score(), 0);
+        assert_eq!(headless.game.wave(), 1);
+        assert_eq!(headless.game.invader_count(), 55);
+    }
+
+    #[test]
+    fn clearing_a_wave_spawns_the_next() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        headless.step(&[Action::Fire]);
+        assert_eq!(headless.game.wave(), 1);
+
+        headless.game.clear_wave();
+        assert_eq!(headless.game.invader_count(), 0);
+        headless.step(&[]);
+        assert_eq!(headless.game.wave(), 2);
+        assert_eq!(headless.game.invader_count(), 55);
+        assert_eq!(headless.game.state(), game::GameState::Playing);
+    }
+
+    #[test]
+    fn the_third_wave_is_a_boss_instead_of_a_formation() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        headless.step(&[Action::Fire]);
+        for _ in 0..2 {
+            headless.game.clear_wave();
+            headless.step(&[]);
+        }
+        assert_eq!(headless.game.wave(), 3);
+        assert_eq!(headless.game.invader_count(), 0);
+        assert_eq!(headless.game.boss_parts(), 3);
+        assert_eq!(headless.game.state(), game::GameState::Playing);
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn queued_input_takes_effect_within_a_frame() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        let events = [input::InputEvent {
+            key: KeyCode::Space,
+            state: ElementState::Pressed,
+            time: Instant::now(),
+        }];
+        let input = input::InputMap::default().snapshot(&events, &[]);
+        headless.step_with(&input);
+        assert_eq!(headless.game.state(), game::GameState::Playing);
+        assert!(headless.game.input_latency().unwrap() < clock::FIXED_STEP);
+    }
+
+    #[test]
+    fn the_same_seed_string_fires_the_same_shots() {
+        let run = || {
+            let mut headless = Headless::with_options(GameOptions {
+                seed: Some("vaders123".to_string()),
+                ..GameOptions::default()
+            })?;
+            headless.step(&[Action::Fire]);
+            for _ in 0..200 {
+                headless.step(&[]);
+            }
+            Some(headless.game.enemy_bullet_positions())
+        };
+        let Some(first) = run() else {
+            return;
+        };
+        assert!(!first.is_empty());
+        assert_eq!(run(), Some(first));
+    }
+
+    // Where a scripted run ends up, compared against a recorded one
+    #[derive(Debug)]
+    struct Snapshot {
+        player: nalgebra_glm::Vec2,
+        first_invader: nalgebra_glm::Vec2,
+        invaders: usize,
+        shots: usize,
+        score: u32,
+        lives: u8,
+    }
+
+    impl Snapshot {
+        fn of(game: &Game) -> Self {
+            Self {
+                player: game.player_position(),
+                first_invader: game.invader_positions()[0],
+                invaders: game.invader_count(),
+                shots: game.shot_count(),
+                score: game.score(),
+                lives: game.lives(),
+            }
+        }
+
+        // Positions within tolerance world units, the rest exactly
+        fn assert_matches(&self, golden: &Snapshot, tolerance: f32) {
+            let near = |a: nalgebra_glm::Vec2, b: nalgebra_glm::Vec2| {
+                nalgebra_glm::distance(&a, &b) <= tolerance
+            };
+            assert!(
+                near(self.player, golden.player)
+                    && near(self.first_invader, golden.first_invader)
+                    && (self.invaders, self.shots, self.score, self.lives)
+                        == (golden.invaders, golden.shots, golden.score, golden.lives),
+                "{self:?} != {golden:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_scripted_run_matches_its_golden_snapshot() {
+        let mut options = GameOptions {
+            seed: Some("GOLDEN".to_string()),
+            ..GameOptions::default()
+        };
+        // No enemy fire, whose aim would depend on the RNG
+        options.formation.fire_interval = 1e9;
+        let Some(mut headless) = Headless::with_options(options) else {
+            return;
+        };
+
+        // 2 seconds to the right, then one shot that takes out a bottom row
+        // invader 36 steps later
+        headless.step(&[Action::Fire]);
+        for _ in 0..120 {
+            headless.step(&[Action::MoveRight]);
+        }
+        headless.step(&[Action::Fire]);
+        for _ in 0..60 {
+            headless.step(&[]);
+        }
+
+        // The formation marched 181 steps at 10 units a second from x 29, the
+        // player 120 steps at 60 from x 30
+        Snapshot::of(&headless.game).assert_matches(
+            &Snapshot {
+                player: nalgebra_glm::vec2(150.0, 30.0),
+                first_invader: nalgebra_glm::vec2(29.0 + 181.0 / 6.0, 162.0),
+                invaders: 54,
+                shots: 0,
+                score: 10,
+                lives: 3,
+            },
+            0.01,
+        );
+    }
+
+    #[test]
+    fn scheduled_waves_spawn_at_their_ticks() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        let schedule = WaveSchedule::parse("0:2x3,1:4x5").unwrap();
+        headless.game = headless.game.with_schedule(schedule);
+        assert_eq!(headless.game.invader_count(), 0);
+
+        // The first step of the run spawns the first wave
+        headless.step(&[Action::Fire]);
+        headless.step(&[]);
+        assert_eq!(
+            (headless.game.wave(), headless.game.invader_count()),
+            (1, 6)
+        );
+        // Clearing it doesn't bring the next one early
+        headless.game.clear_wave();
+        for _ in 0..58 {
+            headless.step(&[]);
+        }
+        assert_eq!(
+            (headless.game.wave(), headless.game.invader_count()),
+            (1, 0)
+        );
+        headless.step(&[]);
+        assert_eq!(
+            (headless.game.wave(), headless.game.invader_count()),
+            (2, 20)
+        );
+    }
+
+    #[test]
+    fn practice_ignores_hits_and_starts_from_the_picked_wave() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        headless.step(&[Action::MoveDown]);
+        headless.step(&[Action::MoveRight]);
+        headless.step(&[Action::MoveRight]);
+        headless.step(&[Action::Fire]);
+        assert_eq!(headless.game.state(), game::GameState::Playing);
+        assert_eq!(headless.game.wave(), 3);
+
+        headless.game.hit_player(&headless.context);
+        assert_eq!(headless.game.lives(), 3);
+    }
+
+    #[test]
+    fn a_redraw_before_init_is_skipped() {
+        let Some(mut headless) = Headless::new() else {
+            return;
+        };
+        let mut clock = Clock::new(clock::FIXED_STEP, clock::MAX_CATCHUP_STEPS);
+        assert_eq!(redraw(&headless.context, None, &mut clock), Redraw::Skipped);
+
+        // A headless game has nothing to present, but the frame still runs
+        assert_eq!(
+            redraw(&headless.context, Some(&mut headless.game), &mut clock),
+            Redraw::Drawn(None)
+        );
+    }
+
+    #[test]
+    fn enumeration_includes_the_adapter_in_use() {
+        let Some(context) = test_context() else {
+            return;
+        };
+        let info = context.adapter.get_info();
+        assert!(context.enumerate_adapters().contains(&info));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::input::{Action, InputSnapshot};
+
+// The pause menu's choices, top to bottom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuItem {
+    Resume,
+    Restart,
+    Quit,
+}
+
+impl MenuItem {
+    pub const ALL: [MenuItem; 3] = [MenuItem::Resume, MenuItem::Restart, MenuItem::Quit];
+
+    fn label(self) -> &'static str {
+        match self {
+            MenuItem::Resume => "RESUME",
+            MenuItem::Restart => "RESTART",
+            MenuItem::Quit => "QUIT",
+        }
+    }
+}
+
+// Which item is highlighted, kept while the game is paused
+#[derive(Debug, Default)]
+pub struct PauseMenu {
+    selected: usize,
+}
+
+impl PauseMenu {
+    // Opens on Resume so a stray fire press doesn't quit
+    pub fn open(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn selected(&self) -> MenuItem {
+        MenuItem::ALL[self.selected]
+    }
+
+    // Up and down move the highlight, wrapping around and repeating while held.
+    // Fire picks it once let go, like a button, so holding fire commits nothing yet.
+    pub fn handle_input(&mut self, input: &InputSnapshot) -> Option<MenuItem> {
+        let count = MenuItem::ALL.len();
+        if input.is_just_pressed_or_repeated(Action::MoveUp) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.is_just_pressed_or_repeated(Action::MoveDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+        input
+            .is_just_released(Action::Fire)
+            .then(|| self.selected())
+    }
+
+    // One line per item, the highlighted one marked
+    pub fn lines(&self) -> String {
+        MenuItem::ALL
+            .iter()
+            .map(|&item| {
+                let marker = if item == self.selected() { '>' } else { ' ' };
+                format!("{marker} {}", item.label())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Highest wave practice can start from
+pub const MAX_PRACTICE_WAVE: u32 = 9;
+
+// What the title screen starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TitleChoice {
+    Play,
+    // Can't lose lives and doesn't set high scores, from the given wave
+    Practice { wave: u32 },
+}
+
+// Play or practice, left and right pick practice's starting wave
+#[derive(Debug)]
+pub struct TitleMenu {
+    practice: bool,
+    wave: u32,
+}
+
+impl Default for TitleMenu {
+    fn default() -> Self {
+        Self {
+            practice: false,
+            wave: 1,
+        }
+    }
+}
+
+impl TitleMenu {
+    // Fire on press here, the same press that used to start the game
+    pub fn handle_input(&mut self, input: &InputSnapshot) -> Option<TitleChoice> {
+        if input.is_just_pressed_or_repeated(Action::MoveUp)
+            || input.is_just_pressed_or_repeated(Action::MoveDown)
+        {
+            self.practice = !self.practice;
+        }
+        if self.practice {
+            if input.is_just_pressed_or_repeated(Action::MoveLeft) {
+                self.wave = self.wave.saturating_sub(1).max(1);
+            }
+            if input.is_just_pressed_or_repeated(Action::MoveRight) {
+                self.wave = (self.wave + 1).min(MAX_PRACTICE_WAVE);
+            }
+        }
+        input.is_just_pressed(Action::Fire).then(|| self.selected())
+    }
+
+    pub fn selected(&self) -> TitleChoice {
+        if self.practice {
+            TitleChoice::Practice { wave: self.wave }
+        } else {
+            TitleChoice::Play
+        }
+    }
+
+    pub fn lines(&self) -> String {
+        let (play, practice) = if self.practice {
+            (' ', '>')
+        } else {
+            ('>', ' ')
+        };
+        format!("{play} PLAY\n{practice} PRACTICE WAVE {}", self.wave)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fire_picks_the_highlighted_item() {
+        let mut menu = PauseMenu::default();
+        assert_eq!(menu.handle_input(&InputSnapshot::default()), None);
+        assert_eq!(
+            menu.handle_input(&InputSnapshot::pressing(&[Action::Fire])),
+            None
+        );
+        assert_eq!(
+            menu.handle_input(&InputSnapshot::releasing(&[Action::Fire])),
+            Some(MenuItem::Resume)
+        );
+
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveDown]));
+        assert_eq!(menu.selected(), MenuItem::Restart);
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveDown]));
+        assert_eq!(menu.selected(), MenuItem::Quit);
+        assert_eq!(menu.lines(), "  RESUME\n  RESTART\n> QUIT");
+        assert_eq!(
+            menu.handle_input(&InputSnapshot::releasing(&[Action::Fire])),
+            Some(MenuItem::Quit)
+        );
+    }
+
+    #[test]
+    fn practice_starts_from_the_picked_wave() {
+        let mut menu = TitleMenu::default();
+        assert_eq!(
+            menu.handle_input(&InputSnapshot::pressing(&[Action::Fire])),
+            Some(TitleChoice::Play)
+        );
+        // Left and right only change practice's wave
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveRight]));
+        assert_eq!(menu.lines(), "> PLAY\n  PRACTICE WAVE 1");
+
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveDown]));
+        for _ in 0..2 {
+            menu.handle_input(&InputSnapshot::pressing(&[Action::MoveRight]));
+        }
+        assert_eq!(menu.lines(), "  PLAY\n> PRACTICE WAVE 3");
+        assert_eq!(
+            menu.handle_input(&InputSnapshot::pressing(&[Action::Fire])),
+            Some(TitleChoice::Practice { wave: 3 })
+        );
+
+        for _ in 0..20 {
+            menu.handle_input(&InputSnapshot::pressing(&[Action::MoveLeft]));
+        }
+        assert_eq!(menu.selected(), TitleChoice::Practice { wave: 1 });
+        for _ in 0..20 {
+            menu.handle_input(&InputSnapshot::pressing(&[Action::MoveRight]));
+        }
+        assert_eq!(
+            menu.selected(),
+            TitleChoice::Practice {
+                wave: MAX_PRACTICE_WAVE
+            }
+        );
+    }
+
+    #[test]
+    fn the_highlight_wraps_and_reopens_on_resume() {
+        let mut menu = PauseMenu::default();
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveUp]));
+        assert_eq!(menu.selected(), MenuItem::Quit);
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveDown]));
+        assert_eq!(menu.selected(), MenuItem::Resume);
+
+        menu.handle_input(&InputSnapshot::pressing(&[Action::MoveDown]));
+        menu.open();
+        assert_eq!(menu.selected(), MenuItem::Resume);
+    }
+}
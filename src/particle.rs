@@ -0,0 +1,149 @@
+use std::f32::consts::TAU;
+
+use crate::event::GameEvent;
+use crate::instance::InstanceRaw;
+
+// Live particles past this are not spawned, so a chain of explosions can't grow
+// the instance buffer without bound
+pub const MAX_PARTICLES: usize = 512;
+
+// In world units
+const PARTICLE_SIZE: f32 = 1.5;
+// In world units per second
+const SPEED_RANGE: (f32, f32) = (20.0, 60.0);
+// In seconds
+const LIFETIME_RANGE: (f32, f32) = (0.3, 0.8);
+const COLOR: [f32; 3] = [1.0, 0.8, 0.3];
+
+struct Particle {
+    pos: nalgebra_glm::Vec2,
+    velocity: nalgebra_glm::Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+// Plain positions and velocities instead of Sprites, drawn through
+// Renderer::draw_particles as instances of one quad
+pub struct Emitter {
+    particles: Vec<Particle>,
+    rng: fastrand::Rng,
+    // Per kill, fewer on low end hardware
+    explosion_particles: usize,
+}
+
+impl Emitter {
+    // explosion_particles is the count per kill, see PerformanceProfile
+    pub fn new(explosion_particles: usize) -> Self {
+        Self {
+            particles: Vec::new(),
+            rng: fastrand::Rng::new(),
+            explosion_particles,
+        }
+    }
+
+    // Spawns up to count particles flying out from pos in random directions
+    pub fn burst(&mut self, pos: nalgebra_glm::Vec2, count: usize) {
+        let count = count.min(MAX_PARTICLES - self.particles.len());
+        for _ in 0..count {
+            let angle = self.rng.f32() * TAU;
+            let speed = lerp(SPEED_RANGE, self.rng.f32());
+            self.particles.push(Particle {
+                pos,
+                velocity: nalgebra_glm::vec2(angle.cos(), angle.sin()) * speed,
+                age: 0.0,
+                lifetime: lerp(LIFETIME_RANGE, self.rng.f32()),
+            });
+        }
+    }
+
+    // Everything that dies explodes
+    pub fn observe(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::InvaderKilled { pos } | GameEvent::PlayerHit { pos } => {
+                self.burst(pos, self.explosion_particles);
+            }
+            GameEvent::WaveCleared
+            | GameEvent::BonusAwarded { .. }
+            | GameEvent::PowerUpCollected { .. } => {}
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.pos += particle.velocity * dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+    }
+
+    // Fully opaque when spawned, fading out linearly over the lifetime
+    pub fn instances(&self) -> impl Iterator<Item = InstanceRaw> + '_ {
+        self.particles.iter().map(|particle| {
+            let alpha = 1.0 - particle.age / particle.lifetime;
+            let half = PARTICLE_SIZE / 2.0;
+            let model = nalgebra_glm::scale(
+                &nalgebra_glm::translate(
+                    &nalgebra_glm::identity(),
+                    &nalgebra_glm::vec3(particle.pos.x - half, particle.pos.y - half, 0.0),
+                ),
+                &nalgebra_glm::vec3(PARTICLE_SIZE, PARTICLE_SIZE, 1.0),
+            );
+            InstanceRaw {
+                model: model.into(),
+                tint: [COLOR[0], COLOR[1], COLOR[2], alpha],
+                uv_rect: [0.0, 0.0, 1.0, 1.0],
+            }
+        })
+    }
+}
+
+fn lerp(range: (f32, f32), t: f32) -> f32 {
+    range.0 + (range.1 - range.0) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPLOSION_PARTICLES: usize = 24;
+
+    #[test]
+    fn a_burst_spawns_the_requested_count() {
+        let mut emitter = Emitter::new(EXPLOSION_PARTICLES);
+        emitter.burst(nalgebra_glm::vec2(10.0, 10.0), 8);
+        assert_eq!(emitter.instances().count(), 8);
+    }
+
+    #[test]
+    fn bursts_stop_at_the_particle_limit() {
+        let mut emitter = Emitter::new(EXPLOSION_PARTICLES);
+        emitter.burst(nalgebra_glm::vec2(0.0, 0.0), MAX_PARTICLES - 4);
+        emitter.burst(nalgebra_glm::vec2(0.0, 0.0), 10);
+        assert_eq!(emitter.instances().count(), MAX_PARTICLES);
+    }
+
+    #[test]
+    fn explosions_use_the_configured_count() {
+        let mut emitter = Emitter::new(8);
+        emitter.observe(&GameEvent::PlayerHit {
+            pos: nalgebra_glm::vec2(50.0, 50.0),
+        });
+        assert_eq!(emitter.instances().count(), 8);
+    }
+
+    #[test]
+    fn particles_are_pruned_after_their_lifetime() {
+        let mut emitter = Emitter::new(EXPLOSION_PARTICLES);
+        emitter.observe(&GameEvent::InvaderKilled {
+            pos: nalgebra_glm::vec2(50.0, 50.0),
+        });
+        assert_eq!(emitter.instances().count(), EXPLOSION_PARTICLES);
+
+        emitter.update(LIFETIME_RANGE.0 / 2.0);
+        assert_eq!(emitter.instances().count(), EXPLOSION_PARTICLES);
+
+        emitter.update(LIFETIME_RANGE.1);
+        assert_eq!(emitter.instances().count(), 0);
+    }
+}
@@ -1,67 +1,658 @@
-use crate::{input, sprite::Sprite, Context, Renderer};
-use image::io::Reader as ImageReader;
-use wgpu::util::DeviceExt;
-use winit::keyboard::KeyCode;
+use crate::animation::{AnimatedSprite, Animation, PlayMode};
+use crate::atlas;
+use crate::bullet::Bullet;
+use crate::clock::Timer;
+use crate::input::{Action, InputSnapshot};
+use crate::powerup::PowerUpKind;
+use crate::rect::Rect;
+use crate::renderer::{Renderer, ShaderId};
+use crate::texture::{SolidTexture, TextureError};
+use crate::{assets, sprite::Sprite, texture_cache::TextureCache, Context, WORLD_WIDTH};
 
-const SPEED: f32 = 0.08;
+// In world units per second, diagonals included
+const SPEED: f32 = 60.0;
+
+// In seconds
+const DEFAULT_FIRE_COOLDOWN: f64 = 0.4;
+const DEFAULT_FIRE_BUFFER_WINDOW: f64 = 0.1;
+// Played once per shot, a squashed frame with the nose stretched into a muzzle
+// flash by the clamped sampler, then the ship at rest
+const RECOIL_FRAMES: [[f32; 4]; 2] = [[0.0, 0.0, 1.0, 8.0 / 7.0], [0.0, 0.0, 1.0, 1.0]];
+const RECOIL_FRAME_DURATION: f32 = 0.06;
+const BULLET_SIZE: (f32, f32) = (1.0, 4.0);
+// In world units per second
+const BULLET_SPEED: f32 = 120.0;
+
+const START_LIVES: u8 = 3;
+// Extra lives stop adding up past this
+const MAX_LIVES: u8 = 5;
+// Seconds after a hit during which further hits are ignored
+const INVULNERABILITY: f64 = 2.0;
+// Fades whatever the base tint is while invulnerable
+const INVULNERABLE_TINT: [f32; 4] = [1.0, 1.0, 1.0, 0.4];
+// Rapid fire's cooldown as a fraction of the normal one, and how long it lasts
+const RAPID_FIRE_FACTOR: f64 = 0.5;
+const RAPID_FIRE_DURATION: f64 = 8.0;
+const SPREAD_DURATION: f64 = 8.0;
+// Radians between the middle shot of a spread and the outer ones
+const SPREAD_ANGLE: f32 = 0.25;
+// A square around the ship's center, its ring drawn by shield.wgsl
+const SHIELD_SIZE: f32 = 17.0;
+const SHIELD_TINT: [f32; 4] = [0.3, 1.0, 1.0, 0.8];
+// Behind the ship, which is at z 0
+const SHIELD_Z: f32 = -0.5;
+// In radians per second, turns the shimmer around the ring
+const SHIELD_SPIN: f32 = 2.0;
+const SPAWN_Y: f32 = 30.0;
+// Where a new run starts, respawns after a hit are centered instead
+const START_X: f32 = 30.0;
+
+// Bottom left of the shield, centered on ship
+fn shield_position(ship: &Sprite) -> nalgebra_glm::Vec2 {
+    ship.position() + (ship.size() - nalgebra_glm::vec2(SHIELD_SIZE, SHIELD_SIZE)) / 2.0
+}
+
+// Keeps the whole sprite inside area
+fn clamp_to_area(
+    pos: nalgebra_glm::Vec2,
+    size: nalgebra_glm::Vec2,
+    area: Rect,
+) -> nalgebra_glm::Vec2 {
+    let max = area.pos + area.size - size;
+    nalgebra_glm::vec2(
+        pos.x.clamp(area.pos.x, max.x.max(area.pos.x)),
+        pos.y.clamp(area.pos.y, max.y.max(area.pos.y)),
+    )
+}
+
+// Offset for one step of input held in direction, whose components are -1, 0
+// or 1. Normalized so a diagonal isn't faster than a straight move.
+fn movement(direction: nalgebra_glm::Vec2, dt: f32) -> nalgebra_glm::Vec2 {
+    if direction == nalgebra_glm::Vec2::zeros() {
+        return direction;
+    }
+    direction.normalize() * SPEED * dt
+}
+
+// Offset for one step towards a point offset away, at full speed but without
+// overshooting it
+fn steer(offset: nalgebra_glm::Vec2, dt: f32) -> nalgebra_glm::Vec2 {
+    let max = SPEED * dt;
+    if nalgebra_glm::length(&offset) <= max {
+        return offset;
+    }
+    offset.normalize() * max
+}
+
+// Remembers a fire press made during the cooldown, times are seconds of
+// simulation time
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FireBuffer {
+    window: f64,
+    pressed_at: Option<f64>,
+}
+
+impl FireBuffer {
+    fn new(window: f64) -> Self {
+        Self {
+            window,
+            pressed_at: None,
+        }
+    }
+
+    // Whether to fire now. A press on cooldown is buffered and fires on the first
+    // call after the cooldown, unless more than window seconds went by.
+    fn should_fire(&mut self, pressed: bool, on_cooldown: bool, now: f64) -> bool {
+        if pressed {
+            self.pressed_at = Some(now);
+        }
+        if on_cooldown {
+            return false;
+        }
+        match self.pressed_at.take() {
+            Some(at) => now - at <= self.window,
+            None => false,
+        }
+    }
+}
+
+// Lives left and the grace period after losing one
+#[derive(Debug, Default)]
+struct Lives {
+    left: u8,
+    invulnerable: Timer,
+    // Practice, every hit is ignored
+    invincible: bool,
+    // Absorbs the next hit instead of a life
+    shield: bool,
+}
+
+impl Lives {
+    fn new(count: u8) -> Self {
+        Self {
+            left: count,
+            invulnerable: Timer::default(),
+            invincible: false,
+            shield: false,
+        }
+    }
+
+    // Takes a life unless invincible, still invulnerable or already out, returns
+    // whether it did. Losing a life that isn't the last starts the grace period.
+    // A shield takes the hit instead, without one.
+    fn hit(&mut self) -> bool {
+        if self.invincible || self.invulnerable.is_running() || self.left == 0 {
+            return false;
+        }
+        if self.shield {
+            self.shield = false;
+            return false;
+        }
+        self.left -= 1;
+        if self.left > 0 {
+            self.invulnerable.start(INVULNERABILITY);
+        }
+        true
+    }
+
+    // One more life unless already at max, returns whether it was added
+    fn gain(&mut self, max: u8) -> bool {
+        if self.left >= max {
+            return false;
+        }
+        self.left += 1;
+        true
+    }
+
+    // Returns true on the step the grace period ends
+    fn update(&mut self, dt: f32) -> bool {
+        self.invulnerable.update(dt)
+    }
+}
+
+// What the power-ups caught have changed, until their timers run out
+#[derive(Debug, Default)]
+struct Effects {
+    rapid_fire: Timer,
+    spread: Timer,
+}
+
+impl Effects {
+    // Catching one while it's still running starts it over
+    fn apply(&mut self, kind: PowerUpKind) {
+        match kind {
+            PowerUpKind::RapidFire => self.rapid_fire.start(RAPID_FIRE_DURATION),
+            PowerUpKind::Spread => self.spread.start(SPREAD_DURATION),
+            // Until it takes a hit, see Lives
+            PowerUpKind::Shield => {}
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.rapid_fire.update(dt);
+        self.spread.update(dt);
+    }
+
+    // One per bullet of a shot, straight up or fanned out left to right
+    fn shot_velocities(&self) -> Vec<nalgebra_glm::Vec2> {
+        let up = nalgebra_glm::vec2(0.0, BULLET_SPEED);
+        if !self.spread.is_running() {
+            return vec![up];
+        }
+        [SPREAD_ANGLE, 0.0, -SPREAD_ANGLE]
+            .iter()
+            .map(|&angle| nalgebra_glm::rotate_vec2(&up, angle))
+            .collect()
+    }
+
+    // base is the ship's normal cooldown
+    fn fire_cooldown(&self, base: f64) -> f64 {
+        if self.rapid_fire.is_running() {
+            base * RAPID_FIRE_FACTOR
+        } else {
+            base
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerOptions {
+    // Seconds between shots
+    pub fire_cooldown: f64,
+    // A press this close to the end of the cooldown still fires, once it runs
+    // out. 0 turns buffering off, presses during the cooldown are then dropped.
+    pub fire_buffer_window: f64,
+    pub starting_lives: u8,
+    // Extra lives are only granted below this
+    pub max_lives: u8,
+}
+
+impl Default for PlayerOptions {
+    fn default() -> Self {
+        Self {
+            fire_cooldown: DEFAULT_FIRE_COOLDOWN,
+            fire_buffer_window: DEFAULT_FIRE_BUFFER_WINDOW,
+            starting_lives: START_LIVES,
+            max_lives: MAX_LIVES,
+        }
+    }
+}
 
 pub struct Player {
-    sprite: Sprite,
-    texture: wgpu::Texture,
+    sprite: AnimatedSprite,
+    // Only drawn while the shield is up, kept centered on the ship
+    shield: Sprite,
+    bullet_texture: SolidTexture,
+    fire_cooldown: f64,
+    // Runs until the next shot is allowed
+    cooldown: Timer,
+    fire_buffer: FireBuffer,
+    // Simulation seconds since spawning, timestamps the fire buffer
+    time: f64,
+    lives: Lives,
+    effects: Effects,
+    starting_lives: u8,
+    max_lives: u8,
 }
 impl Player {
-    pub fn init(context: &Context, sampler: &wgpu::Sampler, renderer: &Renderer) -> Self {
-        let img = ImageReader::open("player.png")
-            .unwrap()
-            .decode()
-            .unwrap()
-            .flipv()
-            .to_rgba8();
-        let texels = bytemuck::cast_slice(img.as_raw());
-
-        let texture = context.device.create_texture_with_data(
-            &context.queue,
-            &wgpu::TextureDescriptor {
-                label: None,
-                size: wgpu::Extent3d {
-                    width: img.width(),
-                    height: img.height(),
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            },
-            wgpu::util::TextureDataOrder::MipMajor,
-            texels,
-        );
-        let sprite = Sprite::new(
-            nalgebra_glm::vec2(30.0, 30.0),
-            nalgebra_glm::vec2(13.0, 8.0),
+    pub fn init(
+        context: &Context,
+        textures: &mut TextureCache,
+        sampler: &wgpu::Sampler,
+        renderer: &Renderer,
+        options: PlayerOptions,
+    ) -> Result<Self, TextureError> {
+        let texture = textures.get_or_embedded(context, "player.png", assets::PLAYER_PNG)?;
+        let sprite = Sprite::from_atlas_entry(
+            nalgebra_glm::vec2(START_X, SPAWN_Y),
+            atlas::PLAYER_SHIP,
+            None,
             &texture,
-            &renderer.pipeline.get_bind_group_layout(1),
+            renderer.sprite_layouts(),
             sampler,
             context,
         );
+        let mut recoil = Animation::new(
+            RECOIL_FRAMES.to_vec(),
+            RECOIL_FRAME_DURATION,
+            PlayMode::Once,
+        );
+        // Spawns at rest, not mid-recoil
+        recoil.update(RECOIL_FRAME_DURATION * RECOIL_FRAMES.len() as f32);
+        let bullet_texture = textures.solid(context);
+        let mut shield = Sprite::new(
+            shield_position(&sprite),
+            nalgebra_glm::vec2(SHIELD_SIZE, SHIELD_SIZE),
+            &bullet_texture.texture,
+            renderer.sprite_layouts(),
+            &bullet_texture.sampler,
+            context,
+        );
+        shield.set_tint(SHIELD_TINT, context);
+        shield.set_z(SHIELD_Z, context);
 
-        Self { sprite, texture }
+        Ok(Self {
+            sprite: AnimatedSprite::new(sprite, recoil, context),
+            shield,
+            bullet_texture,
+            fire_cooldown: options.fire_cooldown,
+            cooldown: Timer::default(),
+            fire_buffer: FireBuffer::new(options.fire_buffer_window),
+            time: 0.0,
+            lives: Lives::new(options.starting_lives),
+            effects: Effects::default(),
+            starting_lives: options.starting_lives,
+            max_lives: options.max_lives,
+        })
     }
 
-    pub fn update(&mut self, context: &Context) {
-        if input::is_key_pressed(KeyCode::ArrowLeft) {
-            self.sprite
-                .move_by(&nalgebra_glm::vec2(-SPEED, 0.0), context);
+    // area is where the ship may go, the world below the HUD
+    pub fn update(&mut self, context: &Context, input: &InputSnapshot, area: Rect, dt: f32) {
+        self.time += f64::from(dt);
+        self.cooldown.update(dt);
+        self.effects.update(dt);
+        self.sprite.update(context, dt);
+        if self.lives.update(dt) {
+            self.sprite.get_sprite_mut().set_tint_effect(None, context);
+        }
+
+        if self.lives.shield {
+            let shield = &mut self.shield;
+            shield.set_rotation(shield.rotation() + SHIELD_SPIN * dt, context);
+        }
+
+        if !input.is_focused() {
+            return;
+        }
+        let mut direction = nalgebra_glm::vec2(0.0, 0.0);
+        if input.is_pressed(Action::MoveLeft) {
+            direction.x -= 1.0;
+        }
+        if input.is_pressed(Action::MoveRight) {
+            direction.x += 1.0;
         }
-        if input::is_key_pressed(KeyCode::ArrowRight) {
-            self.sprite
-                .move_by(&nalgebra_glm::vec2(SPEED, 0.0), context);
+        if input.is_pressed(Action::MoveDown) {
+            direction.y -= 1.0;
         }
+        if input.is_pressed(Action::MoveUp) {
+            direction.y += 1.0;
+        }
+
+        // Keys win over the pointer, which drags the ship's center after it
+        let sprite = self.sprite.get_sprite_mut();
+        let offset = match input.pointer() {
+            Some(pointer) if direction == nalgebra_glm::vec2(0.0, 0.0) => {
+                steer(pointer - (sprite.position() + sprite.size() / 2.0), dt)
+            }
+            _ => movement(direction, dt),
+        };
+        if offset == nalgebra_glm::vec2(0.0, 0.0) {
+            return;
+        }
+
+        // Clamped before it's applied so holding into a wall can't push the sprite
+        // out of the play area
+        let pos = sprite.position() + offset;
+        sprite.set_position(clamp_to_area(pos, sprite.size(), area), context);
+        self.follow_with_shield(context);
+    }
+
+    fn follow_with_shield(&mut self, context: &Context) {
+        let pos = shield_position(self.sprite.get_sprite());
+        self.shield.set_position(pos, context);
+    }
+
+    // The ring is drawn with whatever renderer registered shield.wgsl as
+    pub fn set_shield_shader(&mut self, shader: ShaderId) {
+        self.shield.set_shader(shader);
+    }
+
+    // Fires from the top center of the ship when fire was pressed this frame, or
+    // shortly before the cooldown ran out, see FireBuffer. Empty if it didn't
+    // fire, three bullets during a spread.
+    pub fn shoot(
+        &mut self,
+        context: &Context,
+        input: &InputSnapshot,
+        renderer: &Renderer,
+    ) -> Vec<Bullet> {
+        if !input.is_focused() {
+            return Vec::new();
+        }
+        let pressed = input.is_just_pressed(Action::Fire);
+        if !self
+            .fire_buffer
+            .should_fire(pressed, self.cooldown.is_running(), self.time)
+        {
+            return Vec::new();
+        }
+        self.cooldown
+            .start(self.effects.fire_cooldown(self.fire_cooldown));
+        self.sprite.restart(context);
+
+        let size = nalgebra_glm::vec2(BULLET_SIZE.0, BULLET_SIZE.1);
+        let ship = self.sprite.get_sprite();
+        let pos =
+            ship.position() + nalgebra_glm::vec2((ship.size().x - size.x) / 2.0, ship.size().y);
+        self.effects
+            .shot_velocities()
+            .into_iter()
+            .map(|velocity| {
+                let mut sprite = Sprite::new(
+                    pos,
+                    size,
+                    &self.bullet_texture.texture,
+                    renderer.sprite_layouts(),
+                    &self.bullet_texture.sampler,
+                    context,
+                );
+                // Along its path like the enemies' bullets, 0 is straight up
+                sprite.set_rotation(-velocity.x.atan2(velocity.y), context);
+                Bullet::new(sprite, velocity, context)
+            })
+            .collect()
+    }
+
+    // Back to how a new run starts, all lives and no cooldown. Invincibility is
+    // kept.
+    pub fn reset(&mut self, context: &Context) {
+        self.lives = Lives {
+            invincible: self.lives.invincible,
+            ..Lives::new(self.starting_lives)
+        };
+        self.cooldown = Timer::default();
+        self.effects = Effects::default();
+        self.fire_buffer = FireBuffer::new(self.fire_buffer.window);
+        self.time = 0.0;
+        let sprite = self.sprite.get_sprite_mut();
+        sprite.set_tint_effect(None, context);
+        sprite.set_position(nalgebra_glm::vec2(START_X, SPAWN_Y), context);
+        self.follow_with_shield(context);
+    }
+
+    // Takes a life unless still invulnerable from the last hit, returns whether it
+    // did. A surviving player respawns at the bottom center.
+    pub fn hit(&mut self, context: &Context) -> bool {
+        if !self.lives.hit() {
+            return false;
+        }
+        if self.lives.left > 0 {
+            let sprite = self.sprite.get_sprite_mut();
+            sprite.set_tint_effect(Some(INVULNERABLE_TINT), context);
+            let x = (WORLD_WIDTH - sprite.size().x) / 2.0;
+            sprite.set_position(nalgebra_glm::vec2(x, SPAWN_Y), context);
+            self.follow_with_shield(context);
+        }
+        true
+    }
+
+    // A caught power-up, its effect lasts a while or, for the shield, until
+    // the next hit
+    pub fn apply(&mut self, kind: PowerUpKind) {
+        match kind {
+            PowerUpKind::Shield => self.lives.shield = true,
+            kind => self.effects.apply(kind),
+        }
+    }
+
+    pub fn shield_sprite(&self) -> Option<&Sprite> {
+        self.lives.shield.then_some(&self.shield)
+    }
+
+    // Hits are ignored while invincible, for practice
+    pub fn set_invincible(&mut self, invincible: bool) {
+        self.lives.invincible = invincible;
+    }
+
+    // Returns whether the life was added, see PlayerOptions::max_lives
+    pub fn add_life(&mut self) -> bool {
+        self.lives.gain(self.max_lives)
+    }
+
+    pub fn lives(&self) -> u8 {
+        self.lives.left
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.lives.left > 0
     }
 
     pub fn get_sprite(&self) -> &Sprite {
-        &self.sprite
+        self.sprite.get_sprite()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn press_during_cooldown_fires_when_it_ends() {
+        let mut buffer = FireBuffer::new(0.1);
+        assert!(!buffer.should_fire(true, true, 0.35));
+        assert!(!buffer.should_fire(false, true, 0.38));
+        assert!(buffer.should_fire(false, false, 0.4));
+        // Only once
+        assert!(!buffer.should_fire(false, false, 0.42));
+    }
+
+    #[test]
+    fn buffered_press_is_dropped_after_the_window() {
+        let mut buffer = FireBuffer::new(0.1);
+        assert!(!buffer.should_fire(true, true, 0.2));
+        assert!(!buffer.should_fire(false, false, 0.4));
+    }
+
+    #[test]
+    fn press_off_cooldown_fires_immediately() {
+        let mut buffer = FireBuffer::new(0.0);
+        assert!(buffer.should_fire(true, false, 1.0));
+        assert!(!buffer.should_fire(false, false, 1.0));
+    }
+
+    #[test]
+    fn a_hit_takes_one_life() {
+        let mut lives = Lives::new(3);
+        assert!(lives.hit());
+        assert_eq!(lives.left, 2);
+    }
+
+    #[test]
+    fn hits_during_invulnerability_are_ignored() {
+        let mut lives = Lives::new(3);
+        assert!(lives.hit());
+        assert!(!lives.update(1.0));
+        assert!(!lives.hit());
+        assert_eq!(lives.left, 2);
+
+        assert!(lives.update(1.0));
+        assert!(lives.hit());
+        assert_eq!(lives.left, 1);
+    }
+
+    #[test]
+    fn the_last_life_ends_the_game() {
+        let mut lives = Lives::new(1);
+        assert!(lives.hit());
+        assert_eq!(lives.left, 0);
+        // No grace period after the last life, and nothing left to take
+        assert!(!lives.invulnerable.is_running());
+        assert!(!lives.hit());
+    }
+
+    #[test]
+    fn rapid_fire_lowers_the_cooldown_until_it_runs_out() {
+        let mut effects = Effects::default();
+        assert_eq!(effects.fire_cooldown(0.4), 0.4);
+        effects.apply(PowerUpKind::RapidFire);
+        assert_eq!(effects.fire_cooldown(0.4), 0.2);
+
+        for _ in 0..7 {
+            effects.update(1.0);
+        }
+        assert_eq!(effects.fire_cooldown(0.4), 0.2);
+        effects.update(1.0);
+        assert_eq!(effects.fire_cooldown(0.4), 0.4);
+    }
+
+    #[test]
+    fn a_spread_fires_three_bullets_fanned_out() {
+        let mut effects = Effects::default();
+        assert_eq!(
+            effects.shot_velocities(),
+            [nalgebra_glm::vec2(0.0, BULLET_SPEED)]
+        );
+
+        effects.apply(PowerUpKind::Spread);
+        let velocities = effects.shot_velocities();
+        assert_eq!(velocities.len(), 3);
+        assert!(velocities[0].x < velocities[1].x && velocities[1].x < velocities[2].x);
+        assert_eq!(velocities[1].x, 0.0);
+        for velocity in &velocities {
+            assert!((nalgebra_glm::length(velocity) - BULLET_SPEED).abs() < 1e-3);
+        }
+
+        effects.update(SPREAD_DURATION as f32);
+        assert_eq!(effects.shot_velocities().len(), 1);
+    }
+
+    #[test]
+    fn a_shield_takes_one_hit_instead_of_a_life() {
+        let mut lives = Lives::new(3);
+        lives.shield = true;
+        assert!(!lives.hit());
+        assert_eq!(lives.left, 3);
+        assert!(!lives.shield);
+
+        assert!(lives.hit());
+        assert_eq!(lives.left, 2);
+    }
+
+    #[test]
+    fn hits_while_invincible_take_nothing() {
+        let mut lives = Lives::new(3);
+        lives.invincible = true;
+        assert!(!lives.hit());
+        assert_eq!(lives.left, 3);
+        assert!(!lives.invulnerable.is_running());
+    }
+
+    #[test]
+    fn extra_lives_stop_at_the_max() {
+        let mut lives = Lives::new(4);
+        assert!(lives.gain(5));
+        assert!(!lives.gain(5));
+        assert_eq!(lives.left, 5);
+    }
+
+    #[test]
+    fn holding_into_a_wall_stays_inside_the_play_area() {
+        let size = atlas::PLAYER_SHIP.size();
+        let area = crate::enemy::play_area(16.0);
+        let mut pos = nalgebra_glm::vec2(220.0, 160.0);
+        for _ in 0..100 {
+            pos = clamp_to_area(
+                pos + movement(nalgebra_glm::vec2(1.0, 1.0), 0.1),
+                size,
+                area,
+            );
+        }
+        // Stops under the HUD, not at the top of the world
+        assert_eq!(
+            pos,
+            nalgebra_glm::vec2(WORLD_WIDTH - 13.0, crate::WORLD_HEIGHT - 16.0 - 8.0)
+        );
+
+        for _ in 0..100 {
+            pos = clamp_to_area(
+                pos + movement(nalgebra_glm::vec2(-1.0, -1.0), 0.1),
+                size,
+                area,
+            );
+        }
+        assert_eq!(pos, nalgebra_glm::vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn steering_stops_on_the_pointer() {
+        let mut offset = nalgebra_glm::vec2(30.0, -40.0);
+        let step = steer(offset, 0.1);
+        assert!((nalgebra_glm::length(&step) - SPEED * 0.1).abs() < 1e-4);
+        for _ in 0..20 {
+            offset -= steer(offset, 0.1);
+        }
+        assert_eq!(offset, nalgebra_glm::vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn diagonals_move_at_the_same_speed() {
+        let straight = movement(nalgebra_glm::vec2(1.0, 0.0), 0.5);
+        let diagonal = movement(nalgebra_glm::vec2(-1.0, 1.0), 0.5);
+        assert!((nalgebra_glm::length(&straight) - SPEED * 0.5).abs() < 1e-4);
+        assert!((nalgebra_glm::length(&diagonal) - SPEED * 0.5).abs() < 1e-4);
+        assert_eq!(
+            movement(nalgebra_glm::vec2(0.0, 0.0), 0.5),
+            nalgebra_glm::vec2(0.0, 0.0)
+        );
     }
 }
@@ -0,0 +1,156 @@
+use crate::event::GameEvent;
+use crate::rect::Rect;
+use crate::renderer::Renderer;
+use crate::sprite::Sprite;
+use crate::texture::SolidTexture;
+use crate::Context;
+
+// Chance each killed invader drops one
+const DROP_CHANCE: f32 = 0.1;
+// In world units
+const SIZE: f32 = 5.0;
+// In world units per second
+const FALL_SPEED: f32 = 30.0;
+// In radians per second
+const SPIN_SPEED: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerUpKind {
+    // Halves the fire cooldown for a while
+    RapidFire,
+    // Three shots in a fan for a while
+    Spread,
+    // Absorbs the next hit
+    Shield,
+}
+
+impl PowerUpKind {
+    const ALL: [PowerUpKind; 3] = [
+        PowerUpKind::RapidFire,
+        PowerUpKind::Spread,
+        PowerUpKind::Shield,
+    ];
+
+    fn tint(self) -> [f32; 4] {
+        match self {
+            PowerUpKind::RapidFire => [1.0, 0.9, 0.2, 1.0],
+            PowerUpKind::Spread => [0.3, 0.6, 1.0, 1.0],
+            PowerUpKind::Shield => [0.3, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+// A spinning square falling from where an invader died
+struct PowerUp {
+    kind: PowerUpKind,
+    sprite: Sprite,
+}
+
+// Every power-up still falling, dropped by kills and caught by the ship
+pub struct PowerUps {
+    drops: Vec<PowerUp>,
+    texture: SolidTexture,
+    // Seeded with the run, so drops replay along with the enemy fire
+    rng: fastrand::Rng,
+}
+
+impl PowerUps {
+    pub fn new(texture: SolidTexture, seed: u64) -> Self {
+        Self {
+            drops: Vec::new(),
+            texture,
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    // Some of the invaders killed among events leave a power-up behind
+    pub fn spawn_drops<'a>(
+        &mut self,
+        events: impl IntoIterator<Item = &'a GameEvent>,
+        context: &Context,
+        renderer: &Renderer,
+    ) {
+        for event in events {
+            let GameEvent::InvaderKilled { pos } = *event else {
+                continue;
+            };
+            let Some(kind) = roll_drop(&mut self.rng) else {
+                continue;
+            };
+            let size = nalgebra_glm::vec2(SIZE, SIZE);
+            let mut sprite = Sprite::new(
+                pos - size / 2.0,
+                size,
+                &self.texture.texture,
+                renderer.sprite_layouts(),
+                &self.texture.sampler,
+                context,
+            );
+            sprite.set_tint(kind.tint(), context);
+            self.drops.push(PowerUp { kind, sprite });
+        }
+    }
+
+    // Falling and spinning, dropped once they're below area
+    pub fn update(&mut self, context: &Context, area: Rect, dt: f32) {
+        for drop in &mut self.drops {
+            let sprite = &mut drop.sprite;
+            sprite.move_by(&nalgebra_glm::vec2(0.0, -FALL_SPEED * dt), context);
+            sprite.set_rotation(sprite.rotation() + SPIN_SPEED * dt, context);
+        }
+        self.drops
+            .retain(|drop| drop.sprite.position().y + SIZE >= area.pos.y);
+    }
+
+    // Removes the power-ups touching rect and returns what they were
+    pub fn collect(&mut self, rect: Rect) -> Vec<PowerUpKind> {
+        let (caught, falling) = self
+            .drops
+            .drain(..)
+            .partition(|drop| drop.sprite.touches(rect));
+        self.drops = falling;
+        caught.into_iter().map(|drop: PowerUp| drop.kind).collect()
+    }
+
+    // For a new run, along with reseed
+    pub fn clear(&mut self) {
+        self.drops.clear();
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = fastrand::Rng::with_seed(seed);
+    }
+
+    pub fn sprites(&self) -> Vec<&Sprite> {
+        self.drops.iter().map(|drop| &drop.sprite).collect()
+    }
+}
+
+// DROP_CHANCE of some kind, each as likely as the others
+fn roll_drop(rng: &mut fastrand::Rng) -> Option<PowerUpKind> {
+    if rng.f32() >= DROP_CHANCE {
+        return None;
+    }
+    Some(PowerUpKind::ALL[rng.usize(..PowerUpKind::ALL.len())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn about_one_kill_in_ten_drops_something() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        let drops = (0..10_000)
+            .filter(|_| roll_drop(&mut rng).is_some())
+            .count();
+        assert!((800..1200).contains(&drops), "{drops}");
+
+        // The same seed drops the same way
+        let rolls = |seed| {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            (0..100).map(|_| roll_drop(&mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(3), rolls(3));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::surface::MSAA_SAMPLES;
+
+const PROFILE_ENV: &str = "VADERS_PROFILE";
+
+// Quality knobs set together, so weak hardware gets a sane default without
+// tuning each one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PerformanceProfile {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl PerformanceProfile {
+    // VADERS_PROFILE=low|medium|high, None to detect from the adapter
+    pub fn from_env() -> Option<Self> {
+        crate::enemy::env_option(PROFILE_ENV, Self::parse)
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    // Integrated GPUs share memory bandwidth with the CPU, software ones have none
+    pub fn detect(device_type: wgpu::DeviceType) -> Self {
+        match device_type {
+            wgpu::DeviceType::IntegratedGpu | wgpu::DeviceType::Cpu => Self::Low,
+            wgpu::DeviceType::VirtualGpu | wgpu::DeviceType::Other => Self::Medium,
+            wgpu::DeviceType::DiscreteGpu => Self::High,
+        }
+    }
+
+    pub fn msaa_samples(self) -> u32 {
+        match self {
+            Self::Low => 1,
+            Self::Medium | Self::High => MSAA_SAMPLES,
+        }
+    }
+
+    // Caps the internal resolution's multiple of its base size on HiDPI monitors
+    pub fn max_render_scale(self) -> u32 {
+        match self {
+            Self::Low => 1,
+            Self::Medium => 2,
+            Self::High => u32::MAX,
+        }
+    }
+
+    pub fn explosion_particles(self) -> usize {
+        match self {
+            Self::Low => 8,
+            Self::Medium => 16,
+            Self::High => 24,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_drops_msaa_and_render_scale() {
+        let (low, high) = (PerformanceProfile::Low, PerformanceProfile::High);
+        assert_eq!(low.msaa_samples(), 1);
+        assert!(high.msaa_samples() > 1);
+        assert!(low.max_render_scale() < high.max_render_scale());
+        assert!(low.explosion_particles() < high.explosion_particles());
+    }
+
+    #[test]
+    fn integrated_gpus_default_to_low() {
+        assert_eq!(
+            PerformanceProfile::detect(wgpu::DeviceType::IntegratedGpu),
+            PerformanceProfile::Low
+        );
+        assert_eq!(
+            PerformanceProfile::detect(wgpu::DeviceType::DiscreteGpu),
+            PerformanceProfile::High
+        );
+        assert_eq!(
+            PerformanceProfile::parse(" Medium"),
+            Some(PerformanceProfile::Medium)
+        );
+        assert_eq!(PerformanceProfile::parse("ultra"), None);
+    }
+}
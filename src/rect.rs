@@ -0,0 +1,88 @@
+use bytemuck::{Pod, Zeroable};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub pos: nalgebra_glm::Vec2,
+    pub size: nalgebra_glm::Vec2,
+}
+
+impl Rect {
+    pub fn is_empty(&self) -> bool {
+        self.size.x <= 0.0 || self.size.y <= 0.0
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct RectVertex {
+    pub pos: [f32; 2],
+    pub color: [f32; 4],
+}
+
+// Two triangles with the same winding as the sprite quad
+pub fn quad_vertices(rect: &Rect, color: wgpu::Color) -> [RectVertex; 6] {
+    let color = [
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+    ];
+    let (x0, y0) = (rect.pos.x, rect.pos.y);
+    let (x1, y1) = (x0 + rect.size.x, y0 + rect.size.y);
+
+    [
+        [x0, y1], // top left
+        [x0, y0], // bottom left
+        [x1, y1], // top right
+        [x0, y0], // bottom left
+        [x1, y0], // bottom right
+        [x1, y1], // top right
+    ]
+    .map(|pos| RectVertex { pos, color })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            pos: nalgebra_glm::vec2(x, y),
+            size: nalgebra_glm::vec2(width, height),
+        }
+    }
+
+    #[test]
+    fn zero_or_negative_sizes_are_empty() {
+        assert!(!rect(0.0, 0.0, 1.0, 1.0).is_empty());
+        assert!(rect(0.0, 0.0, 0.0, 1.0).is_empty());
+        assert!(rect(0.0, 0.0, 1.0, -1.0).is_empty());
+    }
+
+    #[test]
+    fn quad_covers_the_rect_in_one_color() {
+        let color = wgpu::Color {
+            r: 1.0,
+            g: 0.5,
+            b: 0.0,
+            a: 1.0,
+        };
+        let vertices = quad_vertices(&rect(2.0, 3.0, 4.0, 5.0), color);
+
+        let positions = vertices.map(|vertex| vertex.pos);
+        assert_eq!(
+            positions,
+            [
+                [2.0, 8.0],
+                [2.0, 3.0],
+                [6.0, 8.0],
+                [2.0, 3.0],
+                [6.0, 3.0],
+                [6.0, 8.0],
+            ]
+        );
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.color == [1.0, 0.5, 0.0, 1.0]));
+    }
+}
@@ -0,0 +1,21 @@
+struct VertOut {
+    @builtin(position) pos: vec4f,
+    @location(0) color: vec4f,
+}
+
+@group(0)
+@binding(0)
+var<uniform> projection: mat4x4<f32>;
+
+@vertex
+fn vs_main(@location(0) pos: vec2f, @location(1) color: vec4f) -> VertOut {
+    var out: VertOut;
+    out.pos = projection * vec4f(pos, 0.0, 1.0);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(vert: VertOut) -> @location(0) vec4f {
+    return vert.color;
+}
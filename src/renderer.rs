@@ -0,0 +1,1625 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::include_wgsl;
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+
+use crate::instance::{self, InstanceRaw};
+use crate::rect::{self, Rect, RectVertex};
+use crate::sprite::{Sprite, SpriteLayouts};
+use crate::texture_cache::TextureCache;
+use crate::upscale::{self, Upscaler};
+use crate::{Context, WORLD_HEIGHT, WORLD_WIDTH};
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 2],
+    tex_coord: [f32; 2],
+}
+
+impl Vertex {
+    // Shared by the sprite and instanced pipelines, both read the same quad
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+            wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Vertex>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+macro_rules! vert {
+    ( $( [$x:expr, $y:expr] ),* ) => {
+        [
+        $(
+            Vertex {
+                pos: $x,
+                tex_coord: $y,
+            },
+        )*
+        ]
+    };
+}
+
+// Both triangles wind counter-clockwise (wgpu's default front face) with y up
+const VERTICES: [Vertex; 6] = vert!(
+    [[0.0, 1.0], [0.0, 1.0]], // top left
+    [[0.0, 0.0], [0.0, 0.0]], // bottom left
+    [[1.0, 1.0], [1.0, 1.0]], // top right
+    [[0.0, 0.0], [0.0, 0.0]], // bottom left
+    [[1.0, 0.0], [1.0, 0.0]], // bottom right
+    [[1.0, 1.0], [1.0, 1.0]]  // top right
+);
+
+// Ortho projection of the world, widened on one axis so it stays centered
+// with its aspect ratio intact when the window's aspect differs
+pub fn letterbox_projection(width: u32, height: u32) -> nalgebra_glm::Mat4 {
+    let window_aspect = width.max(1) as f32 / height.max(1) as f32;
+    let world_aspect = WORLD_WIDTH / WORLD_HEIGHT;
+
+    let (pad_x, pad_y) = if window_aspect > world_aspect {
+        ((WORLD_HEIGHT * window_aspect - WORLD_WIDTH) / 2.0, 0.0)
+    } else {
+        (0.0, (WORLD_WIDTH / window_aspect - WORLD_HEIGHT) / 2.0)
+    };
+
+    // Zero to one depth as wgpu expects, world z 1 is nearest and -1 farthest
+    nalgebra_glm::ortho_rh_zo(
+        -pad_x,
+        WORLD_WIDTH + pad_x,
+        -pad_y,
+        WORLD_HEIGHT + pad_y,
+        -1.0,
+        1.0,
+    )
+}
+
+// viewport is [left, top, width, height] in window pixels, the cursor is clamped to it
+fn cursor_to_world(
+    cursor: (f64, f64),
+    viewport: [u32; 4],
+    projection: &nalgebra_glm::Mat4,
+) -> nalgebra_glm::Vec2 {
+    let [left, top, width, height] = viewport.map(f64::from);
+    let x = (cursor.0 - left).clamp(0.0, width) / width;
+    let y = (cursor.1 - top).clamp(0.0, height) / height;
+
+    // Window y grows downwards, NDC y grows upwards
+    let ndc = nalgebra_glm::vec4(x as f32 * 2.0 - 1.0, 1.0 - y as f32 * 2.0, 0.0, 1.0);
+    let world = nalgebra_glm::inverse(projection) * ndc;
+    nalgebra_glm::vec2(world.x, world.y)
+}
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Shared by the sprite and instanced pipelines, the rect overlay ignores depth
+fn sprite_depth_state() -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
+
+// Layers first, then back to front by z within a layer. Transparent texels
+// still write depth, so whatever is behind has to be drawn before them.
+pub fn sort_draw_order(sprites: &mut [&Sprite]) {
+    sort_by_layer_and_z(sprites, |sprite| (sprite.layer(), sprite.z()));
+}
+
+// Stable, so submission order is kept for equal layer and z
+fn sort_by_layer_and_z<T>(items: &mut [T], key: impl Fn(&T) -> (i32, f32)) {
+    items.sort_by(|a, b| {
+        let ((layer_a, z_a), (layer_b, z_b)) = (key(a), key(b));
+        layer_a.cmp(&layer_b).then(z_a.total_cmp(&z_b))
+    });
+}
+
+const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.2,
+    g: 0.2,
+    b: 0.2,
+    a: 1.0,
+};
+
+const RECT_BUF_INIT_VERTICES: usize = 64 * 6;
+const INSTANCE_BUF_INIT_INSTANCES: usize = 64;
+const PARTICLE_BUF_INIT_INSTANCES: usize = 256;
+
+// The bars around the world when the window's aspect differs from it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LetterboxOptions {
+    pub bar_color: wgpu::Color,
+    // Fills the bars with a blurred, darkened copy of the frame's edges instead.
+    // Needs an offscreen frame and one more pass, at the window's size when there's
+    // no internal resolution.
+    pub blur: bool,
+}
+
+impl Default for LetterboxOptions {
+    fn default() -> Self {
+        Self {
+            bar_color: wgpu::Color::BLACK,
+            blur: false,
+        }
+    }
+}
+
+// Everything outside the world that projection shows, in world units
+pub fn letterbox_bars(projection: &nalgebra_glm::Mat4) -> Vec<Rect> {
+    let inverse = nalgebra_glm::inverse(projection);
+    let min = inverse * nalgebra_glm::vec4(-1.0, -1.0, 0.0, 1.0);
+    let max = inverse * nalgebra_glm::vec4(1.0, 1.0, 0.0, 1.0);
+    let bar = |x, y, width, height| Rect {
+        pos: nalgebra_glm::vec2(x, y),
+        size: nalgebra_glm::vec2(width, height),
+    };
+
+    let bars = [
+        // Left and right
+        bar(min.x, min.y, -min.x, max.y - min.y),
+        bar(WORLD_WIDTH, min.y, max.x - WORLD_WIDTH, max.y - min.y),
+        // Bottom and top
+        bar(0.0, min.y, WORLD_WIDTH, -min.y),
+        bar(0.0, WORLD_HEIGHT, WORLD_WIDTH, max.y - WORLD_HEIGHT),
+    ];
+    // Rounding leaves slivers a fraction of a world unit wide
+    bars.into_iter()
+        .filter(|bar| bar.size.x > 1e-3 && bar.size.y > 1e-3)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RendererOptions {
+    // None by default, the quads wind counter-clockwise so Face::Back is safe to cull
+    pub cull_mode: Option<wgpu::Face>,
+    // Has to match the render target's, see Surface::sample_count
+    pub sample_count: u32,
+    // Draws every frame at this size and scales it up to the window, so the
+    // layout doesn't depend on the window. None draws at the window's size.
+    pub internal_resolution: Option<PhysicalSize<u32>>,
+    pub letterbox: LetterboxOptions,
+}
+
+impl Default for RendererOptions {
+    fn default() -> Self {
+        Self {
+            cull_mode: None,
+            sample_count: 1,
+            internal_resolution: None,
+            letterbox: LetterboxOptions::default(),
+        }
+    }
+}
+
+// Everything a frame is drawn into. With MSAA the passes draw into msaa, which is
+// resolved into color.
+pub struct RenderTarget<'a> {
+    pub color: &'a wgpu::TextureView,
+    pub depth: &'a wgpu::TextureView,
+    pub msaa: Option<&'a wgpu::TextureView>,
+}
+
+impl<'a> RenderTarget<'a> {
+    fn color_attachment(
+        &self,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        let (view, resolve_target) = match self.msaa {
+            Some(msaa) => (msaa, Some(self.color)),
+            None => (self.color, None),
+        };
+        wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load,
+                // The overlay pass loads the multisampled scene again
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+impl ShaderId {
+    pub const DEFAULT: ShaderId = ShaderId(0);
+}
+
+// One region of a split-screen/picture-in-picture frame with its own camera
+pub struct ViewportPass<'a> {
+    // x, y, width, height in target pixels, must lie inside the target
+    viewport: [u32; 4],
+    projection: nalgebra_glm::Mat4,
+    sprites: Vec<&'a Sprite>,
+}
+
+impl<'a> ViewportPass<'a> {
+    pub fn new(
+        viewport: [u32; 4],
+        projection: nalgebra_glm::Mat4,
+        sprites: Vec<&'a Sprite>,
+    ) -> Self {
+        Self {
+            viewport,
+            projection,
+            sprites,
+        }
+    }
+}
+
+pub struct Renderer {
+    vertex_buf: wgpu::Buffer,
+    pipeline_layout: wgpu::PipelineLayout,
+    sprite_layouts: SpriteLayouts,
+    target_format: wgpu::TextureFormat,
+    options: RendererOptions,
+    // Indexed by ShaderId, the default shader always comes first
+    pipelines: Vec<wgpu::RenderPipeline>,
+    shaders: HashMap<String, ShaderId>,
+    proj_group_layout: wgpu::BindGroupLayout,
+    proj_bind_group: wgpu::BindGroup,
+    proj_mat: nalgebra_glm::Mat4,
+    clear_color: wgpu::Color,
+    proj_buf: wgpu::Buffer,
+    // Grown on demand, one projection per viewport pass
+    viewport_projs: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+    rect_pipeline: wgpu::RenderPipeline,
+    rect_buf: wgpu::Buffer,
+    rects: Vec<RectVertex>,
+    instance_pipeline: wgpu::RenderPipeline,
+    // Only reallocated when the sprite count outgrows it
+    instance_buf: wgpu::Buffer,
+    instance_runs: Vec<Range<u32>>,
+    // Never drawn, only lends its white texture's bind group to the particles
+    particle_sprite: Sprite,
+    particle_buf: wgpu::Buffer,
+    particles: Vec<InstanceRaw>,
+    // Only with RendererOptions::internal_resolution
+    upscaler: Option<Upscaler>,
+    // Queue submits so far, a frame should only add one
+    #[cfg(test)]
+    submits: usize,
+}
+
+impl Renderer {
+    // The game always passes options, tests make do with the defaults
+    #[cfg(test)]
+    pub fn init(
+        context: &Context,
+        surface_config: &wgpu::SurfaceConfiguration,
+        textures: &mut TextureCache,
+    ) -> Self {
+        Self::init_with(
+            context,
+            surface_config,
+            textures,
+            RendererOptions::default(),
+        )
+    }
+
+    pub fn init_with(
+        context: &Context,
+        surface_config: &wgpu::SurfaceConfiguration,
+        textures: &mut TextureCache,
+        options: RendererOptions,
+    ) -> Self {
+        let device = &context.device;
+
+        let vertex_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let proj_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Projection Matrix
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sprite_layouts = SpriteLayouts {
+            uniforms: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    // Model Matrix
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Tint
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // UV Rect
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }),
+            texture: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    // Texture
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    // Sampler
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            }),
+        };
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &proj_group_layout,
+                &sprite_layouts.uniforms,
+                &sprite_layouts.texture,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let target_format = surface_config.view_formats[0];
+        let pipeline = Self::create_sprite_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            target_format,
+            &options,
+        );
+
+        let rect_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&proj_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let rect_shader = device.create_shader_module(include_wgsl!("rect.wgsl"));
+
+        let rect_vertex_buffers = [wgpu::VertexBufferLayout {
+            array_stride: size_of::<RectVertex>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 2 * size_of::<f32>() as u64,
+                    shader_location: 1,
+                },
+            ],
+        }];
+
+        let rect_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&rect_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &rect_shader,
+                entry_point: "vs_main",
+                buffers: &rect_vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &rect_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_config.view_formats[0],
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: options.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        let rect_buf = Self::create_rect_buf(device, RECT_BUF_INIT_VERTICES);
+
+        let instance_shader = device.create_shader_module(include_wgsl!("instanced.wgsl"));
+
+        let instance_vertex_buffers = [Vertex::layout(), InstanceRaw::layout()];
+
+        // Everything per sprite comes from the instance buffer, only the texture is bound
+        let instance_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&proj_group_layout, &sprite_layouts.texture],
+                push_constant_ranges: &[],
+            });
+        let instance_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&instance_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &instance_shader,
+                entry_point: "vs_main",
+                buffers: &instance_vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &instance_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: options.cull_mode,
+                ..Default::default()
+            },
+            depth_stencil: Some(sprite_depth_state()),
+            multisample: wgpu::MultisampleState {
+                count: options.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        let instance_buf = Self::create_instance_buf(device, INSTANCE_BUF_INIT_INSTANCES);
+
+        let solid = textures.solid(context);
+        let particle_sprite = Sprite::new(
+            nalgebra_glm::vec2(0.0, 0.0),
+            nalgebra_glm::vec2(1.0, 1.0),
+            &solid.texture,
+            &sprite_layouts,
+            &solid.sampler,
+            context,
+        );
+        let particle_buf = Self::create_instance_buf(device, PARTICLE_BUF_INIT_INSTANCES);
+
+        let window = PhysicalSize::new(surface_config.width, surface_config.height);
+        let upscaler = Self::create_upscaler(context, target_format, &options, window);
+        let scene_size = upscaler
+            .as_ref()
+            .map_or(window, |upscaler| upscaler.internal_size());
+        let proj_mat = letterbox_projection(scene_size.width, scene_size.height);
+
+        let proj_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice((&proj_mat).into()),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+        });
+
+        let proj_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &proj_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: proj_buf.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            vertex_buf,
+            pipeline_layout,
+            sprite_layouts,
+            target_format,
+            options,
+            pipelines: vec![pipeline],
+            shaders: HashMap::new(),
+            proj_group_layout,
+            proj_bind_group,
+            proj_mat,
+            clear_color: DEFAULT_CLEAR_COLOR,
+            proj_buf,
+            viewport_projs: Vec::new(),
+            rect_pipeline,
+            rect_buf,
+            rects: Vec::new(),
+            instance_pipeline,
+            instance_buf,
+            instance_runs: Vec::new(),
+            particle_sprite,
+            particle_buf,
+            particles: Vec::new(),
+            upscaler,
+            #[cfg(test)]
+            submits: 0,
+        }
+    }
+
+    // Only when there's an internal resolution or the bars are blurred, which needs
+    // the frame offscreen to sample its edges
+    fn create_upscaler(
+        context: &Context,
+        format: wgpu::TextureFormat,
+        options: &RendererOptions,
+        window: PhysicalSize<u32>,
+    ) -> Option<Upscaler> {
+        let internal = match options.internal_resolution {
+            Some(internal) => internal,
+            None if options.letterbox.blur => upscale::world_fit(window),
+            None => return None,
+        };
+        Some(Upscaler::new(
+            context,
+            format,
+            options.sample_count,
+            internal,
+            window,
+            options.letterbox,
+        ))
+    }
+
+    fn create_sprite_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        target_format: wgpu::TextureFormat,
+        options: &RendererOptions,
+    ) -> wgpu::RenderPipeline {
+        let vertex_buffers = [Vertex::layout()];
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: options.cull_mode,
+                ..Default::default()
+            },
+            depth_stencil: Some(sprite_depth_state()),
+            multisample: wgpu::MultisampleState {
+                count: options.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+    }
+
+    // Sprites using a custom shader share the default bind group layouts, so the
+    // source must declare the same bindings and vs_main/fs_main entry points
+    pub fn register_shader(&mut self, context: &Context, source: &str) -> ShaderId {
+        if let Some(id) = self.shaders.get(source) {
+            return *id;
+        }
+
+        let shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+        let pipeline = Self::create_sprite_pipeline(
+            &context.device,
+            &self.pipeline_layout,
+            &shader,
+            self.target_format,
+            &self.options,
+        );
+
+        let id = ShaderId(self.pipelines.len());
+        self.pipelines.push(pipeline);
+        self.shaders.insert(source.to_owned(), id);
+        id
+    }
+
+    // Pipelines are built when a shader is registered, so registering every shader
+    // up front (e.g. behind a loading screen) keeps that cost off the first frame
+    // that uses it
+    pub fn prewarm(&mut self, context: &Context, sources: &[&str]) -> Vec<ShaderId> {
+        sources
+            .iter()
+            .map(|source| self.register_shader(context, source))
+            .collect()
+    }
+
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+
+    // With an internal resolution only the upscale changes, the scene keeps its size
+    pub fn resize(&mut self, context: &Context, width: u32, height: u32) {
+        let window = PhysicalSize::new(width, height);
+        if let (Some(upscaler), Some(_)) =
+            (self.upscaler.as_mut(), self.options.internal_resolution)
+        {
+            upscaler.set_window_size(context, window);
+            return;
+        }
+
+        // Blurred bars without an internal resolution follow the window
+        self.upscaler = Self::create_upscaler(context, self.target_format, &self.options, window);
+        let scene_size = self
+            .upscaler
+            .as_ref()
+            .map_or(window, |upscaler| upscaler.internal_size());
+        self.proj_mat = letterbox_projection(scene_size.width, scene_size.height);
+        self.write_projection(context, &self.proj_mat);
+    }
+
+    fn write_projection(&self, context: &Context, projection: &nalgebra_glm::Mat4) {
+        context
+            .queue
+            .write_buffer(&self.proj_buf, 0, bytemuck::cast_slice(projection.into()));
+    }
+
+    // Cursor positions outside the window are clamped to its edges first
+    pub fn screen_to_world(
+        &self,
+        cursor: (f64, f64),
+        surface_config: &wgpu::SurfaceConfiguration,
+    ) -> nalgebra_glm::Vec2 {
+        let window = PhysicalSize::new(surface_config.width, surface_config.height);
+        // The scene only covers the upscaled frame, the bars around it clamp too
+        let [left, top, width, height] = match &self.upscaler {
+            Some(upscaler) => upscale::scaled_viewport(upscaler.internal_size(), window),
+            None => [0, 0, window.width.max(1), window.height.max(1)],
+        };
+        cursor_to_world(cursor, [left, top, width, height], &self.proj_mat)
+    }
+
+    pub fn sprite_layouts(&self) -> &SpriteLayouts {
+        &self.sprite_layouts
+    }
+
+    fn create_instance_buf(device: &wgpu::Device, instances: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (instances * size_of::<InstanceRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // Uploads one instance per sprite, in the given order, and groups them into
+    // draws by shared texture
+    fn update_instances(&mut self, context: &Context, sprites: &[&Sprite]) {
+        let instances: Vec<InstanceRaw> = sprites
+            .iter()
+            .map(|sprite| InstanceRaw::from_sprite(sprite))
+            .collect();
+
+        let needed = (instances.len() * size_of::<InstanceRaw>()) as u64;
+        if needed > self.instance_buf.size() {
+            let capacity = instances.len().next_power_of_two();
+            self.instance_buf = Self::create_instance_buf(&context.device, capacity);
+        }
+        context
+            .queue
+            .write_buffer(&self.instance_buf, 0, bytemuck::cast_slice(&instances));
+
+        self.instance_runs = instance::texture_runs(sprites);
+    }
+
+    // Queues untextured quads for this frame, drawn after the sprites by render
+    pub fn draw_particles(&mut self, particles: impl IntoIterator<Item = InstanceRaw>) {
+        self.particles.extend(particles);
+    }
+
+    fn upload_particles(&mut self, context: &Context) {
+        let needed = (self.particles.len() * size_of::<InstanceRaw>()) as u64;
+        if needed > self.particle_buf.size() {
+            let capacity = self.particles.len().next_power_of_two();
+            self.particle_buf = Self::create_instance_buf(&context.device, capacity);
+        }
+        context
+            .queue
+            .write_buffer(&self.particle_buf, 0, bytemuck::cast_slice(&self.particles));
+    }
+
+    fn create_rect_buf(device: &wgpu::Device, vertices: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Rect Vertex Buffer"),
+            size: (vertices * size_of::<RectVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // Queues a solid colored rectangle in world coordinates, drawn on top of the sprites
+    pub fn draw_rect(&mut self, rect: Rect, color: wgpu::Color) {
+        if rect.is_empty() {
+            return;
+        }
+        self.rects.extend(rect::quad_vertices(&rect, color));
+    }
+
+    fn upload_rects(&mut self, context: &Context) {
+        let needed = self.rects.len() * size_of::<RectVertex>();
+        if needed as u64 > self.rect_buf.size() {
+            let vertices = self.rects.len().next_power_of_two();
+            self.rect_buf = Self::create_rect_buf(&context.device, vertices);
+        }
+        context
+            .queue
+            .write_buffer(&self.rect_buf, 0, bytemuck::cast_slice(&self.rects));
+    }
+
+    // Every pass of a frame is recorded into one encoder and submitted once. Each
+    // sprite is drawn on its own with its shader's pipeline, see render_instanced
+    // for the batched path.
+    pub fn render(&mut self, target: &RenderTarget, context: &Context, sprites: Vec<&Sprite>) {
+        self.render_scene(target, context, sprites, false);
+    }
+
+    // Same as render, but all sprites are drawn with one instanced draw per run of
+    // shared textures, after regrouping them with instance::batch_by_texture.
+    // Custom sprite shaders are ignored here.
+    pub fn render_instanced(
+        &mut self,
+        target: &RenderTarget,
+        context: &Context,
+        sprites: Vec<&Sprite>,
+    ) {
+        self.render_scene(target, context, sprites, true);
+    }
+
+    fn render_scene(
+        &mut self,
+        target: &RenderTarget,
+        context: &Context,
+        mut sprites: Vec<&Sprite>,
+        instanced: bool,
+    ) {
+        sort_draw_order(&mut sprites);
+        instance::batch_by_texture(&mut sprites);
+        if instanced {
+            self.update_instances(context, &sprites);
+        }
+        self.upload_particles(context);
+        self.submit_frame(target, context, |renderer, encoder, scene| {
+            renderer.record_scene_pass(encoder, scene, &sprites, instanced);
+        });
+        self.particles.clear();
+    }
+
+    // Records the scene passes with record, then the overlay, in one submit. With an
+    // internal resolution both go into the internal target, which is then scaled
+    // into target's color view.
+    fn submit_frame(
+        &mut self,
+        target: &RenderTarget,
+        context: &Context,
+        record: impl FnOnce(&Self, &mut wgpu::CommandEncoder, &RenderTarget),
+    ) {
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // Covers whatever the projection shows outside the world, in the window or
+        // in an internal frame of another aspect
+        for bar in letterbox_bars(&self.proj_mat) {
+            self.draw_rect(bar, self.options.letterbox.bar_color);
+        }
+
+        // Taken out for the frame so the scene target can be borrowed next to self
+        match self.upscaler.take() {
+            Some(upscaler) => {
+                let scene = upscaler.render_target();
+                record(self, &mut encoder, &scene);
+                self.record_overlay_pass(&mut encoder, &scene, context);
+                upscaler.record_blit(&mut encoder, target.color);
+                self.upscaler = Some(upscaler);
+            }
+            None => {
+                record(self, &mut encoder, target);
+                self.record_overlay_pass(&mut encoder, target, context);
+            }
+        }
+
+        context.queue.submit(Some(encoder.finish()));
+        #[cfg(test)]
+        {
+            self.submits += 1;
+        }
+    }
+
+    // Renders into a new texture instead of the window, letterboxed for the given
+    // size. The texture has the surface's format and can be copied from.
+    pub fn render_to_texture(
+        &mut self,
+        context: &Context,
+        size: PhysicalSize<u32>,
+        sprites: Vec<&Sprite>,
+    ) -> wgpu::Texture {
+        let width = size.width.max(1);
+        let height = size.height.max(1);
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.target_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let samples = self.options.sample_count;
+        let depth = create_depth_view(&context.device, width, height, samples);
+        let msaa = create_msaa_view(&context.device, self.target_format, width, height, samples);
+        let target = RenderTarget {
+            color: &view,
+            depth: &depth,
+            msaa: msaa.as_ref(),
+        };
+
+        // The window's projection or upscale is put back afterwards, the queued
+        // writes land in order around the submit
+        match self.upscaler.as_mut() {
+            Some(upscaler) => {
+                let window = upscaler.window_size();
+                upscaler.set_window_size(context, PhysicalSize::new(width, height));
+                self.render(&target, context, sprites);
+                if let Some(upscaler) = self.upscaler.as_mut() {
+                    upscaler.set_window_size(context, window);
+                }
+            }
+            None => {
+                let window_proj =
+                    std::mem::replace(&mut self.proj_mat, letterbox_projection(width, height));
+                self.write_projection(context, &self.proj_mat);
+                self.render(&target, context, sprites);
+                self.proj_mat = window_proj;
+                self.write_projection(context, &self.proj_mat);
+            }
+        }
+
+        texture
+    }
+
+    fn begin_scene_pass<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        target: &RenderTarget<'a>,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(
+                target.color_attachment(wgpu::LoadOp::Clear(self.clear_color)),
+            )],
+            // Nothing reads depth after the scene, so it doesn't need storing
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: target.depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    // sprites come in draw order, see render_scene
+    fn record_scene_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &RenderTarget,
+        sprites: &[&Sprite],
+        instanced: bool,
+    ) {
+        let mut rpass = self.begin_scene_pass(encoder, target);
+
+        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        rpass.set_bind_group(0, &self.proj_bind_group, &[]);
+        if instanced {
+            self.draw_instances(&mut rpass, sprites);
+        } else {
+            self.draw_sprites(&mut rpass, sprites);
+        }
+
+        if !self.particles.is_empty() {
+            rpass.set_pipeline(&self.instance_pipeline);
+            rpass.set_vertex_buffer(1, self.particle_buf.slice(..));
+            rpass.set_bind_group(1, self.particle_sprite.texture_bind_group(), &[]);
+            rpass.draw(0..VERTICES.len() as u32, 0..self.particles.len() as u32);
+        }
+    }
+
+    // The instances uploaded by update_instances for the same sprites
+    fn draw_instances<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, sprites: &[&'a Sprite]) {
+        rpass.set_pipeline(&self.instance_pipeline);
+        rpass.set_vertex_buffer(1, self.instance_buf.slice(..));
+        for run in &self.instance_runs {
+            let first = sprites[run.start as usize];
+            rpass.set_bind_group(1, first.texture_bind_group(), &[]);
+            rpass.draw(0..VERTICES.len() as u32, run.clone());
+        }
+    }
+
+    // Sprite pipelines share one layout, so bound groups survive pipeline switches
+    // and the texture is only rebound when it changes
+    fn draw_sprites<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, sprites: &[&'a Sprite]) {
+        let mut current_shader = None;
+        let mut current_texture = None;
+        for sprite in sprites {
+            if current_shader != Some(sprite.shader()) {
+                rpass.set_pipeline(&self.pipelines[sprite.shader().0]);
+                current_shader = Some(sprite.shader());
+            }
+            rpass.set_bind_group(1, sprite.get_bind_group(), &[]);
+            if current_texture != Some(sprite.texture_key()) {
+                rpass.set_bind_group(2, sprite.texture_bind_group(), &[]);
+                current_texture = Some(sprite.texture_key());
+            }
+            rpass.draw(0..VERTICES.len() as u32, 0..1);
+        }
+    }
+
+    // Clears the target once, then draws each pass into its own viewport and scissor
+    // rect. Viewports are in pixels of the internal resolution when there is one.
+    // Particles and rects aren't drawn.
+    pub fn render_viewports(
+        &mut self,
+        target: &RenderTarget,
+        context: &Context,
+        passes: &[ViewportPass],
+    ) {
+        let device = &context.device;
+
+        while self.viewport_projs.len() < passes.len() {
+            let buf = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: size_of::<nalgebra_glm::Mat4>() as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.proj_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buf.as_entire_binding(),
+                }],
+            });
+            self.viewport_projs.push((buf, bind_group));
+        }
+
+        for (pass, (buf, _)) in passes.iter().zip(&self.viewport_projs) {
+            context
+                .queue
+                .write_buffer(buf, 0, bytemuck::cast_slice((&pass.projection).into()));
+        }
+
+        self.submit_frame(target, context, |renderer, encoder, scene| {
+            let mut rpass = renderer.begin_scene_pass(encoder, scene);
+
+            rpass.set_vertex_buffer(0, renderer.vertex_buf.slice(..));
+            for (pass, (_, bind_group)) in passes.iter().zip(&renderer.viewport_projs) {
+                let [x, y, width, height] = pass.viewport;
+                rpass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+                rpass.set_scissor_rect(x, y, width, height);
+                rpass.set_bind_group(0, bind_group, &[]);
+
+                let mut sprites = pass.sprites.clone();
+                sort_draw_order(&mut sprites);
+                instance::batch_by_texture(&mut sprites);
+                renderer.draw_sprites(&mut rpass, &sprites);
+            }
+        });
+    }
+
+    // Draws the queued rects on top of the scene, skipped when nothing was queued
+    fn record_overlay_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &RenderTarget,
+        context: &Context,
+    ) {
+        if self.rects.is_empty() {
+            return;
+        }
+        self.upload_rects(context);
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(target.color_attachment(wgpu::LoadOp::Load))],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            rpass.set_pipeline(&self.rect_pipeline);
+            rpass.set_vertex_buffer(0, self.rect_buf.slice(..));
+            rpass.set_bind_group(0, &self.proj_bind_group, &[]);
+            rpass.draw(0..self.rects.len() as u32, 0..1);
+        }
+
+        self.rects.clear();
+    }
+}
+
+// None without multisampling, the frame is then drawn straight into the target
+pub fn create_msaa_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+pub fn create_depth_view(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::texture;
+
+    #[test]
+    fn letterbox_bars_cover_the_padding_only() {
+        // Twice as wide as the world's aspect, half the window is bars
+        let projection = letterbox_projection(2 * 458, 380);
+        let bars = letterbox_bars(&projection);
+        assert_eq!(bars.len(), 2);
+        let pad = WORLD_WIDTH / 2.0;
+        assert!((bars[0].pos.x + pad).abs() < 1e-2 && (bars[0].size.x - pad).abs() < 1e-2);
+        assert!((bars[1].pos.x - WORLD_WIDTH).abs() < 1e-2);
+        assert!((bars[1].size.y - WORLD_HEIGHT).abs() < 1e-2);
+
+        assert!(letterbox_bars(&letterbox_projection(458, 380)).is_empty());
+    }
+
+    #[test]
+    fn internal_target_keeps_its_resolution_at_any_window_size() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let internal = PhysicalSize::new(458, 380);
+        let mut renderer = Renderer::init_with(
+            &context,
+            &crate::test_surface_config(800, 600),
+            &mut TextureCache::new(),
+            RendererOptions {
+                internal_resolution: Some(internal),
+                ..Default::default()
+            },
+        );
+        for (width, height) in [(800, 600), (1920, 1080), (300, 900)] {
+            renderer.resize(&context, width, height);
+            let upscaler = renderer.upscaler.as_ref().unwrap();
+            assert_eq!(upscaler.internal_size(), internal);
+            assert_eq!(upscaler.window_size(), PhysicalSize::new(width, height));
+
+            let frame =
+                renderer.render_to_texture(&context, PhysicalSize::new(width, height), vec![]);
+            assert_eq!(frame.size().width, width);
+            assert_eq!(frame.size().height, height);
+        }
+    }
+
+    #[test]
+    fn letterboxing_keeps_the_world_centered() {
+        let center = nalgebra_glm::vec4(WORLD_WIDTH / 2.0, WORLD_HEIGHT / 2.0, 0.0, 1.0);
+        for (width, height) in [(1600, 380), (458, 1200)] {
+            let projection = letterbox_projection(width, height);
+            let ndc = projection * center;
+            assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5);
+
+            // The world's corners land symmetrically inside the window
+            let bottom_left = projection * nalgebra_glm::vec4(0.0, 0.0, 0.0, 1.0);
+            let top_right = projection * nalgebra_glm::vec4(WORLD_WIDTH, WORLD_HEIGHT, 0.0, 1.0);
+            assert!((bottom_left.x + top_right.x).abs() < 1e-5);
+            assert!((bottom_left.y + top_right.y).abs() < 1e-5);
+            assert!(top_right.x <= 1.0 && top_right.y <= 1.0);
+            assert!(top_right.x == 1.0 || top_right.y == 1.0);
+        }
+    }
+
+    #[test]
+    fn the_window_center_is_the_world_center() {
+        let (width, height) = (1600, 380);
+        let projection = letterbox_projection(width, height);
+        let world = cursor_to_world((800.0, 190.0), [0, 0, width, height], &projection);
+        assert!((world.x - 114.5).abs() < 1e-3 && (world.y - 95.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cursors_outside_the_window_are_clamped() {
+        let projection = letterbox_projection(458, 380);
+        let viewport = [0, 0, 458, 380];
+        let top_left = cursor_to_world((-50.0, -50.0), viewport, &projection);
+        assert!(top_left.x.abs() < 1e-3 && (top_left.y - WORLD_HEIGHT).abs() < 1e-3);
+        let bottom_right = cursor_to_world((1000.0, 1000.0), viewport, &projection);
+        assert!((bottom_right.x - WORLD_WIDTH).abs() < 1e-3 && bottom_right.y.abs() < 1e-3);
+    }
+
+    // The world's aspect, so frames have no letterbox bars
+    const FRAME: PhysicalSize<u32> = PhysicalSize::new(458, 380);
+    const RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+    fn test_renderer(context: &Context, textures: &mut TextureCache) -> Renderer {
+        let mut renderer = Renderer::init(
+            context,
+            &crate::test_surface_config(FRAME.width, FRAME.height),
+            textures,
+        );
+        renderer.set_clear_color(wgpu::Color::BLACK);
+        renderer
+    }
+
+    // A solid colored quad in world units
+    fn solid_sprite(
+        context: &Context,
+        renderer: &Renderer,
+        textures: &mut TextureCache,
+        rect: [f32; 4],
+        color: [f32; 4],
+    ) -> Sprite {
+        let solid = textures.solid(context);
+        let mut sprite = Sprite::new(
+            nalgebra_glm::vec2(rect[0], rect[1]),
+            nalgebra_glm::vec2(rect[2], rect[3]),
+            &solid.texture,
+            renderer.sprite_layouts(),
+            &solid.sampler,
+            context,
+        );
+        sprite.set_tint(color, context);
+        sprite
+    }
+
+    // RGBA at x, y with y counted from the top row
+    fn pixel(context: &Context, frame: &wgpu::Texture, x: u32, y: u32) -> [u8; 4] {
+        let pixels = texture::read_texture_to_rgba(context, frame).unwrap();
+        let i = ((y * frame.width() + x) * 4) as usize;
+        pixels[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn a_white_sprite_covers_the_center_pixel() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        let sprite = solid_sprite(
+            &context,
+            &renderer,
+            &mut textures,
+            [100.0, 85.0, 29.0, 20.0],
+            [1.0; 4],
+        );
+
+        let frame = renderer.render_to_texture(&context, FRAME, vec![&sprite]);
+        assert_eq!(pixel(&context, &frame, 229, 190), [255, 255, 255, 255]);
+        assert_eq!(pixel(&context, &frame, 10, 10), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn the_sprite_with_the_larger_z_is_in_front() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        let overlap = [100.0, 85.0, 29.0, 20.0];
+        let mut red = solid_sprite(&context, &renderer, &mut textures, overlap, RED);
+        let mut green = solid_sprite(&context, &renderer, &mut textures, overlap, GREEN);
+
+        for (red_z, front) in [(0.5, [255, 0, 0, 255]), (-0.5, [0, 255, 0, 255])] {
+            red.set_z(red_z, &context);
+            green.set_z(-red_z, &context);
+            // Submission order is the same both times, only z decides
+            let frame = renderer.render_to_texture(&context, FRAME, vec![&red, &green]);
+            assert_eq!(pixel(&context, &frame, 229, 190), front);
+        }
+    }
+
+    // A frame-sized color texture with the test surface's format, plus its depth
+    fn offscreen(context: &Context) -> (wgpu::Texture, wgpu::TextureView, wgpu::TextureView) {
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Test Frame"),
+            size: wgpu::Extent3d {
+                width: FRAME.width,
+                height: FRAME.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: crate::test_surface_config(1, 1).format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = create_depth_view(&context.device, FRAME.width, FRAME.height, 1);
+        (texture, view, depth)
+    }
+
+    #[test]
+    fn viewport_passes_draw_into_their_own_halves() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        let world = [0.0, 0.0, WORLD_WIDTH, WORLD_HEIGHT];
+        let red = solid_sprite(&context, &renderer, &mut textures, world, RED);
+        let green = solid_sprite(&context, &renderer, &mut textures, world, GREEN);
+
+        let half = FRAME.width / 2;
+        let projection = letterbox_projection(half, FRAME.height);
+        let passes = [
+            ViewportPass {
+                viewport: [0, 0, half, FRAME.height],
+                projection,
+                sprites: vec![&red],
+            },
+            ViewportPass {
+                viewport: [half, 0, half, FRAME.height],
+                projection,
+                sprites: vec![&green],
+            },
+        ];
+        let (frame, view, depth) = offscreen(&context);
+        let target = RenderTarget {
+            color: &view,
+            depth: &depth,
+            msaa: None,
+        };
+        renderer.render_viewports(&target, &context, &passes);
+
+        assert_eq!(pixel(&context, &frame, half / 2, 190), [255, 0, 0, 255]);
+        assert_eq!(
+            pixel(&context, &frame, half + half / 2, 190),
+            [0, 255, 0, 255]
+        );
+        // The world is letterboxed into each half, the bars stay clear
+        assert_eq!(pixel(&context, &frame, half / 2, 2), [0, 0, 0, 255]);
+    }
+
+    // The default shader with its fragment output replaced by a flat color
+    fn flat_shader(color: &str) -> String {
+        let source = include_str!("shader.wgsl");
+        let sample = "return textureSample(texture, samp, vert.tex_c) * tint;";
+        assert!(source.contains(sample));
+        source.replace(sample, &format!("return {color} * tint;"))
+    }
+
+    #[test]
+    fn sprites_draw_with_their_registered_shader() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        let red_source = flat_shader("vec4f(1.0, 0.0, 0.0, 1.0)");
+        let green_source = flat_shader("vec4f(0.0, 1.0, 0.0, 1.0)");
+        let red_shader = renderer.register_shader(&context, &red_source);
+        let green_shader = renderer.register_shader(&context, &green_source);
+        assert_ne!(red_shader, green_shader);
+        assert_eq!(renderer.pipelines.len(), 3);
+        // Registering the same source again reuses its pipeline
+        assert_eq!(renderer.register_shader(&context, &red_source), red_shader);
+        assert_eq!(renderer.pipelines.len(), 3);
+
+        let white = [1.0; 4];
+        let mut left = solid_sprite(
+            &context,
+            &renderer,
+            &mut textures,
+            [0.0, 0.0, 100.0, 190.0],
+            white,
+        );
+        let mut right = solid_sprite(
+            &context,
+            &renderer,
+            &mut textures,
+            [129.0, 0.0, 100.0, 190.0],
+            white,
+        );
+        left.set_shader(red_shader);
+        right.set_shader(green_shader);
+
+        let frame = renderer.render_to_texture(&context, FRAME, vec![&left, &right]);
+        assert_eq!(pixel(&context, &frame, 100, 190), [255, 0, 0, 255]);
+        assert_eq!(pixel(&context, &frame, 358, 190), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn the_cull_mode_reaches_the_sprite_pipeline() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        // The quad winds counter-clockwise, so only culling front faces hides it
+        for (cull_mode, center) in [
+            (None, [255, 255, 255, 255]),
+            (Some(wgpu::Face::Back), [255, 255, 255, 255]),
+            (Some(wgpu::Face::Front), [0, 0, 0, 255]),
+        ] {
+            let mut textures = TextureCache::new();
+            let mut renderer = Renderer::init_with(
+                &context,
+                &crate::test_surface_config(FRAME.width, FRAME.height),
+                &mut textures,
+                RendererOptions {
+                    cull_mode,
+                    ..Default::default()
+                },
+            );
+            renderer.set_clear_color(wgpu::Color::BLACK);
+            let sprite = solid_sprite(
+                &context,
+                &renderer,
+                &mut textures,
+                [100.0, 85.0, 29.0, 20.0],
+                [1.0; 4],
+            );
+
+            let frame = renderer.render_to_texture(&context, FRAME, vec![&sprite]);
+            assert_eq!(pixel(&context, &frame, 229, 190), center, "{cull_mode:?}");
+        }
+    }
+
+    #[test]
+    fn the_sampler_wrap_mode_tiles_or_clamps() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        // Red on the left, green on the right
+        let texels = [255, 0, 0, 255, 0, 255, 0, 255];
+        let stripes = texture::create_rgba_texture(&context, 2, 1, &texels);
+
+        // Twice across, so 60% of the way across the frame samples u = 1.2
+        for (mode, sampled) in [
+            (wgpu::AddressMode::Repeat, [255, 0, 0, 255]),
+            (wgpu::AddressMode::ClampToEdge, [0, 255, 0, 255]),
+        ] {
+            let sampler = texture::create_sampler(&context, mode, mode);
+            let sprite = Sprite::new_from_atlas(
+                nalgebra_glm::vec2(0.0, 0.0),
+                nalgebra_glm::vec2(WORLD_WIDTH, WORLD_HEIGHT),
+                [0.0, 0.0, 2.0, 1.0],
+                &stripes,
+                renderer.sprite_layouts(),
+                &sampler,
+                &context,
+            );
+            let frame = renderer.render_to_texture(&context, FRAME, vec![&sprite]);
+            let x = FRAME.width * 6 / 10;
+            assert_eq!(pixel(&context, &frame, x, 190), sampled, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn prewarm_registers_every_pipeline_up_front() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        let sources = [
+            flat_shader("vec4f(1.0, 0.0, 0.0, 1.0)"),
+            flat_shader("vec4f(0.0, 0.0, 1.0, 1.0)"),
+        ];
+        let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+
+        let ids = renderer.prewarm(&context, &sources);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(renderer.pipelines.len(), 3);
+        for (source, id) in sources.iter().zip(&ids) {
+            assert_eq!(renderer.shaders.get(*source), Some(id));
+        }
+    }
+
+    #[test]
+    fn scene_and_overlay_share_one_submit() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        for internal_resolution in [None, Some(FRAME)] {
+            let mut textures = TextureCache::new();
+            let mut renderer = Renderer::init_with(
+                &context,
+                &crate::test_surface_config(FRAME.width, FRAME.height),
+                &mut textures,
+                RendererOptions {
+                    internal_resolution,
+                    ..Default::default()
+                },
+            );
+            let sprite = solid_sprite(
+                &context,
+                &renderer,
+                &mut textures,
+                [100.0, 85.0, 29.0, 20.0],
+                RED,
+            );
+            renderer.draw_rect(
+                Rect {
+                    pos: nalgebra_glm::vec2(10.0, 10.0),
+                    size: nalgebra_glm::vec2(20.0, 20.0),
+                },
+                wgpu::Color::WHITE,
+            );
+
+            renderer.render_to_texture(&context, FRAME, vec![&sprite]);
+            assert_eq!(renderer.submits, 1, "{internal_resolution:?}");
+            // The overlay was drawn and cleared with the frame
+            assert!(renderer.rects.is_empty());
+        }
+    }
+
+    #[test]
+    fn per_sprite_and_instanced_paths_draw_the_same_frame() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let mut renderer = test_renderer(&context, &mut textures);
+        let mut red = solid_sprite(
+            &context,
+            &renderer,
+            &mut textures,
+            [90.0, 85.0, 29.0, 20.0],
+            RED,
+        );
+        let green = solid_sprite(
+            &context,
+            &renderer,
+            &mut textures,
+            [110.0, 85.0, 29.0, 20.0],
+            GREEN,
+        );
+        red.set_z(0.5, &context);
+
+        let mut frames = Vec::new();
+        for instanced in [false, true] {
+            let (frame, view, depth) = offscreen(&context);
+            let target = RenderTarget {
+                color: &view,
+                depth: &depth,
+                msaa: None,
+            };
+            if instanced {
+                renderer.render_instanced(&target, &context, vec![&green, &red]);
+            } else {
+                renderer.render(&target, &context, vec![&green, &red]);
+            }
+            // Red is in front where they overlap, at x 110..119 in world units
+            assert_eq!(pixel(&context, &frame, 230, 190), [255, 0, 0, 255]);
+            assert_eq!(pixel(&context, &frame, 260, 190), [0, 255, 0, 255]);
+            frames.push(texture::read_texture_to_rgba(&context, &frame).unwrap());
+        }
+        assert_eq!(frames[0], frames[1]);
+    }
+
+    #[test]
+    fn draw_order_sorts_by_layer_and_keeps_submission_order_within_one() {
+        // (layer, z, submission index)
+        let mut items = vec![
+            (2, 0.0, 0),
+            (0, 0.0, 1),
+            (1, 0.0, 2),
+            (0, 0.0, 3),
+            (-1, 0.0, 4),
+            (1, 0.0, 5),
+            (0, -0.5, 6),
+        ];
+        sort_by_layer_and_z(&mut items, |&(layer, z, _)| (layer, z));
+        let order: Vec<usize> = items.iter().map(|&(_, _, i)| i).collect();
+        // Within layer 0 the farther z goes first, equal z keeps 1 before 3
+        assert_eq!(order, [4, 6, 1, 3, 2, 5, 0]);
+    }
+}
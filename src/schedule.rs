@@ -0,0 +1,100 @@
+// "0:5x11,30:3x8" is a 5 by 11 formation at the start and a 3 by 8 one 30
+// seconds in, see WaveSchedule::parse
+const SCHEDULE_ENV: &str = "VADERS_SCHEDULE";
+
+// A formation of rows by cols spawned at seconds of simulation time into a run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduledWave {
+    pub at: f64,
+    pub rows: u32,
+    pub cols: u32,
+}
+
+// Scripted waves instead of a new one whenever the last is cleared, so a run's
+// progression doesn't depend on play
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WaveSchedule {
+    // By time, waves before next have spawned
+    waves: Vec<ScheduledWave>,
+    next: usize,
+}
+
+impl WaveSchedule {
+    pub fn new(mut waves: Vec<ScheduledWave>) -> Self {
+        waves.sort_by(|a, b| a.at.total_cmp(&b.at));
+        Self { waves, next: 0 }
+    }
+
+    // Unparseable values are reported and ignored
+    pub fn from_env() -> Option<Self> {
+        crate::enemy::env_option(SCHEDULE_ENV, Self::parse)
+    }
+
+    // Comma separated "seconds:rowsxcols", None if any entry is malformed
+    pub fn parse(value: &str) -> Option<Self> {
+        let waves = value
+            .split(',')
+            .map(|entry| {
+                let (at, grid) = entry.trim().split_once(':')?;
+                let (rows, cols) = grid.split_once('x')?;
+                Some(ScheduledWave {
+                    at: at.parse().ok()?,
+                    rows: rows.parse().ok()?,
+                    cols: cols.parse().ok()?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self::new(waves))
+    }
+
+    // The next wave once time has reached it, one per call so two waves due at
+    // once spawn on consecutive steps
+    pub fn due(&mut self, time: f64) -> Option<ScheduledWave> {
+        let wave = *self.waves.get(self.next)?;
+        if time + crate::clock::TIMER_EPSILON < wave.at {
+            return None;
+        }
+        self.next += 1;
+        Some(wave)
+    }
+
+    // Every wave has spawned
+    pub fn is_done(&self) -> bool {
+        self.next == self.waves.len()
+    }
+
+    // From the first wave again, for a new run
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_waves_spawn_at_their_scheduled_ticks() {
+        let dt = crate::clock::FIXED_STEP.as_secs_f32();
+        let mut schedule = WaveSchedule::parse("0.5:3x8, 0:5x11").unwrap();
+        let mut time = 0.0;
+        let mut spawned = Vec::new();
+        for tick in 0..60 {
+            if let Some(wave) = schedule.due(time) {
+                spawned.push((tick, wave.rows, wave.cols));
+            }
+            time += f64::from(dt);
+        }
+        assert_eq!(spawned, [(0, 5, 11), (30, 3, 8)]);
+        assert!(schedule.is_done());
+
+        schedule.reset();
+        assert_eq!(schedule.due(0.0).map(|wave| wave.rows), Some(5));
+    }
+
+    #[test]
+    fn malformed_schedules_are_rejected() {
+        assert_eq!(WaveSchedule::parse("0:5x11,soon:3x8"), None);
+        assert_eq!(WaveSchedule::parse("0:5"), None);
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::event::{Events, GameEvent};
+
+const HIGH_SCORE_FILE: &str = "highscore.dat";
+pub const POINTS_PER_INVADER: u32 = 10;
+
+// The current run's score and the best one seen, including previous runs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    current: u32,
+    high: u32,
+    // Practice runs are scored but never set a high score
+    practice: bool,
+}
+
+impl Score {
+    // <config dir>/vaders/highscore.dat, None if the platform has no config dir
+    pub fn default_path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("vaders").join(HIGH_SCORE_FILE))
+    }
+
+    // A missing file is a fresh install, anything unreadable is reported and
+    // treated the same so a bad file can't stop the game from starting
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let high = match fs::read_to_string(path) {
+            Ok(contents) => contents.trim().parse().unwrap_or_else(|_| {
+                eprintln!("Ignoring unreadable high score in {}", path.display());
+                0
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => {
+                eprintln!("Could not read high score {}: {err}", path.display());
+                0
+            }
+        };
+        Self {
+            current: 0,
+            high,
+            practice: false,
+        }
+    }
+
+    // Written to a temporary file first and renamed over the old one, so a crash
+    // mid-write leaves the previous high score intact
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        writeln!(file, "{}", self.high)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+
+    pub fn add(&mut self, points: u32) {
+        self.current = self.current.saturating_add(points);
+        if !self.practice {
+            self.high = self.high.max(self.current);
+        }
+    }
+
+    pub fn set_practice(&mut self, practice: bool) {
+        self.practice = practice;
+    }
+
+    // Kills in the same update chain, each one is worth as many times the base
+    // points as there were kills, so a double kill scores 2 * 2 * 10
+    pub fn apply(&mut self, events: &Events) {
+        let kills = events.kills() as u32;
+        self.add(kills * kills * POINTS_PER_INVADER);
+        for event in events.iter() {
+            if let GameEvent::BonusAwarded { points } = *event {
+                self.add(points);
+            }
+        }
+    }
+
+    // Starts a new run, the high score is kept
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    pub fn high(&self) -> u32 {
+        self.high
+    }
+}
+
+// Score thresholds that each grant a life, once per run
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtraLives {
+    // Ascending, thresholds before next have been reached
+    thresholds: Vec<u32>,
+    next: usize,
+}
+
+impl ExtraLives {
+    pub fn new(mut thresholds: Vec<u32>) -> Self {
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        Self {
+            thresholds,
+            next: 0,
+        }
+    }
+
+    // How many thresholds score has crossed since the last call, which are then
+    // used up
+    pub fn reached(&mut self, score: u32) -> usize {
+        let start = self.next;
+        while self
+            .thresholds
+            .get(self.next)
+            .is_some_and(|&threshold| score >= threshold)
+        {
+            self.next += 1;
+        }
+        self.next - start
+    }
+
+    // Every threshold counts again, for a new run
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adding_points_raises_the_high_score_only_past_it() {
+        let mut score = Score {
+            current: 0,
+            high: 50,
+            practice: false,
+        };
+        score.add(30);
+        assert_eq!((score.current(), score.high()), (30, 50));
+
+        score.add(30);
+        assert_eq!((score.current(), score.high()), (60, 60));
+
+        score.reset();
+        score.add(10);
+        assert_eq!((score.current(), score.high()), (10, 60));
+    }
+
+    #[test]
+    fn practice_scores_never_set_a_high_score() {
+        let mut score = Score::default();
+        score.set_practice(true);
+        score.add(100);
+        assert_eq!((score.current(), score.high()), (100, 0));
+    }
+
+    #[test]
+    fn each_extra_life_threshold_grants_once() {
+        let mut extra = ExtraLives::new(vec![5000, 1500]);
+        assert_eq!(extra.reached(1400), 0);
+        assert_eq!(extra.reached(1500), 1);
+        assert_eq!(extra.reached(1600), 0);
+        assert_eq!(extra.reached(4000), 0);
+
+        // A big bonus can cross more than one
+        let mut extra = ExtraLives::new(vec![100, 200]);
+        assert_eq!(extra.reached(250), 2);
+        assert_eq!(extra.reached(300), 0);
+        extra.reset();
+        assert_eq!(extra.reached(150), 1);
+    }
+
+    #[test]
+    fn high_score_survives_a_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("vaders-score-{}", std::process::id()));
+        let path = dir.join(HIGH_SCORE_FILE);
+        let mut score = Score::default();
+        score.add(120);
+        score.save(&path).unwrap();
+
+        let loaded = Score::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((loaded.current(), loaded.high()), (0, 120));
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn a_missing_file_loads_as_zero() {
+        let path = std::env::temp_dir()
+            .join("vaders-score-missing")
+            .join(HIGH_SCORE_FILE);
+        assert_eq!(Score::load(path), Score::default());
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = "settings.txt";
+
+// Player choices kept across runs, stored next to the high score as key=value
+// lines. Volumes are 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    master: f32,
+    sfx: f32,
+    music: f32,
+    // Silences everything without losing master, so unmuting restores it
+    muted: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            sfx: 1.0,
+            music: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl Settings {
+    // <config dir>/vaders/settings.txt, None if the platform has no config dir
+    pub fn default_path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("vaders").join(SETTINGS_FILE))
+    }
+
+    // Like Score::load, a missing or unreadable file gives the defaults. Lines
+    // that don't parse are reported and skipped, the rest still apply.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let mut settings = Self::default();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return settings,
+            Err(err) => {
+                eprintln!("Could not read settings {}: {err}", path.display());
+                return settings;
+            }
+        };
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            if !settings.apply_line(line) {
+                eprintln!("Ignoring {line:?} in {}", path.display());
+            }
+        }
+        settings
+    }
+
+    fn apply_line(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.split_once('=') else {
+            return false;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "muted" => match value.parse() {
+                Ok(muted) => self.muted = muted,
+                Err(_) => return false,
+            },
+            key => {
+                let Ok(volume) = value.parse() else {
+                    return false;
+                };
+                match key {
+                    "master" => self.master = clamp_volume(volume),
+                    "sfx" => self.set_sfx_volume(volume),
+                    "music" => self.set_music_volume(volume),
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+
+    // Written like Score::save, through a temporary file renamed over the old one
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        writeln!(file, "master={}", self.master)?;
+        writeln!(file, "sfx={}", self.sfx)?;
+        writeln!(file, "music={}", self.music)?;
+        writeln!(file, "muted={}", self.muted)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+
+    // Changing the volume unmutes, so the change can be heard
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master = clamp_volume(volume);
+        self.muted = false;
+    }
+
+    pub fn set_sfx_volume(&mut self, volume: f32) {
+        self.sfx = clamp_volume(volume);
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music = clamp_volume(volume);
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+
+    // What the player picked, muted or not
+    pub fn master_volume(&self) -> f32 {
+        self.master
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    // What an effect plays at, master times its channel
+    #[cfg(feature = "audio")]
+    pub fn sfx_gain(&self) -> f32 {
+        self.master_gain() * self.sfx
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn music_gain(&self) -> f32 {
+        self.master_gain() * self.music
+    }
+
+    #[cfg(feature = "audio")]
+    fn master_gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master
+        }
+    }
+}
+
+// NaN, from a hand edited file, counts as silent
+fn clamp_volume(volume: f32) -> f32 {
+    if volume.is_nan() {
+        return 0.0;
+    }
+    volume.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volumes_are_clamped_to_0_through_1() {
+        let mut settings = Settings::default();
+        settings.set_master_volume(1.5);
+        assert_eq!(settings.master_volume(), 1.0);
+        settings.set_sfx_volume(-0.5);
+        assert_eq!(settings.sfx, 0.0);
+        settings.set_music_volume(f32::NAN);
+        assert_eq!(settings.music, 0.0);
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn channels_play_at_master_times_their_volume() {
+        let mut settings = Settings::default();
+        settings.set_master_volume(0.5);
+        settings.set_sfx_volume(0.5);
+        settings.set_music_volume(0.8);
+        assert_eq!(settings.sfx_gain(), 0.25);
+        assert_eq!(settings.music_gain(), 0.4);
+
+        settings.toggle_mute();
+        assert_eq!((settings.sfx_gain(), settings.music_gain()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn unmuting_restores_the_previous_volume() {
+        let mut settings = Settings::default();
+        settings.set_master_volume(0.7);
+        settings.toggle_mute();
+        assert!(settings.is_muted());
+        settings.toggle_mute();
+        assert!(!settings.is_muted());
+        assert_eq!(settings.master_volume(), 0.7);
+
+        // Turning the volume while muted unmutes
+        settings.toggle_mute();
+        settings.set_master_volume(0.3);
+        assert!(!settings.is_muted());
+    }
+
+    #[test]
+    fn settings_survive_a_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("vaders-settings-{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE);
+        let mut settings = Settings::default();
+        settings.set_master_volume(0.6);
+        settings.set_music_volume(0.25);
+        settings.toggle_mute();
+        settings.save(&path).unwrap();
+
+        let loaded = Settings::load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded, settings);
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn unknown_lines_are_skipped() {
+        let mut settings = Settings::default();
+        assert!(!settings.apply_line("volume=0.5"));
+        assert!(!settings.apply_line("sfx=loud"));
+        assert!(settings.apply_line(" sfx = 0.5 "));
+        assert_eq!(settings.sfx, 0.5);
+    }
+}
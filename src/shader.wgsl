@@ -9,24 +9,30 @@ var<uniform> model: mat4x4<f32>;
 @group(0)
 @binding(0)
 var<uniform> projection: mat4x4<f32>;
+// xy offset, zw scale
+@group(1)
+@binding(2)
+var<uniform> uv_rect: vec4f;
 
 @vertex
 fn vs_main(@location(0) pos: vec2f, @location(1) tex_coords: vec2f) -> VertOut {
     var out: VertOut;
     out.pos = projection * model * vec4f(pos, 0.0, 1.0);
-    out.tex_c = tex_coords;
+    out.tex_c = uv_rect.xy + tex_coords * uv_rect.zw;
     return out;
 }
 
-@group(1)
-@binding(1)
+@group(2)
+@binding(0)
 var texture: texture_2d<f32>;
-@group(1)
-@binding(2)
+@group(2)
+@binding(1)
 var samp: sampler;
+@group(1)
+@binding(1)
+var<uniform> tint: vec4f;
 
 @fragment
 fn fs_main(vert: VertOut) -> @location(0) vec4f {
-    return textureSample(texture, samp, vert.tex_c);
+    return textureSample(texture, samp, vert.tex_c) * tint;
 }
-
@@ -0,0 +1,49 @@
+// shader.wgsl's bindings, drawing a shimmering ring inside the sprite's quad
+struct VertOut {
+    @builtin(position) pos: vec4f,
+    @location(0) tex_c: vec2f,
+    // 0 to 1 across the quad, whatever the uv rect
+    @location(1) local: vec2f,
+}
+
+@group(1)
+@binding(0)
+var<uniform> model: mat4x4<f32>;
+@group(0)
+@binding(0)
+var<uniform> projection: mat4x4<f32>;
+// xy offset, zw scale
+@group(1)
+@binding(2)
+var<uniform> uv_rect: vec4f;
+
+@vertex
+fn vs_main(@location(0) pos: vec2f, @location(1) tex_coords: vec2f) -> VertOut {
+    var out: VertOut;
+    out.pos = projection * model * vec4f(pos, 0.0, 1.0);
+    out.tex_c = uv_rect.xy + tex_coords * uv_rect.zw;
+    out.local = tex_coords;
+    return out;
+}
+
+@group(2)
+@binding(0)
+var texture: texture_2d<f32>;
+@group(2)
+@binding(1)
+var samp: sampler;
+@group(1)
+@binding(1)
+var<uniform> tint: vec4f;
+
+@fragment
+fn fs_main(vert: VertOut) -> @location(0) vec4f {
+    let centered = vert.local * 2.0 - 1.0;
+    let d = length(centered);
+    // A band just inside the quad's edge, faded at both sides
+    let ring = smoothstep(0.6, 0.85, d) * (1.0 - smoothstep(0.9, 1.0, d));
+    // Brighter and darker arcs around it, they sweep as the sprite turns
+    let shimmer = 0.6 + 0.4 * sin(atan2(centered.y, centered.x) * 6.0);
+    let color = textureSample(texture, samp, vert.tex_c) * tint;
+    return vec4f(color.rgb, color.a * ring * shimmer);
+}
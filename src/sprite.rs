@@ -1,27 +1,105 @@
 use wgpu::util::DeviceExt;
 
+use crate::atlas::AtlasEntry;
+use crate::rect::Rect;
+use crate::renderer::ShaderId;
+
+// The bind groups every sprite draws with at groups 1 and 2, see shader.wgsl
+pub struct SpriteLayouts {
+    // Model matrix, tint and UV rect, bound for every sprite
+    pub uniforms: wgpu::BindGroupLayout,
+    // Texture and sampler, only rebound when the texture_key changes
+    pub texture: wgpu::BindGroupLayout,
+}
+
 pub struct Sprite {
+    pos: nalgebra_glm::Vec2,
+    size: nalgebra_glm::Vec2,
+    // Radians, counter-clockwise about the sprite's center
+    rotation: f32,
+    // Depth in -1..1, sprites with a smaller z are drawn behind
+    z: f32,
     model_mat: nalgebra_glm::Mat4,
     model_buf: wgpu::Buffer,
-    texture_view: wgpu::TextureView,
+    tint: Tint,
+    tint_buf: wgpu::Buffer,
+    uv_rect: [f32; 4],
+    uv_buf: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
+    texture_bind_group: wgpu::BindGroup,
+    shader: ShaderId,
+    layer: i32,
+    // Sprites with equal keys can share one texture binding when instanced
+    texture_key: (wgpu::Id<wgpu::Texture>, wgpu::Id<wgpu::Sampler>),
 }
 
+// A base color plus an optional transient effect multiplied over it, so a flash
+// or fade can come and go without losing the base color
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Tint {
+    base: [f32; 4],
+    effect: Option<[f32; 4]>,
+}
+
+impl Tint {
+    fn rendered(&self) -> [f32; 4] {
+        match self.effect {
+            Some(effect) => std::array::from_fn(|i| self.base[i] * effect[i]),
+            None => self.base,
+        }
+    }
+}
+
+// The whole texture
+const FULL_UV_RECT: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
 impl Sprite {
     pub fn new(
         pos: nalgebra_glm::Vec2,
         size: nalgebra_glm::Vec2,
         texture: &wgpu::Texture,
-        bind_layout: &wgpu::BindGroupLayout,
+        layouts: &SpriteLayouts,
+        sampler: &wgpu::Sampler,
+        context: &crate::Context,
+    ) -> Self {
+        Self::new_from_atlas(pos, size, FULL_UV_RECT, texture, layouts, sampler, context)
+    }
+
+    // The entry's part of texture at its pixel size, unless size overrides it
+    pub fn from_atlas_entry(
+        pos: nalgebra_glm::Vec2,
+        entry: AtlasEntry,
+        size: Option<nalgebra_glm::Vec2>,
+        texture: &wgpu::Texture,
+        layouts: &SpriteLayouts,
         sampler: &wgpu::Sampler,
         context: &crate::Context,
     ) -> Self {
-        let pos = nalgebra_glm::vec2_to_vec3(&pos);
-        let mut size = nalgebra_glm::vec2_to_vec3(&size);
-        size[2] = 1.0;
-        let mut model_mat = nalgebra_glm::identity();
-        model_mat = nalgebra_glm::translate(&model_mat, &pos);
-        model_mat = nalgebra_glm::scale(&model_mat, &size);
+        Self::new_from_atlas(
+            pos,
+            size.unwrap_or_else(|| entry.size()),
+            entry.uv_rect(texture.width(), texture.height()),
+            texture,
+            layouts,
+            sampler,
+            context,
+        )
+    }
+
+    // uv_rect is [offset_u, offset_v, scale_u, scale_v] with (0, 0) at the image's
+    // bottom left, since textures are flipped on load. Linear filtering samples
+    // right up to the rect's edge, so atlas cells need a pixel of padding to keep
+    // neighbours from bleeding in.
+    pub fn new_from_atlas(
+        pos: nalgebra_glm::Vec2,
+        size: nalgebra_glm::Vec2,
+        uv_rect: [f32; 4],
+        texture: &wgpu::Texture,
+        layouts: &SpriteLayouts,
+        sampler: &wgpu::Sampler,
+        context: &crate::Context,
+    ) -> Self {
+        let model_mat = build_model_mat(&pos, &size, 0.0, 0.0);
 
         let model_buf = context
             .device
@@ -31,6 +109,27 @@ impl Sprite {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
 
+        // Opaque white leaves the texture unchanged
+        let tint = Tint {
+            base: [1.0; 4],
+            effect: None,
+        };
+        let tint_buf = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&tint.rendered()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let uv_buf = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&uv_rect),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
             format: Some(wgpu::TextureFormat::Rgba8Unorm),
             ..Default::default()
@@ -40,7 +139,7 @@ impl Sprite {
             .device
             .create_bind_group(&wgpu::BindGroupDescriptor {
                 label: None,
-                layout: bind_layout,
+                layout: &layouts.uniforms,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
@@ -50,26 +149,61 @@ impl Sprite {
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                        resource: wgpu::BindingResource::Buffer(
+                            tint_buf.as_entire_buffer_binding(),
+                        ),
                     },
                     wgpu::BindGroupEntry {
                         binding: 2,
+                        resource: wgpu::BindingResource::Buffer(uv_buf.as_entire_buffer_binding()),
+                    },
+                ],
+            });
+
+        let texture_bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layouts.texture,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
                         resource: wgpu::BindingResource::Sampler(sampler),
                     },
                 ],
             });
 
         Self {
+            pos,
+            size,
+            rotation: 0.0,
+            z: 0.0,
             model_mat,
             model_buf,
-            texture_view,
+            tint,
+            tint_buf,
+            uv_rect,
+            uv_buf,
             bind_group,
+            texture_bind_group,
+            shader: ShaderId::DEFAULT,
+            layer: 0,
+            texture_key: (texture.global_id(), sampler.global_id()),
         }
     }
 
+    // v is in world units
     pub fn move_by(&mut self, v: &nalgebra_glm::Vec2, context: &crate::Context) {
-        let v = nalgebra_glm::vec2_to_vec3(v);
-        self.model_mat = nalgebra_glm::translate(&self.model_mat, &v);
+        self.pos += v;
+        self.update_model(context);
+    }
+
+    fn update_model(&mut self, context: &crate::Context) {
+        self.model_mat = build_model_mat(&self.pos, &self.size, self.rotation, self.z);
         context.queue.write_buffer(
             &self.model_buf,
             0,
@@ -77,15 +211,450 @@ impl Sprite {
         );
     }
 
-    pub fn get_view(&self) -> &wgpu::TextureView {
-        &self.texture_view
+    // World position of the bottom left corner, before rotation
+    pub fn position(&self) -> nalgebra_glm::Vec2 {
+        self.pos
+    }
+
+    pub fn size(&self) -> nalgebra_glm::Vec2 {
+        self.size
+    }
+
+    // Axis aligned bounds, ignores rotation like the collision checks do
+    pub fn rect(&self) -> Rect {
+        Rect {
+            pos: self.pos,
+            size: self.size,
+        }
+    }
+
+    pub fn set_size(&mut self, size: nalgebra_glm::Vec2, context: &crate::Context) {
+        self.size = size;
+        self.update_model(context);
+    }
+
+    // Snaps to an exact world position, clearing any drift from repeated move_by
+    pub fn set_position(&mut self, pos: nalgebra_glm::Vec2, context: &crate::Context) {
+        self.pos = pos;
+        self.update_model(context);
     }
 
-    pub fn get_buf(&self) -> &wgpu::Buffer {
-        &self.model_buf
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, angle: f32, context: &crate::Context) {
+        self.rotation = angle;
+        self.update_model(context);
+    }
+
+    pub fn z(&self) -> f32 {
+        self.z
+    }
+
+    // Anything outside -1..1 would be clipped by the projection
+    pub fn set_z(&mut self, z: f32, context: &crate::Context) {
+        self.z = z.clamp(-1.0, 1.0);
+        self.update_model(context);
+    }
+
+    // Maps a world point into the quad's [0, 1] space, None if the scale is degenerate
+    pub fn world_to_local(&self, p: nalgebra_glm::Vec2) -> Option<nalgebra_glm::Vec2> {
+        to_local(&self.model_mat, p)
+    }
+
+    // Whether the quad as drawn, rotation included, reaches into rect
+    pub fn touches(&self, rect: Rect) -> bool {
+        quad_touches(corners(&self.model_mat), |p| self.world_to_local(p), rect)
+    }
+
+    // The color actually drawn, the base tint with any effect applied
+    pub fn tint(&self) -> [f32; 4] {
+        self.tint.rendered()
+    }
+
+    // Multiplied with the sampled texture color, so alpha below 1.0 fades the sprite.
+    // Sets the base tint, a running effect stays applied on top of it.
+    pub fn set_tint(&mut self, color: [f32; 4], context: &crate::Context) {
+        self.tint.base = color;
+        self.write_tint(context);
+    }
+
+    // Multiplied over the base tint until cleared with None, for transient
+    // effects like a hit flash
+    pub fn set_tint_effect(&mut self, effect: Option<[f32; 4]>, context: &crate::Context) {
+        self.tint.effect = effect;
+        self.write_tint(context);
+    }
+
+    fn write_tint(&self, context: &crate::Context) {
+        context.queue.write_buffer(
+            &self.tint_buf,
+            0,
+            bytemuck::cast_slice(&self.tint.rendered()),
+        );
     }
 
+    pub fn uv_rect(&self) -> [f32; 4] {
+        self.uv_rect
+    }
+
+    pub fn set_uv_rect(&mut self, uv_rect: [f32; 4], context: &crate::Context) {
+        self.uv_rect = uv_rect;
+        context
+            .queue
+            .write_buffer(&self.uv_buf, 0, bytemuck::cast_slice(&self.uv_rect));
+    }
+
+    // This sprite's uniforms
     pub fn get_bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
+
+    // Interchangeable between sprites with the same texture_key
+    pub fn texture_bind_group(&self) -> &wgpu::BindGroup {
+        &self.texture_bind_group
+    }
+
+    pub fn model_mat(&self) -> &nalgebra_glm::Mat4 {
+        &self.model_mat
+    }
+
+    pub fn texture_key(&self) -> (wgpu::Id<wgpu::Texture>, wgpu::Id<wgpu::Sampler>) {
+        self.texture_key
+    }
+
+    pub fn shader(&self) -> ShaderId {
+        self.shader
+    }
+
+    pub fn set_shader(&mut self, shader: ShaderId) {
+        self.shader = shader;
+    }
+
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    // Lower layers are drawn first, so higher layers end up on top of sprites at
+    // the same z
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+}
+
+// translate * rotate about the center * scale, so rotating keeps the sprite centered
+fn build_model_mat(
+    pos: &nalgebra_glm::Vec2,
+    size: &nalgebra_glm::Vec2,
+    rotation: f32,
+    z: f32,
+) -> nalgebra_glm::Mat4 {
+    let mut pos = nalgebra_glm::vec2_to_vec3(pos);
+    pos[2] = z;
+    let half = nalgebra_glm::vec2_to_vec3(&(size / 2.0));
+    let mut size = nalgebra_glm::vec2_to_vec3(size);
+    size[2] = 1.0;
+    let mut model_mat = nalgebra_glm::identity();
+    model_mat = nalgebra_glm::translate(&model_mat, &(pos + half));
+    model_mat = nalgebra_glm::rotate_z(&model_mat, rotation);
+    model_mat = nalgebra_glm::translate(&model_mat, &-half);
+    nalgebra_glm::scale(&model_mat, &size)
+}
+
+fn to_local(model_mat: &nalgebra_glm::Mat4, p: nalgebra_glm::Vec2) -> Option<nalgebra_glm::Vec2> {
+    let inverse = model_mat.try_inverse()?;
+    let local = inverse * nalgebra_glm::vec4(p.x, p.y, 0.0, 1.0);
+    Some(nalgebra_glm::vec2(local.x, local.y))
+}
+
+// The unit quad's corners in world space, counter-clockwise from the bottom left
+fn corners(model_mat: &nalgebra_glm::Mat4) -> [nalgebra_glm::Vec2; 4] {
+    [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(x, y)| {
+        let p = model_mat * nalgebra_glm::vec4(x, y, 0.0, 1.0);
+        nalgebra_glm::vec2(p.x, p.y)
+    })
+}
+
+// Checks for a corner of either shape inside the other, or the rect's center inside
+// the quad. That misses only shapes crossing like a plus sign, which a bullet shorter
+// than the ship can't do.
+fn quad_touches(
+    quad: [nalgebra_glm::Vec2; 4],
+    to_local: impl Fn(nalgebra_glm::Vec2) -> Option<nalgebra_glm::Vec2>,
+    rect: Rect,
+) -> bool {
+    let (min, max) = (rect.pos, rect.pos + rect.size);
+    let in_rect = |p: &nalgebra_glm::Vec2| p.x > min.x && p.x < max.x && p.y > min.y && p.y < max.y;
+    if quad.iter().any(in_rect) {
+        return true;
+    }
+    let rect_points = [
+        min,
+        nalgebra_glm::vec2(max.x, min.y),
+        max,
+        nalgebra_glm::vec2(min.x, max.y),
+        (min + max) / 2.0,
+    ];
+    rect_points
+        .into_iter()
+        .filter_map(to_local)
+        .any(|local| local.x > 0.0 && local.x < 1.0 && local.y > 0.0 && local.y < 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_effect_returns_to_the_base_tint() {
+        let green = [0.4, 1.0, 0.4, 1.0];
+        let mut tint = Tint {
+            base: green,
+            effect: None,
+        };
+
+        tint.effect = Some([1.0, 1.0, 1.0, 0.4]);
+        assert_eq!(tint.rendered(), [0.4, 1.0, 0.4, 0.4]);
+
+        tint.effect = None;
+        assert_eq!(tint.rendered(), green);
+    }
+
+    #[test]
+    fn an_atlas_sprite_adopts_its_entry_size() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = crate::texture_cache::TextureCache::new();
+        let renderer = crate::renderer::Renderer::init(
+            &context,
+            &crate::test_surface_config(458, 380),
+            &mut textures,
+        );
+        let texture = crate::texture::create_rgba_texture(&context, 16, 8, &[255; 16 * 8 * 4]);
+        let sampler = crate::texture::create_sampler(
+            &context,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        let entry = AtlasEntry {
+            x: 8,
+            y: 0,
+            width: 8,
+            height: 4,
+        };
+        let pos = nalgebra_glm::vec2(0.0, 0.0);
+        let new = |size| {
+            Sprite::from_atlas_entry(
+                pos,
+                entry,
+                size,
+                &texture,
+                renderer.sprite_layouts(),
+                &sampler,
+                &context,
+            )
+        };
+
+        let sprite = new(None);
+        assert_eq!(sprite.size(), nalgebra_glm::vec2(8.0, 4.0));
+        assert_eq!(sprite.uv_rect(), [0.5, 0.5, 0.5, 0.5]);
+        let size = nalgebra_glm::vec2(3.0, 2.0);
+        assert_eq!(new(Some(size)).size(), size);
+    }
+
+    #[test]
+    fn changing_the_base_keeps_the_effect() {
+        let mut tint = Tint {
+            base: [1.0; 4],
+            effect: Some([1.0, 1.0, 1.0, 0.5]),
+        };
+        tint.base = [0.0, 0.0, 1.0, 1.0];
+        assert_eq!(tint.rendered(), [0.0, 0.0, 1.0, 0.5]);
+    }
+
+    fn assert_near(a: nalgebra_glm::Vec2, b: nalgebra_glm::Vec2) {
+        assert!(nalgebra_glm::distance(&a, &b) < 1e-4, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn corners_map_to_the_unit_quad() {
+        let pos = nalgebra_glm::vec2(30.0, 30.0);
+        let size = nalgebra_glm::vec2(13.0, 8.0);
+        let model_mat = build_model_mat(&pos, &size, 0.0, 0.0);
+
+        assert_near(
+            to_local(&model_mat, pos).unwrap(),
+            nalgebra_glm::vec2(0.0, 0.0),
+        );
+        assert_near(
+            to_local(&model_mat, pos + size).unwrap(),
+            nalgebra_glm::vec2(1.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn degenerate_scale_has_no_local_space() {
+        let model_mat = build_model_mat(
+            &nalgebra_glm::vec2(30.0, 30.0),
+            &nalgebra_glm::vec2(0.0, 8.0),
+            0.0,
+            0.0,
+        );
+        assert_eq!(to_local(&model_mat, nalgebra_glm::vec2(30.0, 30.0)), None);
+    }
+
+    fn transform(model_mat: &nalgebra_glm::Mat4, x: f32, y: f32) -> nalgebra_glm::Vec2 {
+        let p = model_mat * nalgebra_glm::vec4(x, y, 0.0, 1.0);
+        nalgebra_glm::vec2(p.x, p.y)
+    }
+
+    #[test]
+    fn rotation_turns_the_corners_about_the_center() {
+        let model_mat = build_model_mat(
+            &nalgebra_glm::vec2(0.0, 0.0),
+            &nalgebra_glm::vec2(1.0, 1.0),
+            std::f32::consts::FRAC_PI_2,
+            0.0,
+        );
+        // Counter-clockwise, each corner moves to the next one round
+        assert_near(
+            transform(&model_mat, 0.0, 0.0),
+            nalgebra_glm::vec2(1.0, 0.0),
+        );
+        assert_near(
+            transform(&model_mat, 1.0, 0.0),
+            nalgebra_glm::vec2(1.0, 1.0),
+        );
+        assert_near(
+            transform(&model_mat, 1.0, 1.0),
+            nalgebra_glm::vec2(0.0, 1.0),
+        );
+        assert_near(
+            transform(&model_mat, 0.0, 1.0),
+            nalgebra_glm::vec2(0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn a_rotated_player_stays_centered() {
+        let pos = nalgebra_glm::vec2(30.0, 30.0);
+        let size = nalgebra_glm::vec2(13.0, 8.0);
+        let model_mat = build_model_mat(&pos, &size, std::f32::consts::FRAC_PI_2, 0.0);
+        assert_near(transform(&model_mat, 0.5, 0.5), pos + size / 2.0);
+    }
+
+    fn touches(pos: (f32, f32), size: (f32, f32), rotation: f32, rect: Rect) -> bool {
+        let model_mat = build_model_mat(
+            &nalgebra_glm::vec2(pos.0, pos.1),
+            &nalgebra_glm::vec2(size.0, size.1),
+            rotation,
+            0.0,
+        );
+        quad_touches(corners(&model_mat), |p| to_local(&model_mat, p), rect)
+    }
+
+    #[test]
+    fn a_tilted_bullet_misses_where_its_box_would_hit() {
+        let ship = Rect {
+            pos: nalgebra_glm::vec2(30.0, 10.0),
+            size: nalgebra_glm::vec2(13.0, 8.0),
+        };
+        // A long thin bullet at the ship's top right corner, its unrotated box
+        // overlaps the ship but turned 45 degrees it passes beside it
+        let (pos, size) = ((42.0, 17.0), (2.0, 10.0));
+        assert!(crate::collision::overlaps(
+            nalgebra_glm::vec2(pos.0, pos.1),
+            nalgebra_glm::vec2(size.0, size.1),
+            ship.pos,
+            ship.size,
+        ));
+        assert!(!touches(pos, size, std::f32::consts::FRAC_PI_4, ship));
+        // Straight down and lower, its tip is inside
+        assert!(touches((35.0, 15.0), size, 0.0, ship));
+        // Big enough to swallow the ship, only the center check sees it
+        assert!(touches((20.0, 0.0), (40.0, 40.0), 0.3, ship));
+        // Only touching at an edge doesn't count, like collision::overlaps
+        assert!(!touches((43.0, 10.0), (2.0, 8.0), 0.0, ship));
+    }
+
+    // The lookup vs_main in shader.wgsl does
+    fn atlas_uv(uv_rect: [f32; 4], tex_coord: [f32; 2]) -> [f32; 2] {
+        [
+            uv_rect[0] + tex_coord[0] * uv_rect[2],
+            uv_rect[1] + tex_coord[1] * uv_rect[3],
+        ]
+    }
+
+    #[test]
+    fn a_2x2_atlas_cell_covers_one_quarter() {
+        // Top right cell, textures are flipped so v grows upwards
+        let cell = [0.5, 0.5, 0.5, 0.5];
+        assert_eq!(atlas_uv(cell, [0.0, 0.0]), [0.5, 0.5]);
+        assert_eq!(atlas_uv(cell, [1.0, 1.0]), [1.0, 1.0]);
+        // The quad's center samples the center of the cell's texel
+        assert_eq!(atlas_uv(cell, [0.5, 0.5]), [0.75, 0.75]);
+
+        assert_eq!(atlas_uv(FULL_UV_RECT, [0.25, 0.75]), [0.25, 0.75]);
+    }
+
+    // A solid white sprite drawn with a fresh renderer's layouts
+    fn test_sprite(
+        context: &crate::Context,
+        pos: nalgebra_glm::Vec2,
+        size: nalgebra_glm::Vec2,
+    ) -> Sprite {
+        let mut textures = crate::texture_cache::TextureCache::new();
+        let renderer = crate::renderer::Renderer::init(
+            context,
+            &crate::test_surface_config(458, 380),
+            &mut textures,
+        );
+        let solid = textures.solid(context);
+        Sprite::new(
+            pos,
+            size,
+            &solid.texture,
+            renderer.sprite_layouts(),
+            &solid.sampler,
+            context,
+        )
+    }
+
+    #[test]
+    fn set_position_snaps_exactly_after_many_moves() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut sprite = test_sprite(
+            &context,
+            nalgebra_glm::vec2(30.0, 30.0),
+            nalgebra_glm::vec2(13.0, 8.0),
+        );
+        for _ in 0..1000 {
+            sprite.move_by(&nalgebra_glm::vec2(0.001, 0.0), &context);
+        }
+        sprite.set_position(nalgebra_glm::vec2(50.0, 40.0), &context);
+        assert_eq!(sprite.position(), nalgebra_glm::vec2(50.0, 40.0));
+        assert_eq!(
+            sprite.model_mat().column(3).xy(),
+            nalgebra_glm::vec2(50.0, 40.0)
+        );
+    }
+
+    #[test]
+    fn getters_follow_moves() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut sprite = test_sprite(
+            &context,
+            nalgebra_glm::vec2(30.0, 30.0),
+            nalgebra_glm::vec2(13.0, 8.0),
+        );
+        sprite.move_by(&nalgebra_glm::vec2(1.0, 0.5), &context);
+        assert_eq!(sprite.position(), nalgebra_glm::vec2(31.0, 30.5));
+        assert_eq!(sprite.size(), nalgebra_glm::vec2(13.0, 8.0));
+    }
 }
@@ -0,0 +1,379 @@
+use std::sync::Arc;
+
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+use crate::renderer::{create_depth_view, create_msaa_view, RenderTarget, DEPTH_FORMAT};
+use crate::Context;
+
+#[derive(Debug)]
+pub enum SurfaceCreationError {
+    Create(wgpu::CreateSurfaceError),
+    // The adapter can't present to this window
+    Unsupported,
+}
+
+impl std::fmt::Display for SurfaceCreationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SurfaceCreationError::Create(err) => {
+                write!(f, "Could not create a window surface: {err}")?
+            }
+            SurfaceCreationError::Unsupported => {
+                write!(f, "The graphics adapter can't present to this window")?
+            }
+        }
+        if cfg!(target_os = "linux") {
+            write!(
+                f,
+                "\nOn Wayland, try running under X11 instead with WINIT_UNIX_BACKEND=x11"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SurfaceCreationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SurfaceCreationError::Create(err) => Some(err),
+            SurfaceCreationError::Unsupported => None,
+        }
+    }
+}
+
+// The surface's default config, None when the adapter can't present to it
+fn window_config(
+    default: Option<wgpu::SurfaceConfiguration>,
+) -> Result<wgpu::SurfaceConfiguration, SurfaceCreationError> {
+    let mut config = default.ok_or(SurfaceCreationError::Unsupported)?;
+    let format = config.format.remove_srgb_suffix();
+    config.format = format;
+    config.view_formats.push(format);
+    Ok(config)
+}
+
+// Fifo is the one mode every surface has to support, so it's the fallback
+fn supported_present_mode(
+    supported: &[wgpu::PresentMode],
+    requested: wgpu::PresentMode,
+) -> wgpu::PresentMode {
+    if supported.contains(&requested) {
+        return requested;
+    }
+    eprintln!("Present mode {requested:?} is not supported, falling back to Fifo");
+    wgpu::PresentMode::Fifo
+}
+
+// Order the present mode hotkey steps through
+const PRESENT_MODE_CYCLE: [wgpu::PresentMode; 3] = [
+    wgpu::PresentMode::Fifo,
+    wgpu::PresentMode::Mailbox,
+    wgpu::PresentMode::Immediate,
+];
+
+// The mode after current in PRESENT_MODE_CYCLE, skipping unsupported ones and
+// wrapping around. Stays on current when nothing else is supported.
+fn next_present_mode(
+    supported: &[wgpu::PresentMode],
+    current: wgpu::PresentMode,
+) -> wgpu::PresentMode {
+    let start = PRESENT_MODE_CYCLE
+        .iter()
+        .position(|&mode| mode == current)
+        .unwrap_or(0);
+    (1..=PRESENT_MODE_CYCLE.len())
+        .map(|offset| PRESENT_MODE_CYCLE[(start + offset) % PRESENT_MODE_CYCLE.len()])
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(current)
+}
+
+pub const MSAA_SAMPLES: u32 = 4;
+
+// Falls back to 1 when the adapter can't multisample the color or depth format at
+// the requested count. Counts other than 1 and 4 also need the adapter specific
+// format features enabled on the device.
+fn supported_sample_count(
+    color: wgpu::TextureFormatFeatureFlags,
+    depth: wgpu::TextureFormatFeatureFlags,
+    adapter_specific: bool,
+    requested: u32,
+) -> u32 {
+    if requested <= 1 {
+        return 1;
+    }
+    let allowed = requested == 4 || adapter_specific;
+    if allowed && color.sample_count_supported(requested) && depth.sample_count_supported(requested)
+    {
+        return requested;
+    }
+    eprintln!("{requested}x MSAA is not supported, falling back to no multisampling");
+    1
+}
+
+// Resizing the window fires a flood of events, only the latest size matters.
+// Requests are coalesced here until the next frame applies them.
+#[derive(Debug, Default)]
+pub struct PendingResize {
+    size: Option<PhysicalSize<u32>>,
+}
+
+impl PendingResize {
+    pub fn request(&mut self, size: PhysicalSize<u32>) {
+        self.size = Some(size);
+    }
+
+    // The last requested size, once
+    pub fn take(&mut self) -> Option<PhysicalSize<u32>> {
+        self.size.take()
+    }
+}
+
+pub struct Surface {
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    pending_resize: PendingResize,
+    // Match the surface size, recreated with it
+    depth_view: wgpu::TextureView,
+    msaa_view: Option<wgpu::TextureView>,
+    sample_count: u32,
+    // Physical pixels per logical pixel of the window's current monitor
+    scale_factor: f64,
+}
+impl Surface {
+    // sample_count is a request, check sample_count() for what the adapter allows
+    pub fn new(
+        context: &Context,
+        window: Arc<Window>,
+        sample_count: u32,
+    ) -> Result<Self, SurfaceCreationError> {
+        let window_size = window.inner_size();
+        let scale_factor = window.scale_factor();
+        let width = window_size.width.max(1);
+        let height = window_size.height.max(1);
+
+        let surface = context
+            .instance
+            .create_surface(window)
+            .map_err(SurfaceCreationError::Create)?;
+
+        let config = window_config(surface.get_default_config(&context.adapter, width, height))?;
+        let format = config.format;
+
+        surface.configure(&context.device, &config);
+        let flags = |format| context.adapter.get_texture_format_features(format).flags;
+        let sample_count = supported_sample_count(
+            flags(format),
+            flags(DEPTH_FORMAT),
+            context
+                .device
+                .features()
+                .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES),
+            sample_count,
+        );
+        let depth_view = create_depth_view(&context.device, width, height, sample_count);
+        let msaa_view = create_msaa_view(&context.device, format, width, height, sample_count);
+
+        Ok(Self {
+            surface,
+            config,
+            pending_resize: PendingResize::default(),
+            depth_view,
+            msaa_view,
+            sample_count,
+            scale_factor,
+        })
+    }
+
+    // Lost and Outdated are expected after resizes, minimizing or a GPU reset, the
+    // caller recovers with reconfigure and skips the frame. Timeout just skips the
+    // frame, only OutOfMemory is fatal.
+    pub fn next_texture(&mut self) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
+        self.surface.get_current_texture()
+    }
+
+    pub fn reconfigure(&mut self, context: &Context) {
+        self.surface.configure(&context.device, &self.config);
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    pub fn set_present_mode(&mut self, context: &Context, mode: wgpu::PresentMode) {
+        let supported = self
+            .surface
+            .get_capabilities(&context.adapter)
+            .present_modes;
+        let mode = supported_present_mode(&supported, mode);
+        if mode == self.config.present_mode {
+            return;
+        }
+        self.config.present_mode = mode;
+        self.reconfigure(context);
+    }
+
+    pub fn cycle_present_mode(&mut self, context: &Context) -> wgpu::PresentMode {
+        let supported = self
+            .surface
+            .get_capabilities(&context.adapter)
+            .present_modes;
+        let mode = next_present_mode(&supported, self.config.present_mode);
+        self.set_present_mode(context, mode);
+        self.config.present_mode
+    }
+
+    pub fn resize(&mut self, context: &Context, size: PhysicalSize<u32>) {
+        let config = &mut self.config;
+        config.width = size.width.max(1);
+        config.height = size.height.max(1);
+
+        let surface = &mut self.surface;
+        surface.configure(&context.device, config);
+        self.depth_view = create_depth_view(
+            &context.device,
+            config.width,
+            config.height,
+            self.sample_count,
+        );
+        self.msaa_view = create_msaa_view(
+            &context.device,
+            config.view_formats[0],
+            config.width,
+            config.height,
+            self.sample_count,
+        );
+    }
+
+    // The world is letterboxed onto physical pixels, so only the surface size
+    // depends on the scale factor. size is the window's physical size under it.
+    pub fn set_scale_factor(&mut self, scale_factor: f64, size: PhysicalSize<u32>) {
+        self.scale_factor = scale_factor;
+        self.request_resize(size);
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn request_resize(&mut self, size: PhysicalSize<u32>) {
+        self.pending_resize.request(size);
+    }
+
+    // Returns the applied size so dependents like the projection can follow it
+    pub fn apply_pending_resize(&mut self, context: &Context) -> Option<PhysicalSize<u32>> {
+        let size = self.pending_resize.take()?;
+        self.resize(context, size);
+        Some(size)
+    }
+
+    pub fn config(&self) -> &wgpu::SurfaceConfiguration {
+        &self.config
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    // Wraps a view of the current frame with the surface's depth and MSAA textures
+    pub fn render_target<'a>(&'a self, color: &'a wgpu::TextureView) -> RenderTarget<'a> {
+        RenderTarget {
+            color,
+            depth: &self.depth_view,
+            msaa: self.msaa_view.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wgpu::PresentMode;
+
+    #[test]
+    fn present_mode_cycle_skips_unsupported_and_wraps() {
+        let supported = [PresentMode::Fifo, PresentMode::Immediate];
+        assert_eq!(
+            next_present_mode(&supported, PresentMode::Fifo),
+            PresentMode::Immediate
+        );
+        assert_eq!(
+            next_present_mode(&supported, PresentMode::Immediate),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn resize_requests_coalesce_into_the_last_size() {
+        let mut pending = PendingResize::default();
+        assert_eq!(pending.take(), None);
+        for width in 100..110 {
+            pending.request(PhysicalSize::new(width, 300));
+        }
+        // One configure per frame, at the size the window settled on
+        assert_eq!(pending.take(), Some(PhysicalSize::new(109, 300)));
+        assert_eq!(pending.take(), None);
+    }
+
+    #[test]
+    fn present_mode_cycle_with_one_mode_is_a_no_op() {
+        assert_eq!(
+            next_present_mode(&[PresentMode::Fifo], PresentMode::Fifo),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn unsupported_present_modes_fall_back_to_fifo() {
+        let supported = [PresentMode::Fifo, PresentMode::Mailbox];
+        assert_eq!(
+            supported_present_mode(&supported, PresentMode::Mailbox),
+            PresentMode::Mailbox
+        );
+        assert_eq!(
+            supported_present_mode(&supported, PresentMode::Immediate),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn msaa_falls_back_without_multisampled_formats() {
+        use wgpu::TextureFormatFeatureFlags as Flags;
+        let x4 = Flags::MULTISAMPLE_X4;
+        assert_eq!(supported_sample_count(x4, x4, false, 4), 4);
+        assert_eq!(supported_sample_count(x4, Flags::empty(), false, 4), 1);
+        assert_eq!(supported_sample_count(Flags::empty(), x4, false, 4), 1);
+        assert_eq!(
+            supported_sample_count(Flags::empty(), Flags::empty(), false, 1),
+            1
+        );
+    }
+
+    #[test]
+    fn other_counts_need_adapter_specific_features() {
+        use wgpu::TextureFormatFeatureFlags as Flags;
+        let x8 = Flags::MULTISAMPLE_X8;
+        assert_eq!(supported_sample_count(x8, x8, false, 8), 1);
+        assert_eq!(supported_sample_count(x8, x8, true, 8), 8);
+    }
+
+    #[test]
+    fn no_default_config_is_an_unsupported_surface() {
+        let err = window_config(None).unwrap_err();
+        assert!(matches!(err, SurfaceCreationError::Unsupported));
+        assert!(std::error::Error::source(&err).is_none());
+        if cfg!(target_os = "linux") {
+            assert!(err.to_string().contains("WINIT_UNIX_BACKEND=x11"));
+        }
+    }
+
+    #[test]
+    fn window_configs_drop_the_srgb_suffix() {
+        let config = window_config(Some(crate::test_surface_config(458, 380))).unwrap();
+        assert_eq!(config.format, wgpu::TextureFormat::Rgba8Unorm);
+        assert!(config
+            .view_formats
+            .contains(&wgpu::TextureFormat::Rgba8Unorm));
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+// Turns pacing off, frames without vsync then go out as fast as they render
+const NO_TEAR_CONTROL_ENV: &str = "VADERS_NO_TEAR_CONTROL";
+
+// Without vsync every frame is presented the moment it's done, so several land
+// in one refresh and tear. Holding the next redraw until the monitor's next
+// refresh keeps the low latency of Immediate and Mailbox with far less tearing.
+#[derive(Debug)]
+pub struct TearControl {
+    enabled: bool,
+    next: Option<Instant>,
+}
+
+impl TearControl {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var_os(NO_TEAR_CONTROL_ENV).is_none(),
+            next: None,
+        }
+    }
+
+    // When to start the next frame, None for straight away. Vsync already paces
+    // itself and a monitor that doesn't report its refresh isn't capped.
+    pub fn next_frame(
+        &mut self,
+        mode: wgpu::PresentMode,
+        refresh_millihertz: Option<u32>,
+        now: Instant,
+    ) -> Option<Instant> {
+        let interval = refresh_millihertz
+            .filter(|_| self.enabled && !is_vsync(mode))
+            .and_then(frame_interval);
+        let Some(interval) = interval else {
+            self.next = None;
+            return None;
+        };
+        // A slow frame starts the cadence over rather than rushing to catch up
+        let next = self
+            .next
+            .map(|next| next + interval)
+            .filter(|&next| next > now)
+            .unwrap_or(now + interval);
+        self.next = Some(next);
+        Some(next)
+    }
+}
+
+fn is_vsync(mode: wgpu::PresentMode) -> bool {
+    matches!(
+        mode,
+        wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed | wgpu::PresentMode::AutoVsync
+    )
+}
+
+// One refresh, winit reports rates in thousandths of a hertz
+fn frame_interval(refresh_millihertz: u32) -> Option<Duration> {
+    (refresh_millihertz > 0)
+        .then(|| Duration::from_secs_f64(1000.0 / f64::from(refresh_millihertz)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_cap_is_one_refresh() {
+        let interval = frame_interval(60_000).unwrap();
+        assert!((interval.as_secs_f64() - 1.0 / 60.0).abs() < 1e-9);
+        let interval = frame_interval(143_856).unwrap();
+        assert!((interval.as_secs_f64() - 1.0 / 143.856).abs() < 1e-9);
+        assert_eq!(frame_interval(0), None);
+    }
+
+    #[test]
+    fn only_frames_without_vsync_are_paced() {
+        let mut control = TearControl {
+            enabled: true,
+            next: None,
+        };
+        let now = Instant::now();
+        let interval = frame_interval(60_000).unwrap();
+        let mode = wgpu::PresentMode::Immediate;
+        assert_eq!(
+            control.next_frame(mode, Some(60_000), now),
+            Some(now + interval)
+        );
+        // Steady frames keep the cadence
+        let soon = now + interval / 2;
+        assert_eq!(
+            control.next_frame(mode, Some(60_000), soon),
+            Some(now + interval * 2)
+        );
+
+        assert_eq!(
+            control.next_frame(wgpu::PresentMode::Fifo, Some(60_000), now),
+            None
+        );
+        assert_eq!(control.next_frame(mode, None, now), None);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::renderer::Renderer;
+use crate::{sprite::Sprite, texture, Context};
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+// Each glyph sits in a cell with a pixel of padding on every side so
+// neighbouring glyphs can't bleed in
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 2;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+const ATLAS_COLUMNS: u32 = 16;
+// In glyph pixels, before scaling
+const ADVANCE: f32 = (GLYPH_WIDTH + 1) as f32;
+const LINE_HEIGHT: f32 = (GLYPH_HEIGHT + 1) as f32;
+// Above the game's sprites
+const TEXT_LAYER: i32 = 100;
+
+// 3x5 glyphs, one row per byte from the top, the lowest three bits are the
+// pixels left to right. Lowercase letters are drawn as uppercase.
+const GLYPHS: &[(char, [u8; 5])] = &[
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b111, 0b001, 0b010, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+    ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+    (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+];
+// Drawn for anything without a glyph, stored right after GLYPHS in the atlas
+const FALLBACK_GLYPH: [u8; 5] = [0b111, 0b111, 0b111, 0b111, 0b111];
+
+// Where a glyph quad goes in the world and which part of the atlas it shows
+struct GlyphQuad {
+    pos: nalgebra_glm::Vec2,
+    uv_rect: [f32; 4],
+}
+
+// Draws ASCII text as one sprite per glyph, sampling a font atlas built at
+// startup. Sprites are pooled and reused across frames.
+pub struct TextRenderer {
+    atlas: wgpu::Texture,
+    sampler: wgpu::Sampler,
+    glyphs: Vec<Sprite>,
+    // Glyphs drawn since the last clear, the rest of the pool is idle
+    used: usize,
+}
+
+impl TextRenderer {
+    pub fn new(context: &Context) -> Self {
+        // Nearest filtering keeps the pixel font crisp at any scale
+        let sampler = texture::create_sampler(
+            context,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        Self {
+            atlas: create_atlas(context),
+            sampler,
+            glyphs: Vec::new(),
+            used: 0,
+        }
+    }
+
+    // Call once per frame before drawing that frame's text
+    pub fn clear(&mut self) {
+        self.used = 0;
+    }
+
+    // pos is the top left of the first line, scale is world units per font pixel.
+    // Newlines start a new line below it.
+    pub fn draw_string(
+        &mut self,
+        context: &Context,
+        renderer: &Renderer,
+        text: &str,
+        pos: nalgebra_glm::Vec2,
+        scale: f32,
+    ) {
+        let size = nalgebra_glm::vec2(GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32) * scale;
+        for quad in layout_string(text, pos, scale) {
+            if self.used == self.glyphs.len() {
+                let mut sprite = Sprite::new_from_atlas(
+                    quad.pos,
+                    size,
+                    quad.uv_rect,
+                    &self.atlas,
+                    renderer.sprite_layouts(),
+                    &self.sampler,
+                    context,
+                );
+                sprite.set_layer(TEXT_LAYER);
+                self.glyphs.push(sprite);
+            } else {
+                let sprite = &mut self.glyphs[self.used];
+                sprite.set_size(size, context);
+                sprite.set_position(quad.pos, context);
+                sprite.set_uv_rect(quad.uv_rect, context);
+            }
+            self.used += 1;
+        }
+    }
+
+    pub fn sprites(&self) -> Vec<&Sprite> {
+        self.glyphs[..self.used].iter().collect()
+    }
+}
+
+// World units from the left of the first glyph to the right of the last, for
+// single lines. Used to right-align text.
+pub fn line_width(text: &str, scale: f32) -> f32 {
+    let count = text.chars().count() as f32;
+    (count * ADVANCE - 1.0).max(0.0) * scale
+}
+
+// One quad per visible character, spaces and newlines only move the pen
+fn layout_string(text: &str, pos: nalgebra_glm::Vec2, scale: f32) -> Vec<GlyphQuad> {
+    let mut quads = Vec::new();
+    let mut pen = pos;
+    for c in text.chars() {
+        match c {
+            '\n' => {
+                pen.x = pos.x;
+                pen.y -= LINE_HEIGHT * scale;
+            }
+            ' ' => pen.x += ADVANCE * scale,
+            c => {
+                quads.push(GlyphQuad {
+                    // Sprite positions are bottom left, the pen is at the top
+                    pos: nalgebra_glm::vec2(pen.x, pen.y - GLYPH_HEIGHT as f32 * scale),
+                    uv_rect: glyph_uv_rect(glyph_index(c)),
+                });
+                pen.x += ADVANCE * scale;
+            }
+        }
+    }
+    quads
+}
+
+fn glyph_index(c: char) -> usize {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .position(|(glyph, _)| *glyph == c)
+        .unwrap_or(GLYPHS.len())
+}
+
+fn atlas_size() -> (u32, u32) {
+    let glyph_count = GLYPHS.len() as u32 + 1;
+    let rows = glyph_count.div_ceil(ATLAS_COLUMNS);
+    (ATLAS_COLUMNS * CELL_WIDTH, rows * CELL_HEIGHT)
+}
+
+// The atlas is flipped like loaded textures, so v counts up from its bottom row
+fn glyph_uv_rect(index: usize) -> [f32; 4] {
+    let (width, height) = atlas_size();
+    let col = index as u32 % ATLAS_COLUMNS;
+    let row = index as u32 / ATLAS_COLUMNS;
+    let x = col * CELL_WIDTH + 1;
+    let y = height - (row * CELL_HEIGHT + 1 + GLYPH_HEIGHT);
+    [
+        x as f32 / width as f32,
+        y as f32 / height as f32,
+        GLYPH_WIDTH as f32 / width as f32,
+        GLYPH_HEIGHT as f32 / height as f32,
+    ]
+}
+
+fn create_atlas(context: &Context) -> wgpu::Texture {
+    let (width, height) = atlas_size();
+    let mut texels = vec![0u8; (width * height * 4) as usize];
+
+    let bitmaps = GLYPHS
+        .iter()
+        .map(|(_, bitmap)| bitmap)
+        .chain([&FALLBACK_GLYPH]);
+    for (index, bitmap) in bitmaps.enumerate() {
+        let col = index as u32 % ATLAS_COLUMNS;
+        let row = index as u32 / ATLAS_COLUMNS;
+        for (gy, bits) in bitmap.iter().enumerate() {
+            for gx in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - gx)) == 0 {
+                    continue;
+                }
+                let x = col * CELL_WIDTH + 1 + gx;
+                // Bitmaps are top down, texture rows bottom up
+                let y = height - 1 - (row * CELL_HEIGHT + 1 + gy as u32);
+                let i = ((y * width + x) * 4) as usize;
+                texels[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+            }
+        }
+    }
+
+    texture::create_rgba_texture(context, width, height, &texels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_and_newlines_only_move_the_pen() {
+        let quads = layout_string("A B\nC", nalgebra_glm::vec2(10.0, 50.0), 2.0);
+        let positions: Vec<_> = quads.iter().map(|quad| (quad.pos.x, quad.pos.y)).collect();
+        assert_eq!(positions, [(10.0, 40.0), (26.0, 40.0), (10.0, 28.0)]);
+    }
+
+    #[test]
+    fn line_width_leaves_off_the_trailing_gap() {
+        assert_eq!(line_width("PAUSED", 2.0), 46.0);
+        assert_eq!(line_width("", 2.0), 0.0);
+    }
+
+    #[test]
+    fn lowercase_and_unknown_characters_map_to_glyphs() {
+        let quads = layout_string("a~", nalgebra_glm::vec2(0.0, 0.0), 1.0);
+        assert_eq!(quads[0].uv_rect, glyph_uv_rect(glyph_index('A')));
+        assert_eq!(quads[1].uv_rect, glyph_uv_rect(GLYPHS.len()));
+    }
+
+    #[test]
+    fn glyph_rects_skip_the_cell_padding() {
+        let (width, height) = atlas_size();
+        assert_eq!((width, height), (80, 28));
+
+        assert_eq!(
+            glyph_uv_rect(0),
+            [1.0 / 80.0, 22.0 / 28.0, 3.0 / 80.0, 5.0 / 28.0]
+        );
+        // First glyph of the second row
+        assert_eq!(
+            glyph_uv_rect(ATLAS_COLUMNS as usize),
+            [1.0 / 80.0, 15.0 / 28.0, 3.0 / 80.0, 5.0 / 28.0]
+        );
+    }
+}
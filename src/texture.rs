@@ -0,0 +1,326 @@
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::io::Reader as ImageReader;
+use image::ImageError;
+use wgpu::util::DeviceExt;
+
+use crate::Context;
+
+#[derive(Debug)]
+pub enum TextureError {
+    NotFound(PathBuf),
+    Io(PathBuf, std::io::Error),
+    // The header didn't match any format the image crate can decode
+    UnsupportedFormat(PathBuf),
+    Decode(PathBuf, ImageError),
+    // Bytes compiled into the binary, there's no path to report
+    Embedded(ImageError),
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::NotFound(path) => write!(f, "Missing texture {}", path.display()),
+            TextureError::Io(path, err) => write!(f, "Could not read {}: {err}", path.display()),
+            TextureError::UnsupportedFormat(path) => {
+                write!(f, "{} is not in a supported image format", path.display())
+            }
+            TextureError::Decode(path, err) => {
+                write!(f, "Could not decode {}: {err}", path.display())
+            }
+            TextureError::Embedded(err) => write!(f, "Could not decode embedded image: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::NotFound(_) | TextureError::UnsupportedFormat(_) => None,
+            TextureError::Io(_, err) => Some(err),
+            TextureError::Decode(_, err) | TextureError::Embedded(err) => Some(err),
+        }
+    }
+}
+
+// The format is detected from the file header, not the extension, and every
+// image is converted to Rgba8
+pub fn load_texture(
+    context: &Context,
+    path: impl AsRef<Path>,
+) -> Result<wgpu::Texture, TextureError> {
+    Ok(upload_image(context, &decode_file(path)?))
+}
+
+// Same as load_texture for an image already in memory, e.g. from include_bytes!
+pub fn load_texture_from_bytes(
+    context: &Context,
+    bytes: &[u8],
+) -> Result<wgpu::Texture, TextureError> {
+    Ok(upload_image(context, &decode_bytes(bytes)?))
+}
+
+pub fn decode_file(path: impl AsRef<Path>) -> Result<image::RgbaImage, TextureError> {
+    let path = path.as_ref();
+    let img = open_file(path)?.decode().map_err(|err| match err {
+        ImageError::Unsupported(_) => TextureError::UnsupportedFormat(path.to_owned()),
+        err => TextureError::Decode(path.to_owned(), err),
+    })?;
+    Ok(to_texels(img))
+}
+
+pub fn decode_bytes(bytes: &[u8]) -> Result<image::RgbaImage, TextureError> {
+    let img = image::load_from_memory(bytes).map_err(TextureError::Embedded)?;
+    Ok(to_texels(img))
+}
+
+// Width and height from the header alone, without decoding the pixels
+pub fn file_size(path: impl AsRef<Path>) -> Result<(u32, u32), TextureError> {
+    let path = path.as_ref();
+    open_file(path)?
+        .into_dimensions()
+        .map_err(|err| TextureError::Decode(path.to_owned(), err))
+}
+
+pub fn bytes_size(bytes: &[u8]) -> Result<(u32, u32), TextureError> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|err| TextureError::Embedded(err.into()))?
+        .into_dimensions()
+        .map_err(TextureError::Embedded)
+}
+
+fn open_file(path: &Path) -> Result<ImageReader<BufReader<File>>, TextureError> {
+    // ImageReader::open would take the format from the extension and keep it when
+    // the header doesn't match, so garbage named .png would fail as a bad PNG
+    let reader = File::open(path)
+        .map(BufReader::new)
+        .and_then(|file| ImageReader::new(file).with_guessed_format())
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => TextureError::NotFound(path.to_owned()),
+            _ => TextureError::Io(path.to_owned(), err),
+        })?;
+    if reader.format().is_none() {
+        return Err(TextureError::UnsupportedFormat(path.to_owned()));
+    }
+    Ok(reader)
+}
+
+// Flipped so rows run bottom up like texture coordinates
+fn to_texels(img: image::DynamicImage) -> image::RgbaImage {
+    img.flipv().to_rgba8()
+}
+
+fn upload_image(context: &Context, img: &image::RgbaImage) -> wgpu::Texture {
+    create_rgba_texture(context, img.width(), img.height(), img.as_raw())
+}
+
+// Replaces all of texture, img has to be its size
+pub fn write_image(context: &Context, texture: &wgpu::Texture, img: &image::RgbaImage) {
+    context.queue.write_texture(
+        texture.as_image_copy(),
+        img.as_raw(),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(img.width() * 4),
+            rows_per_image: Some(img.height()),
+        },
+        texture.size(),
+    );
+}
+
+// A 1x1 opaque white texture with a ClampToEdge sampler, for sprites that are
+// just a tinted rectangle. Shared through TextureCache::solid.
+#[derive(Clone)]
+pub struct SolidTexture {
+    pub texture: Arc<wgpu::Texture>,
+    pub sampler: Arc<wgpu::Sampler>,
+}
+
+pub fn create_solid_texture(context: &Context) -> SolidTexture {
+    SolidTexture {
+        texture: Arc::new(create_rgba_texture(context, 1, 1, &[255, 255, 255, 255])),
+        sampler: Arc::new(create_sampler(
+            context,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::AddressMode::ClampToEdge,
+        )),
+    }
+}
+
+// Rows bottom up, matching the flip load_texture applies
+pub fn create_rgba_texture(
+    context: &Context,
+    width: u32,
+    height: u32,
+    texels: &[u8],
+) -> wgpu::Texture {
+    context.device.create_texture_with_data(
+        &context.queue,
+        &wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::MipMajor,
+        texels,
+    )
+}
+
+// Repeat lets a small texture tile across a quad with UVs past 1.0
+pub fn create_sampler(
+    context: &Context,
+    address_mode_u: wgpu::AddressMode,
+    address_mode_v: wgpu::AddressMode,
+) -> wgpu::Sampler {
+    context.device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u,
+        address_mode_v,
+        ..Default::default()
+    })
+}
+
+// Copies a texture back to the CPU as tightly packed RGBA rows, top row first.
+// Only 8 bit RGBA and BGRA formats are supported, BGRA is swizzled to RGBA.
+pub fn read_texture_to_rgba(
+    context: &Context,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+    let width = texture.width();
+    let height = texture.height();
+    let row_bytes = width * 4;
+    // Buffer copies need rows aligned to 256 bytes
+    let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texture Readback"),
+        size: (padded_row_bytes * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = context
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    context.queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    context.device.poll(wgpu::Maintain::Wait);
+    // The callback always runs during the blocking poll
+    receiver.recv().unwrap()?;
+
+    let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+    for row in slice.get_mapped_range().chunks(padded_row_bytes as usize) {
+        pixels.extend_from_slice(&row[..row_bytes as usize]);
+    }
+    buffer.unmap();
+
+    if matches!(
+        texture.format(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Written under the temp dir, unique per test process
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vaders-{}-{name}", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn a_missing_file_is_not_found() {
+        let path = std::env::temp_dir().join("vaders-no-such-texture.png");
+        assert!(matches!(decode_file(&path), Err(TextureError::NotFound(p)) if p == path));
+    }
+
+    #[test]
+    fn garbage_is_an_unsupported_format_whatever_the_extension() {
+        let path = temp_file("garbage.png", b"definitely not an image");
+        let result = decode_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(TextureError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn a_truncated_png_fails_to_decode() {
+        let path = temp_file("truncated.png", &crate::assets::PLAYER_PNG[..40]);
+        let result = decode_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(TextureError::Decode(..))));
+    }
+
+    #[test]
+    fn the_embedded_player_decodes() {
+        let img = decode_bytes(crate::assets::PLAYER_PNG).unwrap();
+        assert_eq!(img.dimensions(), (13, 8));
+        assert!(matches!(
+            decode_bytes(b"not a png"),
+            Err(TextureError::Embedded(_))
+        ));
+    }
+
+    // A 3x2 image with distinct pixels, encoded in format under the given name
+    fn encoded(name: &str, format: image::ImageFormat) -> PathBuf {
+        let img =
+            image::RgbImage::from_fn(3, 2, |x, y| image::Rgb([x as u8 * 80, y as u8 * 120, 40]));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut bytes, format).unwrap();
+        temp_file(name, bytes.get_ref())
+    }
+
+    #[test]
+    fn bmp_and_jpeg_decode_to_rgba8_by_header() {
+        for (name, format) in [
+            ("small.bmp", image::ImageFormat::Bmp),
+            ("small.jpg", image::ImageFormat::Jpeg),
+            // Named for a different format, only the header counts
+            ("jpeg.png", image::ImageFormat::Jpeg),
+        ] {
+            let path = encoded(name, format);
+            let result = decode_file(&path);
+            std::fs::remove_file(&path).unwrap();
+            let img = result.unwrap();
+            assert_eq!(img.dimensions(), (3, 2), "{name}");
+            assert_eq!(img.as_raw().len(), 3 * 2 * 4, "{name}");
+            assert!(img.pixels().all(|pixel| pixel[3] == 255), "{name}");
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::loading::Loading;
+use crate::texture::{self, SolidTexture, TextureError};
+use crate::{assets, Context};
+
+// Decodes and uploads each image file once, sprites sharing a spritesheet then
+// share one GPU texture
+#[derive(Default)]
+pub struct TextureCache {
+    textures: HashMap<PathBuf, Arc<wgpu::Texture>>,
+    // Keyed by name, kept apart so an embedded image never shadows a file
+    embedded: HashMap<&'static str, Arc<wgpu::Texture>>,
+    solid: Option<SolidTexture>,
+}
+
+impl TextureCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(
+        &mut self,
+        context: &Context,
+        path: impl AsRef<Path>,
+    ) -> Result<Arc<wgpu::Texture>, TextureError> {
+        let path = path.as_ref();
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(texture.clone());
+        }
+
+        let texture = Arc::new(texture::load_texture(context, path)?);
+        self.textures.insert(path.to_owned(), texture.clone());
+        Ok(texture)
+    }
+
+    // Created on first use, every bullet and particle shares it
+    pub fn solid(&mut self, context: &Context) -> SolidTexture {
+        self.solid
+            .get_or_insert_with(|| texture::create_solid_texture(context))
+            .clone()
+    }
+
+    // A file with this name in the assets dir overrides the bytes built into
+    // the binary
+    pub fn get_or_embedded(
+        &mut self,
+        context: &Context,
+        name: &'static str,
+        bytes: &[u8],
+    ) -> Result<Arc<wgpu::Texture>, TextureError> {
+        let path = assets::resolve(name);
+        if path.is_file() {
+            return self.get(context, path);
+        }
+        self.get_embedded(context, name, bytes)
+    }
+
+    // As get_or_embedded, but only the header is read here. The texture is
+    // blank until loading writes the pixels decoded on another thread.
+    pub fn get_or_embedded_in_background(
+        &mut self,
+        context: &Context,
+        name: &'static str,
+        bytes: &'static [u8],
+        loading: &mut Loading,
+    ) -> Result<Arc<wgpu::Texture>, TextureError> {
+        let path = assets::resolve(name);
+        if path.is_file() {
+            if let Some(texture) = self.textures.get(&path) {
+                return Ok(texture.clone());
+            }
+            let texture = blank_texture(context, texture::file_size(&path)?);
+            self.textures.insert(path.clone(), texture.clone());
+            loading.decode(texture.clone(), move || texture::decode_file(path));
+            return Ok(texture);
+        }
+
+        if let Some(texture) = self.embedded.get(name) {
+            return Ok(texture.clone());
+        }
+        let texture = blank_texture(context, texture::bytes_size(bytes)?);
+        self.embedded.insert(name, texture.clone());
+        loading.decode(texture.clone(), move || texture::decode_bytes(bytes));
+        Ok(texture)
+    }
+
+    fn get_embedded(
+        &mut self,
+        context: &Context,
+        name: &'static str,
+        bytes: &[u8],
+    ) -> Result<Arc<wgpu::Texture>, TextureError> {
+        if let Some(texture) = self.embedded.get(name) {
+            return Ok(texture.clone());
+        }
+
+        let texture = Arc::new(texture::load_texture_from_bytes(context, bytes)?);
+        self.embedded.insert(name, texture.clone());
+        Ok(texture)
+    }
+}
+
+fn blank_texture(context: &Context, (width, height): (u32, u32)) -> Arc<wgpu::Texture> {
+    let texels = vec![0; width as usize * height as usize * 4];
+    Arc::new(texture::create_rgba_texture(
+        context, width, height, &texels,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_texture_is_created_once() {
+        let Some(context) = crate::test_context() else {
+            return;
+        };
+        let mut textures = TextureCache::new();
+        let first = textures.solid(&context);
+        let second = textures.solid(&context);
+        assert!(Arc::ptr_eq(&first.texture, &second.texture));
+        assert!(Arc::ptr_eq(&first.sampler, &second.sampler));
+    }
+}
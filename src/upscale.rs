@@ -0,0 +1,366 @@
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::include_wgsl;
+use winit::dpi::PhysicalSize;
+
+use crate::renderer::{create_depth_view, create_msaa_view, LetterboxOptions, RenderTarget};
+use crate::Context;
+use crate::{WORLD_HEIGHT, WORLD_WIDTH};
+
+// Where a frame of the internal size lands in the window: centered at the largest
+// whole multiple that fits, so every texel covers the same number of pixels.
+// Windows smaller than one multiple get the frame scaled down to fit instead.
+// x, y, width, height in window pixels, y down.
+pub fn scaled_viewport(internal: PhysicalSize<u32>, window: PhysicalSize<u32>) -> [u32; 4] {
+    let (iw, ih) = (internal.width.max(1), internal.height.max(1));
+    let (ww, wh) = (window.width.max(1), window.height.max(1));
+
+    let scale = (ww / iw).min(wh / ih);
+    let (width, height) = if scale >= 1 {
+        (iw * scale, ih * scale)
+    } else {
+        let fit = (ww as f32 / iw as f32).min(wh as f32 / ih as f32);
+        (
+            ((iw as f32 * fit).round() as u32).clamp(1, ww),
+            ((ih as f32 * fit).round() as u32).clamp(1, wh),
+        )
+    };
+    [(ww - width) / 2, (wh - height) / 2, width, height]
+}
+
+// The largest size with the world's aspect ratio that fits in the window, the
+// internal size when only blurred bars need an offscreen frame
+pub fn world_fit(window: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let (ww, wh) = (window.width.max(1) as f32, window.height.max(1) as f32);
+    let scale = (ww / WORLD_WIDTH).min(wh / WORLD_HEIGHT);
+    PhysicalSize::new(
+        ((WORLD_WIDTH * scale) as u32).max(1),
+        ((WORLD_HEIGHT * scale) as u32).max(1),
+    )
+}
+
+// Blurred bars sample this far around the nearest frame edge, in frame UVs
+const BLUR_RADIUS: f32 = 0.03;
+// And are darkened to this fraction of the frame's brightness
+const BLUR_BRIGHTNESS: f32 = 0.35;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+struct Params {
+    dest: [f32; 4],
+    bar_color: [f32; 4],
+    // x 1 when blurring, y radius, z brightness
+    blur: [f32; 4],
+}
+
+impl Params {
+    fn new(internal: PhysicalSize<u32>, window: PhysicalSize<u32>, bars: LetterboxOptions) -> Self {
+        let [x, y, width, height] = scaled_viewport(internal, window);
+        let color = bars.bar_color;
+        let blur = if bars.blur {
+            [1.0, BLUR_RADIUS, BLUR_BRIGHTNESS, 0.0]
+        } else {
+            [0.0; 4]
+        };
+        Self {
+            dest: [x as f32, y as f32, width as f32, height as f32],
+            bar_color: [color.r, color.g, color.b, color.a].map(|c| c as f32),
+            blur,
+        }
+    }
+}
+
+// The scene target of a fixed internal resolution and the pass that scales it
+// up into the window, see RendererOptions::internal_resolution
+pub struct Upscaler {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    depth: wgpu::TextureView,
+    msaa: Option<wgpu::TextureView>,
+    pipeline: wgpu::RenderPipeline,
+    params_buf: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    window: PhysicalSize<u32>,
+    bars: LetterboxOptions,
+}
+
+impl Upscaler {
+    pub fn new(
+        context: &Context,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        internal: PhysicalSize<u32>,
+        window: PhysicalSize<u32>,
+        bars: LetterboxOptions,
+    ) -> Self {
+        let device = &context.device;
+        let width = internal.width.max(1);
+        let height = internal.height.max(1);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Internal Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = create_depth_view(device, width, height, sample_count);
+        let msaa = create_msaa_view(device, format, width, height, sample_count);
+
+        // Nearest, a whole multiple shouldn't blur the texels
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+        // Linear, for the blurred bars
+        let blur_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let params_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Upscale Params"),
+            size: size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                // Internal frame
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Params
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Blur Sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&blur_sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(include_wgsl!("upscale.wgsl"));
+        // Draws straight into the window's resolved view, so never multisampled
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Upscale Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let upscaler = Self {
+            texture,
+            view,
+            depth,
+            msaa,
+            pipeline,
+            params_buf,
+            bind_group,
+            window,
+            bars,
+        };
+        upscaler.write_params(context);
+        upscaler
+    }
+
+    pub fn internal_size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.texture.width(), self.texture.height())
+    }
+
+    pub fn window_size(&self) -> PhysicalSize<u32> {
+        self.window
+    }
+
+    pub fn set_window_size(&mut self, context: &Context, window: PhysicalSize<u32>) {
+        self.window = window;
+        self.write_params(context);
+    }
+
+    fn write_params(&self, context: &Context) {
+        let params = Params::new(self.internal_size(), self.window, self.bars);
+        context
+            .queue
+            .write_buffer(&self.params_buf, 0, bytemuck::bytes_of(&params));
+    }
+
+    // What the scene passes draw into instead of the window
+    pub fn render_target(&self) -> RenderTarget<'_> {
+        RenderTarget {
+            color: &self.view,
+            depth: &self.depth,
+            msaa: self.msaa.as_ref(),
+        }
+    }
+
+    // Fills the whole of view, the scaled frame in the middle and bars around it
+    pub fn record_blit(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // Every pixel is written, nothing to clear
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_scale_is_centered() {
+        let internal = PhysicalSize::new(458, 380);
+        // 3x fits horizontally and vertically, 4x doesn't fit vertically
+        assert_eq!(
+            scaled_viewport(internal, PhysicalSize::new(1920, 1200)),
+            [273, 30, 1374, 1140]
+        );
+        assert_eq!(
+            scaled_viewport(internal, PhysicalSize::new(458, 380)),
+            [0, 0, 458, 380]
+        );
+    }
+
+    #[test]
+    fn configured_bar_color_is_used_without_blur() {
+        let bars = LetterboxOptions {
+            bar_color: wgpu::Color {
+                r: 0.25,
+                g: 0.5,
+                b: 0.75,
+                a: 1.0,
+            },
+            blur: false,
+        };
+        let params = Params::new(
+            PhysicalSize::new(458, 380),
+            PhysicalSize::new(1000, 380),
+            bars,
+        );
+        assert_eq!(params.bar_color, [0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(params.blur[0], 0.0);
+
+        let blurred = LetterboxOptions { blur: true, ..bars };
+        let params = Params::new(
+            PhysicalSize::new(458, 380),
+            PhysicalSize::new(1000, 380),
+            blurred,
+        );
+        assert_eq!(params.blur[0], 1.0);
+    }
+
+    #[test]
+    fn world_fit_keeps_the_world_aspect() {
+        assert_eq!(
+            world_fit(PhysicalSize::new(1000, 380)),
+            PhysicalSize::new(458, 380)
+        );
+        assert_eq!(
+            world_fit(PhysicalSize::new(458, 1000)),
+            PhysicalSize::new(458, 380)
+        );
+    }
+
+    #[test]
+    fn small_windows_scale_down_to_fit() {
+        let viewport = scaled_viewport(PhysicalSize::new(458, 380), PhysicalSize::new(229, 400));
+        assert_eq!(viewport, [0, 105, 229, 190]);
+    }
+}
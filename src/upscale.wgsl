@@ -0,0 +1,53 @@
+struct Params {
+    // x, y, width, height of the scaled frame in target pixels, y down
+    dest: vec4f,
+    bar_color: vec4f,
+    // x 1 when the bars show the blurred frame edges, y radius in UVs, z brightness
+    blur: vec4f,
+}
+
+@group(0)
+@binding(0)
+var frame: texture_2d<f32>;
+@group(0)
+@binding(1)
+var samp: sampler;
+@group(0)
+@binding(2)
+var<uniform> params: Params;
+@group(0)
+@binding(3)
+var blur_samp: sampler;
+
+// One triangle covering the whole target, no vertex buffer
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4f {
+    let corner = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4f(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Box blur of the frame around its edge texel closest to uv
+fn blurred_edge(uv: vec2f) -> vec4f {
+    let edge = clamp(uv, vec2f(0.0), vec2f(1.0));
+    var sum = vec4f(0.0);
+    for (var x = -2; x <= 2; x++) {
+        for (var y = -2; y <= 2; y++) {
+            let offset = vec2f(f32(x), f32(y)) * params.blur.y / 2.0;
+            sum += textureSampleLevel(frame, blur_samp, edge + offset, 0.0);
+        }
+    }
+    return vec4f(sum.rgb / 25.0 * params.blur.z, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4f) -> @location(0) vec4f {
+    let uv = (pos.xy - params.dest.xy) / params.dest.zw;
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) {
+        if params.blur.x > 0.5 {
+            return blurred_edge(uv);
+        }
+        return params.bar_color;
+    }
+    // Level 0 explicitly, implicit derivatives aren't allowed after the branch
+    return textureSampleLevel(frame, samp, uv, 0.0);
+}
@@ -0,0 +1,101 @@
+use winit::monitor::{MonitorHandle, VideoMode};
+use winit::window::{Fullscreen, Window};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    ExclusiveFullscreen,
+}
+
+impl WindowMode {
+    pub fn next(self) -> Self {
+        match self {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            WindowMode::BorderlessFullscreen => WindowMode::ExclusiveFullscreen,
+            WindowMode::ExclusiveFullscreen => WindowMode::Windowed,
+        }
+    }
+
+    // Back to windowed from either fullscreen mode
+    pub fn toggle_fullscreen(self) -> Self {
+        match self {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            WindowMode::BorderlessFullscreen | WindowMode::ExclusiveFullscreen => {
+                WindowMode::Windowed
+            }
+        }
+    }
+
+    pub fn decorations(self) -> bool {
+        self == WindowMode::Windowed
+    }
+
+    // Borderless with no monitor lets winit pick the current one. Exclusive
+    // mode falls back to borderless when the monitor reports no video modes
+    pub fn fullscreen(self, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+        match self {
+            WindowMode::Windowed => None,
+            WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(monitor)),
+            WindowMode::ExclusiveFullscreen => match monitor.as_ref().and_then(best_video_mode) {
+                Some(mode) => Some(Fullscreen::Exclusive(mode)),
+                None => Some(Fullscreen::Borderless(monitor)),
+            },
+        }
+    }
+
+    pub fn apply(self, window: &Window) {
+        window.set_fullscreen(self.fullscreen(window.current_monitor()));
+        window.set_decorations(self.decorations());
+    }
+}
+
+fn best_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    monitor.video_modes().max_by_key(|mode| {
+        let size = mode.size();
+        (
+            size.width * size.height,
+            mode.bit_depth(),
+            mode.refresh_rate_millihertz(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_windowed_mode_has_decorations() {
+        assert!(WindowMode::Windowed.decorations());
+        assert!(!WindowMode::BorderlessFullscreen.decorations());
+        assert!(!WindowMode::ExclusiveFullscreen.decorations());
+    }
+
+    #[test]
+    fn fullscreen_without_a_monitor_is_borderless() {
+        assert_eq!(WindowMode::Windowed.fullscreen(None), None);
+        assert_eq!(
+            WindowMode::BorderlessFullscreen.fullscreen(None),
+            Some(Fullscreen::Borderless(None))
+        );
+        assert_eq!(
+            WindowMode::ExclusiveFullscreen.fullscreen(None),
+            Some(Fullscreen::Borderless(None))
+        );
+    }
+
+    #[test]
+    fn modes_cycle_and_toggle() {
+        let mut mode = WindowMode::default();
+        for _ in 0..3 {
+            mode = mode.next();
+        }
+        assert_eq!(mode, WindowMode::Windowed);
+        assert_eq!(
+            WindowMode::ExclusiveFullscreen.toggle_fullscreen(),
+            WindowMode::Windowed
+        );
+    }
+}